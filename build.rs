@@ -1,5 +1,5 @@
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 fn main() {
     // Skip binding generation on docs.rs
@@ -15,34 +15,38 @@ fn main() {
     // link hipBLAS
     println!("cargo:rustc-link-lib=dylib=hipblas");
 
+    // link hipRTC for runtime compilation
+    println!("cargo:rustc-link-lib=dylib=hiprtc");
+
     // Tell cargo when to rerun this build script
     println!("cargo:rerun-if-changed=src/core/sys/wrapper.h");
     println!("cargo:rerun-if-changed=build.rs");
 
     // Set up HIP paths - making them configurable via environment variables
-    let rocm_path = env::var("ROCM_PATH").unwrap_or_else(|_| "/opt/rocm".to_string());
-    let hip_lib_path = format!("{}/lib", rocm_path);
-    let hip_include_path = format!("{}/include", rocm_path);
-    let hipcc_path = format!("{}/bin/hipcc", rocm_path);
+    println!("cargo:rerun-if-env-changed={}", hip_rs_build::ROCM_PATH_ENV);
+    let rocm_path = hip_rs_build::rocm_path();
+    let hip_lib_path = rocm_path.join("lib");
+    let hip_include_path = rocm_path.join("include");
+    let hipcc_path = rocm_path.join("bin").join("hipcc");
 
     // Configure library search paths and linking
-    println!("cargo:rustc-link-search=native={}", hip_lib_path);
+    println!("cargo:rustc-link-search=native={}", hip_lib_path.display());
     println!("cargo:rustc-link-lib=dylib=amdhip64");
 
     // Tell cargo to use hipcc as the linker, whether we're testing or not
     if env::var("CARGO_CFG_TARGET_OS").unwrap() == "linux" {
-        println!("cargo:rustc-linker={}", hipcc_path);
+        println!("cargo:rustc-linker={}", hipcc_path.display());
     }
 
     // Generate bindings
     generate_bindings(&hip_include_path);
 }
 
-fn generate_bindings(hip_include_path: &str) {
+fn generate_bindings(hip_include_path: &Path) {
     let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
     let bindings = bindgen::Builder::default()
         .header("src/sys/wrapper.h")
-        .clang_arg(&format!("-I{}", hip_include_path))
+        .clang_arg(format!("-I{}", hip_include_path.display()))
         .clang_arg("-D__HIP_PLATFORM_AMD__")
         // Blocklist problematic items
         .blocklist_item("FP_INT_.*")
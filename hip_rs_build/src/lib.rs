@@ -0,0 +1,629 @@
+//! Helpers for compiling HIP kernels from a `build.rs`.
+//!
+//! [`Build`] compiles a list of `.hip` files for one or more GPU targets
+//! into a single offload bundle in `OUT_DIR`, which `hip_rs::include_kernels!`
+//! then embeds in the crate.
+//!
+//! # Examples
+//! ```no_run
+//! // In build.rs
+//! hip_rs_build::Build::new()
+//!     .with_file("kernels/vector_add.hip")
+//!     .with_targets(["gfx90a", "gfx942"])
+//!     .with_output_name("kernels")
+//!     .compile()
+//!     .unwrap();
+//! ```
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+/// Environment variable pointing at the ROCm installation.
+pub const ROCM_PATH_ENV: &str = "ROCM_PATH";
+
+/// ROCm installation used when [`ROCM_PATH_ENV`] is not set.
+pub const DEFAULT_ROCM_PATH: &str = "/opt/rocm";
+
+/// Environment variable with comma separated GPU targets, used when
+/// [`Build::with_target`] was never called.
+pub const OFFLOAD_ARCH_ENV: &str = "HIP_RS_OFFLOAD_ARCH";
+
+/// File extension of the offload bundles written by [`Build::compile`].
+pub const BUNDLE_EXTENSION: &str = "hipfb";
+
+/// Returns the ROCm installation directory.
+///
+/// # Returns
+/// * The value of `ROCM_PATH`, or `/opt/rocm` if it is not set
+pub fn rocm_path() -> PathBuf {
+    env::var_os(ROCM_PATH_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_ROCM_PATH))
+}
+
+/// The compiler driver used by [`Build`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compiler {
+    /// `bin/hipcc` in the ROCm installation
+    #[default]
+    Hipcc,
+    /// `llvm/bin/clang++` in the ROCm installation
+    Clang,
+}
+
+/// Compiles `.hip` files into an offload bundle.
+///
+/// All files are compiled as one translation unit, so kernels and device
+/// functions must have distinct names across files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Build {
+    files: Vec<PathBuf>,
+    targets: Vec<String>,
+    compiler: Compiler,
+    rocm_path: Option<PathBuf>,
+    include_dirs: Vec<PathBuf>,
+    defines: Vec<(String, String)>,
+    flags: Vec<String>,
+    optimization_level: u32,
+    output_name: String,
+    out_dir: Option<PathBuf>,
+}
+
+impl Default for Build {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            targets: Vec::new(),
+            compiler: Compiler::default(),
+            rocm_path: None,
+            include_dirs: Vec::new(),
+            defines: Vec::new(),
+            flags: Vec::new(),
+            optimization_level: 3,
+            output_name: "kernels".to_string(),
+            out_dir: None,
+        }
+    }
+}
+
+impl Build {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a source file, relative to the package root.
+    pub fn with_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.files.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn with_files<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        self.files
+            .extend(paths.into_iter().map(|path| path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Adds a GPU target ID, e.g. `gfx90a` or `gfx90a:xnack+`.
+    pub fn with_target(mut self, target: &str) -> Self {
+        self.targets.push(target.to_string());
+        self
+    }
+
+    pub fn with_targets<I, S>(mut self, targets: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.targets.extend(
+            targets
+                .into_iter()
+                .map(|target| target.as_ref().to_string()),
+        );
+        self
+    }
+
+    pub fn with_compiler(mut self, compiler: Compiler) -> Self {
+        self.compiler = compiler;
+        self
+    }
+
+    /// Overrides the ROCm installation found by [`rocm_path`].
+    pub fn with_rocm_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.rocm_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn with_include_dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.include_dirs.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Defines a preprocessor macro. An empty value defines it without one.
+    pub fn with_define(mut self, name: &str, value: &str) -> Self {
+        self.defines.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Passes an extra flag to the compiler.
+    pub fn with_flag(mut self, flag: &str) -> Self {
+        self.flags.push(flag.to_string());
+        self
+    }
+
+    /// Sets the optimization level, from 0 to 3. Defaults to 3.
+    pub fn with_optimization_level(mut self, level: u32) -> Self {
+        self.optimization_level = level;
+        self
+    }
+
+    /// Sets the bundle file name, without extension. Defaults to `kernels`.
+    pub fn with_output_name(mut self, name: &str) -> Self {
+        self.output_name = name.to_string();
+        self
+    }
+
+    /// Overrides the output directory, which defaults to `OUT_DIR`.
+    pub fn with_out_dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.out_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn targets(&self) -> &[String] {
+        &self.targets
+    }
+
+    pub fn output_name(&self) -> &str {
+        &self.output_name
+    }
+
+    /// Returns the path of the compiler driver that will be invoked.
+    pub fn compiler_path(&self) -> PathBuf {
+        let rocm = self.rocm_path.clone().unwrap_or_else(rocm_path);
+        match self.compiler {
+            Compiler::Hipcc => rocm.join("bin").join("hipcc"),
+            Compiler::Clang => rocm.join("llvm").join("bin").join("clang++"),
+        }
+    }
+
+    /// Compiles the sources and prints the `cargo:` directives that rerun
+    /// the build script when a source or the environment changes.
+    ///
+    /// # Returns
+    /// * `Ok(PathBuf)` - Path of the offload bundle, `<out_dir>/<output_name>.hipfb`
+    /// * `Err(BuildError)` - If the configuration is invalid or the compiler fails
+    pub fn compile(&self) -> Result<PathBuf, BuildError> {
+        println!("cargo:rerun-if-env-changed={}", ROCM_PATH_ENV);
+        println!("cargo:rerun-if-env-changed={}", OFFLOAD_ARCH_ENV);
+        for path in self.files.iter().chain(&self.include_dirs) {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+
+        let targets = self.resolve_targets()?;
+        self.validate()?;
+        let out_dir = match &self.out_dir {
+            Some(dir) => dir.clone(),
+            None => env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or(BuildError::MissingOutDir)?,
+        };
+
+        let files = self
+            .files
+            .iter()
+            .map(|path| {
+                fs::canonicalize(path).map_err(|source| BuildError::Io {
+                    path: path.clone(),
+                    source,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let input = out_dir.join(format!("{}.hip", self.output_name));
+        fs::write(&input, wrapper_source(&files)).map_err(|source| BuildError::Io {
+            path: input.clone(),
+            source,
+        })?;
+
+        let output = out_dir.join(format!("{}.{}", self.output_name, BUNDLE_EXTENSION));
+        let compiler = self.compiler_path();
+        let args = self.args(&targets, &input, &output);
+        let mut command = Command::new(&compiler);
+        command.args(&args);
+        if let Some(rocm) = &self.rocm_path {
+            command.env(ROCM_PATH_ENV, rocm);
+        }
+        let result = command.output().map_err(|source| match source.kind() {
+            io::ErrorKind::NotFound => BuildError::CompilerNotFound(compiler.clone()),
+            _ => BuildError::Io {
+                path: compiler.clone(),
+                source,
+            },
+        })?;
+        if !result.status.success() {
+            return Err(BuildError::CompilerFailed {
+                command: command_line(&compiler, &args),
+                status: result.status,
+                stderr: String::from_utf8_lossy(&result.stderr).into_owned(),
+            });
+        }
+        Ok(output)
+    }
+
+    /// Returns the configured targets, falling back to `HIP_RS_OFFLOAD_ARCH`.
+    fn resolve_targets(&self) -> Result<Vec<String>, BuildError> {
+        let targets = if self.targets.is_empty() {
+            parse_targets(&env::var(OFFLOAD_ARCH_ENV).unwrap_or_default())
+        } else {
+            self.targets.clone()
+        };
+        if targets.is_empty() {
+            return Err(BuildError::NoTargets);
+        }
+        if let Some(target) = targets.iter().find(|target| !is_valid_target(target)) {
+            return Err(BuildError::InvalidTarget(target.clone()));
+        }
+        Ok(targets)
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        if self.files.is_empty() {
+            return Err(BuildError::NoSources);
+        }
+        let valid_name = !self.output_name.is_empty()
+            && self
+                .output_name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_name {
+            return Err(BuildError::InvalidOutputName(self.output_name.clone()));
+        }
+        Ok(())
+    }
+
+    /// Builds the compiler arguments for a device-only, bundled compile.
+    fn args(&self, targets: &[String], input: &Path, output: &Path) -> Vec<OsString> {
+        let mut args: Vec<OsString> = match self.compiler {
+            Compiler::Hipcc => vec!["--genco".into()],
+            Compiler::Clang => {
+                let rocm = self.rocm_path.clone().unwrap_or_else(rocm_path);
+                let mut rocm_arg = OsString::from("--rocm-path=");
+                rocm_arg.push(rocm);
+                vec![
+                    "-x".into(),
+                    "hip".into(),
+                    "--cuda-device-only".into(),
+                    "-c".into(),
+                    rocm_arg,
+                ]
+            }
+        };
+        args.push("--gpu-bundle-output".into());
+        args.extend(
+            targets
+                .iter()
+                .map(|target| format!("--offload-arch={}", target).into()),
+        );
+        args.push(format!("-O{}", self.optimization_level).into());
+        for dir in &self.include_dirs {
+            let mut arg = OsString::from("-I");
+            arg.push(dir);
+            args.push(arg);
+        }
+        for (name, value) in &self.defines {
+            let define = if value.is_empty() {
+                format!("-D{}", name)
+            } else {
+                format!("-D{}={}", name, value)
+            };
+            args.push(define.into());
+        }
+        args.extend(self.flags.iter().map(OsString::from));
+        args.push("-o".into());
+        args.push(output.into());
+        args.push(input.into());
+        args
+    }
+}
+
+/// Errors returned by [`Build::compile`].
+#[derive(Debug)]
+pub enum BuildError {
+    /// No source files were added
+    NoSources,
+    /// No targets were added and `HIP_RS_OFFLOAD_ARCH` is unset or empty
+    NoTargets,
+    /// A target is not a `gfx` target ID
+    InvalidTarget(String),
+    /// The output name is empty or not a plain file name
+    InvalidOutputName(String),
+    /// `OUT_DIR` is not set, i.e. not running in a build script
+    MissingOutDir,
+    /// The compiler driver does not exist
+    CompilerNotFound(PathBuf),
+    /// A file could not be read or written
+    Io { path: PathBuf, source: io::Error },
+    /// The compiler exited with an error
+    CompilerFailed {
+        command: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::NoSources => write!(f, "no .hip files to compile"),
+            BuildError::NoTargets => write!(
+                f,
+                "no GPU targets, add one with Build::with_target or set {}",
+                OFFLOAD_ARCH_ENV
+            ),
+            BuildError::InvalidTarget(target) => write!(f, "invalid GPU target '{}'", target),
+            BuildError::InvalidOutputName(name) => write!(f, "invalid output name '{}'", name),
+            BuildError::MissingOutDir => write!(f, "OUT_DIR is not set"),
+            BuildError::CompilerNotFound(path) => write!(
+                f,
+                "compiler not found at {}, set {} to the ROCm installation",
+                path.display(),
+                ROCM_PATH_ENV
+            ),
+            BuildError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            BuildError::CompilerFailed {
+                command,
+                status,
+                stderr,
+            } => write!(
+                f,
+                "`{}` failed ({}):\n{}",
+                command,
+                status,
+                stderr.trim_end()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BuildError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Splits a comma separated target list, ignoring whitespace and empty entries.
+fn parse_targets(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|target| !target.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Checks the shape of a target ID such as `gfx90a:sramecc+:xnack-`.
+fn is_valid_target(target: &str) -> bool {
+    let mut parts = target.split(':');
+    let processor = parts.next().unwrap_or_default();
+    let valid_processor = processor.len() > 3
+        && processor.starts_with("gfx")
+        && processor[3..].chars().all(|c| c.is_ascii_alphanumeric());
+    valid_processor
+        && parts.all(|feature| {
+            let name = feature.trim_end_matches(['+', '-']);
+            feature.len() == name.len() + 1 && !name.is_empty()
+        })
+}
+
+/// Generates a translation unit including every source file.
+fn wrapper_source(files: &[PathBuf]) -> String {
+    let mut source = String::from("// Generated by hip_rs_build\n");
+    for file in files {
+        let path = file
+            .display()
+            .to_string()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        source.push_str(&format!("#include \"{}\"\n", path));
+    }
+    source
+}
+
+fn command_line(compiler: &Path, args: &[OsString]) -> String {
+    std::iter::once(compiler.as_os_str())
+        .chain(args.iter().map(OsString::as_os_str))
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[OsString]) -> Vec<String> {
+        args.iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_hipcc_args() {
+        let build = Build::new()
+            .with_file("a.hip")
+            .with_include_dir("include")
+            .with_define("BLOCK", "256")
+            .with_define("FAST_MATH", "")
+            .with_flag("-ffast-math")
+            .with_optimization_level(2);
+        let targets = vec!["gfx90a".to_string(), "gfx942".to_string()];
+        let args = build.args(&targets, Path::new("in.hip"), Path::new("out.hipfb"));
+        assert_eq!(
+            strings(&args),
+            vec![
+                "--genco",
+                "--gpu-bundle-output",
+                "--offload-arch=gfx90a",
+                "--offload-arch=gfx942",
+                "-O2",
+                "-Iinclude",
+                "-DBLOCK=256",
+                "-DFAST_MATH",
+                "-ffast-math",
+                "-o",
+                "out.hipfb",
+                "in.hip",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clang_args() {
+        let build = Build::new()
+            .with_compiler(Compiler::Clang)
+            .with_rocm_path("/rocm");
+        assert_eq!(
+            build.compiler_path(),
+            PathBuf::from("/rocm/llvm/bin/clang++")
+        );
+        let targets = vec!["gfx1100".to_string()];
+        let args = build.args(&targets, Path::new("in.hip"), Path::new("out.hipfb"));
+        assert_eq!(
+            strings(&args),
+            vec![
+                "-x",
+                "hip",
+                "--cuda-device-only",
+                "-c",
+                "--rocm-path=/rocm",
+                "--gpu-bundle-output",
+                "--offload-arch=gfx1100",
+                "-O3",
+                "-o",
+                "out.hipfb",
+                "in.hip",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compiler_path() {
+        let build = Build::new().with_rocm_path("/opt/rocm-6.2.0");
+        assert_eq!(
+            build.compiler_path(),
+            PathBuf::from("/opt/rocm-6.2.0/bin/hipcc")
+        );
+    }
+
+    #[test]
+    fn test_parse_targets() {
+        assert_eq!(
+            parse_targets(" gfx90a:xnack+ ,gfx942,, "),
+            vec!["gfx90a:xnack+", "gfx942"]
+        );
+        assert!(parse_targets("").is_empty());
+    }
+
+    #[test]
+    fn test_valid_targets() {
+        for target in [
+            "gfx90a",
+            "gfx1100",
+            "gfx90a:xnack-",
+            "gfx90a:sramecc+:xnack+",
+        ] {
+            assert!(is_valid_target(target), "{}", target);
+        }
+        for target in [
+            "",
+            "gfx",
+            "sm_80",
+            "gfx90a:",
+            "gfx90a:xnack",
+            "gfx90a:+",
+            "gfx 90a",
+        ] {
+            assert!(!is_valid_target(target), "{}", target);
+        }
+    }
+
+    #[test]
+    fn test_wrapper_source() {
+        let files = [
+            PathBuf::from("/src/a.hip"),
+            PathBuf::from("/src/b \"x\".hip"),
+        ];
+        assert_eq!(
+            wrapper_source(&files),
+            "// Generated by hip_rs_build\n#include \"/src/a.hip\"\n#include \"/src/b \\\"x\\\".hip\"\n"
+        );
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let out_dir = env::temp_dir();
+        let result = Build::new()
+            .with_target("gfx90a")
+            .with_out_dir(&out_dir)
+            .compile();
+        assert!(matches!(result, Err(BuildError::NoSources)));
+
+        let result = Build::new()
+            .with_file("a.hip")
+            .with_target("sm_80")
+            .with_out_dir(&out_dir)
+            .compile();
+        assert!(matches!(result, Err(BuildError::InvalidTarget(target)) if target == "sm_80"));
+
+        let result = Build::new()
+            .with_file("a.hip")
+            .with_target("gfx90a")
+            .with_output_name("../kernels")
+            .with_out_dir(&out_dir)
+            .compile();
+        assert!(matches!(result, Err(BuildError::InvalidOutputName(_))));
+
+        let result = Build::new()
+            .with_file("/nonexistent/a.hip")
+            .with_target("gfx90a")
+            .with_out_dir(&out_dir)
+            .compile();
+        match result {
+            Err(error @ BuildError::Io { .. }) => {
+                assert!(error.to_string().starts_with("/nonexistent/a.hip: "))
+            }
+            other => panic!("expected an I/O error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_missing_compiler() {
+        let dir = env::temp_dir().join(format!("hip_rs_build_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("empty.hip");
+        fs::write(&source, "").unwrap();
+
+        let result = Build::new()
+            .with_file(&source)
+            .with_target("gfx90a")
+            .with_rocm_path(dir.join("rocm"))
+            .with_out_dir(&dir)
+            .compile();
+        assert!(matches!(result, Err(BuildError::CompilerNotFound(_))));
+        assert!(dir.join("kernels.hip").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields};
+
+/// Derives `hip_rs::DeviceCopy` for a struct.
+///
+/// The struct must be `#[repr(C)]` or `#[repr(transparent)]` so its layout
+/// matches the layout device code expects, and every field must implement
+/// `DeviceCopy`. Generic parameters are required to be `DeviceCopy` as well.
+///
+/// # Examples
+/// ```
+/// use hip_rs::DeviceCopy;
+///
+/// #[derive(Clone, Copy, DeviceCopy)]
+/// #[repr(C)]
+/// struct Particle {
+///     position: [f32; 3],
+///     mass: f32,
+/// }
+/// ```
+///
+/// Fields that cannot be copied to the device are rejected:
+/// ```compile_fail
+/// use hip_rs::DeviceCopy;
+///
+/// #[derive(Clone, Copy, DeviceCopy)]
+/// #[repr(C)]
+/// struct Flagged {
+///     value: f32,
+///     valid: bool,
+/// }
+/// ```
+///
+/// So are structs without a defined layout:
+/// ```compile_fail
+/// use hip_rs::DeviceCopy;
+///
+/// #[derive(Clone, Copy, DeviceCopy)]
+/// struct Point {
+///     x: f32,
+///     y: f32,
+/// }
+/// ```
+#[proc_macro_derive(DeviceCopy)]
+pub fn derive_device_copy(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_device_copy(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_device_copy(mut input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
+            Fields::Unit => Vec::new(),
+        },
+        Data::Enum(_) | Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "DeviceCopy can only be derived for structs",
+            ))
+        }
+    };
+
+    if !has_defined_layout(&input)? {
+        return Err(Error::new(
+            input.ident.span(),
+            "DeviceCopy requires #[repr(C)] or #[repr(transparent)]",
+        ));
+    }
+
+    // Every field must be DeviceCopy, which also rejects non-generic structs
+    // with fields like `String` at the impl
+    let field_types: Vec<_> = fields.iter().map(|field| field.ty.clone()).collect();
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::hip_rs::DeviceCopy));
+    }
+    let where_clause = input.generics.make_where_clause();
+    for ty in &field_types {
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: ::hip_rs::DeviceCopy));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        unsafe impl #impl_generics ::hip_rs::DeviceCopy for #name #type_generics #where_clause {}
+    })
+}
+
+/// Returns true if the struct has `#[repr(C)]` or `#[repr(transparent)]`,
+/// possibly combined with other representation hints like `align(N)`.
+fn has_defined_layout(input: &DeriveInput) -> syn::Result<bool> {
+    let mut defined = false;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("repr"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") || meta.path.is_ident("transparent") {
+                defined = true;
+            } else if meta.input.peek(syn::token::Paren) {
+                // Skip the arguments of hints like align(N) and packed(N)
+                let content;
+                syn::parenthesized!(content in meta.input);
+                content.parse::<proc_macro2::TokenStream>()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(defined)
+}
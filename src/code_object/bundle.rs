@@ -0,0 +1,475 @@
+use super::elf::{check_extents, Elf};
+use super::metadata::CodeObject;
+use super::result::{CodeObjectError, CodeObjectResult};
+use super::target::TargetId;
+
+const BUNDLE_MAGIC: &[u8] = b"__CLANG_OFFLOAD_BUNDLE__";
+const COMPRESSED_MAGIC: &[u8] = b"CCOB";
+
+/// Triple of entries holding AMDGPU ELF code objects
+const AMDGPU_TRIPLE: &str = "amdgcn-amd-amdhsa";
+
+/// Size of an entry header without its ID: offset, size and ID length
+const ENTRY_HEADER_SIZE: usize = 24;
+
+/// A clang offload bundle, as produced by `hipcc --genco` or `clang-offload-bundler`.
+///
+/// The bundle holds one code object per GPU target, and usually an empty
+/// host entry. Parsing borrows the bundle, so no code is copied.
+///
+/// # Examples
+/// ```no_run
+/// use hip_rs::OffloadBundle;
+///
+/// let data = std::fs::read("kernels.hipfb").unwrap();
+/// let bundle = OffloadBundle::parse(&data).unwrap();
+/// for entry in bundle.device_entries() {
+///     let code_object = entry.code_object().unwrap();
+///     for kernel in &code_object.kernels {
+///         println!("{}: {} VGPRs", kernel.name, kernel.vgpr_count);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffloadBundle<'a> {
+    entries: Vec<BundleEntry<'a>>,
+}
+
+impl<'a> OffloadBundle<'a> {
+    /// Checks whether the data starts with the offload bundle magic.
+    pub fn is_bundle(data: &[u8]) -> bool {
+        data.starts_with(BUNDLE_MAGIC)
+    }
+
+    /// Parses the bundle header and entry table.
+    ///
+    /// # Arguments
+    /// * `data` - The complete bundle
+    ///
+    /// # Returns
+    /// * `Ok(OffloadBundle)` - The bundle, borrowing `data`
+    /// * `Err(CodeObjectError)` - If the data is not an uncompressed bundle or an entry lies outside it
+    pub fn parse(data: &'a [u8]) -> CodeObjectResult<Self> {
+        if data.starts_with(COMPRESSED_MAGIC) {
+            return Err(CodeObjectError::CompressedBundle);
+        }
+        if !Self::is_bundle(data) {
+            return Err(CodeObjectError::NotABundle);
+        }
+
+        let mut reader = Reader {
+            data,
+            position: BUNDLE_MAGIC.len(),
+        };
+        let count = reader.u64()?;
+        if count > data.len() / ENTRY_HEADER_SIZE {
+            return Err(invalid_bundle("entry count exceeds the bundle size"));
+        }
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (offset, size, id_len) = (reader.u64()?, reader.u64()?, reader.u64()?);
+            let id = std::str::from_utf8(reader.bytes(id_len)?)
+                .map_err(|_| invalid_bundle("entry ID is not UTF-8"))?;
+            let data = offset
+                .checked_add(size)
+                .and_then(|end| data.get(offset..end))
+                .ok_or_else(|| {
+                    CodeObjectError::InvalidBundle(format!(
+                        "entry '{}' extends past the end of the bundle",
+                        id
+                    ))
+                })?;
+            entries.push(BundleEntry { id, data });
+        }
+        Ok(Self { entries })
+    }
+
+    /// Returns every entry, including the host entry.
+    pub fn entries(&self) -> &[BundleEntry<'a>] {
+        &self.entries
+    }
+
+    /// Returns the entries holding device code.
+    pub fn device_entries(&self) -> impl Iterator<Item = &BundleEntry<'a>> {
+        self.entries.iter().filter(|entry| !entry.is_host())
+    }
+
+    /// Returns the target IDs of the device entries, e.g. `gfx90a:xnack-`.
+    pub fn targets(&self) -> Vec<&'a str> {
+        self.device_entries()
+            .filter_map(|entry| entry.target_id())
+            .collect()
+    }
+
+    /// Picks the entry that best matches a device.
+    ///
+    /// Among compatible entries, the one setting the most features wins,
+    /// so `gfx90a:xnack-` is preferred over `gfx90a` on a device with XNACK
+    /// off. Ties go to the first entry.
+    ///
+    /// # Arguments
+    /// * `device` - The device target ID
+    ///
+    /// # Returns
+    /// * `Ok(BundleEntry)` - The best compatible entry
+    /// * `Err(CodeObjectError)` - If no entry runs on the device, listing the available targets
+    pub fn select(&self, device: &TargetId) -> CodeObjectResult<BundleEntry<'a>> {
+        let mut best: Option<(usize, BundleEntry<'a>)> = None;
+        for entry in self.device_entries() {
+            let Some(target) = entry.target_id().and_then(|id| TargetId::parse(id).ok()) else {
+                continue;
+            };
+            let specificity = target.specificity();
+            if target.is_compatible_with(device)
+                && best.is_none_or(|(best_specificity, _)| specificity > best_specificity)
+            {
+                best = Some((specificity, *entry));
+            }
+        }
+        best.map(|(_, entry)| entry)
+            .ok_or_else(|| CodeObjectError::NoCompatibleTarget {
+                device: device.to_string(),
+                available: self.targets().into_iter().map(str::to_string).collect(),
+            })
+    }
+}
+
+/// Returns the code object in `image` to load on a device.
+///
+/// Offload bundles are narrowed to their best entry with
+/// [`OffloadBundle::select`]. A plain code object is returned as is if its
+/// ELF flags are compatible with the device.
+///
+/// # Arguments
+/// * `image` - An offload bundle or AMDGPU ELF
+/// * `device` - The device target ID
+///
+/// # Returns
+/// * `Ok(&[u8])` - The code object to load
+/// * `Err(CodeObjectError)` - If the image is malformed or has no code for the device
+pub fn select_code_object<'a>(image: &'a [u8], device: &TargetId) -> CodeObjectResult<&'a [u8]> {
+    if OffloadBundle::is_bundle(image) {
+        return OffloadBundle::parse(image)?
+            .select(device)
+            .map(|entry| entry.data());
+    }
+    let elf = Elf::parse(image)?;
+    let Some(target) = elf.target_id() else {
+        // Leave unknown processors to the runtime
+        return Ok(image);
+    };
+    if !TargetId::parse(&target)?.is_compatible_with(device) {
+        return Err(CodeObjectError::NoCompatibleTarget {
+            device: device.to_string(),
+            available: vec![target],
+        });
+    }
+    Ok(image)
+}
+
+/// Checks the parts of `image` the runtime reads that can be bounded.
+///
+/// The bundle header and every AMDGPU code object in it must lie within the
+/// slice. Compressed bundles and entries for other targets, such as SPIR-V,
+/// are left to the runtime.
+///
+/// # Returns
+/// * `Ok(())` - If no checked part extends past the end of `image`
+/// * `Err(CodeObjectError)` - If the image is neither a bundle nor a code object, or is truncated
+pub(crate) fn check_image(image: &[u8]) -> CodeObjectResult<()> {
+    match OffloadBundle::parse(image) {
+        Ok(bundle) => bundle
+            .device_entries()
+            .filter(|entry| entry.triple() == AMDGPU_TRIPLE && !entry.data().is_empty())
+            .try_for_each(|entry| check_extents(entry.data())),
+        Err(CodeObjectError::CompressedBundle) => Ok(()),
+        Err(CodeObjectError::NotABundle) => check_extents(image),
+        Err(error) => Err(error),
+    }
+}
+
+fn invalid_bundle(reason: &str) -> CodeObjectError {
+    CodeObjectError::InvalidBundle(reason.to_string())
+}
+
+/// Reads the little-endian bundle header.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> CodeObjectResult<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.position..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or_else(|| invalid_bundle("header is truncated"))?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn u64(&mut self) -> CodeObjectResult<usize> {
+        let bytes = self.bytes(8)?.try_into().unwrap();
+        usize::try_from(u64::from_le_bytes(bytes)).map_err(|_| invalid_bundle("value is too large"))
+    }
+}
+
+/// One entry of an [`OffloadBundle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BundleEntry<'a> {
+    id: &'a str,
+    data: &'a [u8],
+}
+
+impl<'a> BundleEntry<'a> {
+    /// Returns the entry ID, e.g. `hipv4-amdgcn-amd-amdhsa--gfx90a:xnack-`.
+    pub fn id(&self) -> &'a str {
+        self.id
+    }
+
+    /// Returns the code object of the entry.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns the offload kind, e.g. `hipv4`, `hip` or `host`.
+    pub fn offload_kind(&self) -> &'a str {
+        self.id.split('-').next().unwrap_or_default()
+    }
+
+    /// Returns the target triple, e.g. `amdgcn-amd-amdhsa`.
+    pub fn triple(&self) -> &'a str {
+        split_triple(self.without_kind()).0
+    }
+
+    /// Returns the target ID, or `None` for the host entry.
+    pub fn target_id(&self) -> Option<&'a str> {
+        Some(split_triple(self.without_kind()).1).filter(|target| !target.is_empty())
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.offload_kind() == "host"
+    }
+
+    /// Parses the code object of a device entry.
+    pub fn code_object(&self) -> CodeObjectResult<CodeObject> {
+        CodeObject::parse(self.data)
+    }
+
+    fn without_kind(&self) -> &'a str {
+        self.id.split_once('-').map_or("", |(_, rest)| rest)
+    }
+}
+
+/// Splits `amdgcn-amd-amdhsa--gfx90a` into the triple and the target ID.
+///
+/// Triples have four components, the last of which is usually empty for
+/// AMDGPU. Older bundles omit it, which is detected by the processor
+/// name following the OS directly.
+pub(crate) fn split_triple(id: &str) -> (&str, &str) {
+    let Some((third_dash, _)) = id.match_indices('-').nth(2) else {
+        return (id, "");
+    };
+    let rest = &id[third_dash + 1..];
+    if rest.starts_with("gfx") {
+        return (&id[..third_dash], rest);
+    }
+    match rest.find('-') {
+        Some(end) => (
+            id[..third_dash + 1 + end].trim_end_matches('-'),
+            &rest[end + 1..],
+        ),
+        None => (id, ""),
+    }
+}
+
+/// Builds a bundle from entry IDs and code objects.
+#[cfg(test)]
+pub(crate) fn build_bundle(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let header_size = BUNDLE_MAGIC.len()
+        + 8
+        + entries
+            .iter()
+            .map(|(id, _)| ENTRY_HEADER_SIZE + id.len())
+            .sum::<usize>();
+    let mut bundle = BUNDLE_MAGIC.to_vec();
+    bundle.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    let mut offset = header_size;
+    for (id, data) in entries {
+        bundle.extend_from_slice(&(offset as u64).to_le_bytes());
+        bundle.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        bundle.extend_from_slice(&(id.len() as u64).to_le_bytes());
+        bundle.extend_from_slice(id.as_bytes());
+        offset += data.len();
+    }
+    for (_, data) in entries {
+        bundle.extend_from_slice(data);
+    }
+    bundle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_triple() {
+        let cases = [
+            ("amdgcn-amd-amdhsa--gfx90a", ("amdgcn-amd-amdhsa", "gfx90a")),
+            (
+                "amdgcn-amd-amdhsa--gfx90a:sramecc+:xnack-",
+                ("amdgcn-amd-amdhsa", "gfx90a:sramecc+:xnack-"),
+            ),
+            ("amdgcn-amd-amdhsa-gfx906", ("amdgcn-amd-amdhsa", "gfx906")),
+            (
+                "x86_64-unknown-linux-gnu-",
+                ("x86_64-unknown-linux-gnu", ""),
+            ),
+            ("x86_64-unknown-linux-gnu", ("x86_64-unknown-linux-gnu", "")),
+            ("x86_64", ("x86_64", "")),
+        ];
+        for (id, expected) in cases {
+            assert_eq!(split_triple(id), expected, "{}", id);
+        }
+    }
+
+    #[test]
+    fn test_parse_bundle() {
+        let data = build_bundle(&[
+            ("host-x86_64-unknown-linux-gnu-", b""),
+            ("hipv4-amdgcn-amd-amdhsa--gfx90a:xnack-", b"first"),
+            ("hipv4-amdgcn-amd-amdhsa--gfx1100", b"second"),
+        ]);
+        assert!(OffloadBundle::is_bundle(&data));
+        let bundle = OffloadBundle::parse(&data).unwrap();
+        assert_eq!(bundle.entries().len(), 3);
+
+        let host = bundle.entries()[0];
+        assert!(host.is_host());
+        assert_eq!(host.triple(), "x86_64-unknown-linux-gnu");
+        assert_eq!(host.target_id(), None);
+
+        let device: Vec<_> = bundle.device_entries().collect();
+        assert_eq!(device.len(), 2);
+        assert_eq!(device[0].offload_kind(), "hipv4");
+        assert_eq!(device[0].triple(), "amdgcn-amd-amdhsa");
+        assert_eq!(device[0].data(), b"first");
+        assert_eq!(device[1].data(), b"second");
+        assert_eq!(bundle.targets(), vec!["gfx90a:xnack-", "gfx1100"]);
+    }
+
+    #[test]
+    fn test_select_entry() {
+        let data = build_bundle(&[
+            ("host-x86_64-unknown-linux-gnu-", b""),
+            ("hipv4-amdgcn-amd-amdhsa--gfx90a", b"gfx90a"),
+            ("hipv4-amdgcn-amd-amdhsa--gfx90a:xnack+", b"xnack+"),
+            (
+                "hipv4-amdgcn-amd-amdhsa--gfx90a:sramecc+:xnack-",
+                b"sramecc+ xnack-",
+            ),
+            ("hipv4-amdgcn-amd-amdhsa--gfx942", b"gfx942"),
+            ("hipv4-amdgcn-amd-amdhsa--gfx1100", b"gfx1100"),
+        ]);
+        let bundle = OffloadBundle::parse(&data).unwrap();
+        let cases = [
+            ("gfx90a:sramecc+:xnack-", "sramecc+ xnack-"),
+            ("gfx90a:sramecc-:xnack+", "xnack+"),
+            ("gfx90a:sramecc-:xnack-", "gfx90a"),
+            ("gfx942:sramecc+:xnack-", "gfx942"),
+            ("gfx1100", "gfx1100"),
+        ];
+        for (device, expected) in cases {
+            let entry = bundle.select(&device.parse().unwrap()).unwrap();
+            assert_eq!(entry.data(), expected.as_bytes(), "{}", device);
+        }
+
+        let error = bundle.select(&"gfx1030".parse().unwrap()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "no code object for gfx1030, the image has code for gfx90a, gfx90a:xnack+, \
+             gfx90a:sramecc+:xnack-, gfx942, gfx1100"
+        );
+    }
+
+    #[test]
+    fn test_select_code_object() {
+        let gfx90a = crate::code_object::elf::build_elf(2, 0x03f | 0x200, &[]);
+        let bundle = build_bundle(&[("hipv4-amdgcn-amd-amdhsa--gfx90a:xnack-", &gfx90a)]);
+        let device: TargetId = "gfx90a:sramecc+:xnack-".parse().unwrap();
+        assert_eq!(select_code_object(&bundle, &device).unwrap(), &gfx90a[..]);
+        assert_eq!(select_code_object(&gfx90a, &device).unwrap(), &gfx90a[..]);
+
+        let device: TargetId = "gfx90a:sramecc+:xnack+".parse().unwrap();
+        assert_eq!(
+            select_code_object(&gfx90a, &device),
+            Err(CodeObjectError::NoCompatibleTarget {
+                device: "gfx90a:sramecc+:xnack+".to_string(),
+                available: vec!["gfx90a:xnack-".to_string()],
+            })
+        );
+        assert!(select_code_object(&bundle, &device).is_err());
+        assert!(select_code_object(b"garbage", &device).is_err());
+    }
+
+    #[test]
+    fn test_check_image() {
+        let code = crate::code_object::elf::build_elf(2, 0x03f, &[]);
+        let bundle = build_bundle(&[
+            ("host-x86_64-unknown-linux-gnu-", b""),
+            ("hipv4-amdgcn-amd-amdhsa--gfx90a", &code),
+        ]);
+        assert_eq!(check_image(&code), Ok(()));
+        assert_eq!(check_image(&bundle), Ok(()));
+
+        // The entry fits in the bundle, but the code object inside it is cut short
+        let short = build_bundle(&[("hipv4-amdgcn-amd-amdhsa--gfx90a", &code[..code.len() - 8])]);
+        assert!(matches!(
+            check_image(&short),
+            Err(CodeObjectError::InvalidElf(_))
+        ));
+        assert!(check_image(&bundle[..bundle.len() - 1]).is_err());
+        assert!(check_image(&code[..code.len() - 1]).is_err());
+        assert!(check_image(&[]).is_err());
+
+        // Formats the runtime handles itself are passed through
+        assert_eq!(check_image(b"CCOB\x01\x00"), Ok(()));
+        let spirv = build_bundle(&[("hip-spirv64-amd-amdhsa--amdgcnspirv", b"\x03\x02\x23\x07")]);
+        assert_eq!(check_image(&spirv), Ok(()));
+    }
+
+    #[test]
+    fn test_invalid_bundles() {
+        assert_eq!(
+            OffloadBundle::parse(b"\x7fELF"),
+            Err(CodeObjectError::NotABundle)
+        );
+        assert_eq!(
+            OffloadBundle::parse(b"CCOB\x01\x00"),
+            Err(CodeObjectError::CompressedBundle)
+        );
+
+        let valid = build_bundle(&[("hipv4-amdgcn-amd-amdhsa--gfx90a", b"code")]);
+        let mut huge_count = valid.clone();
+        huge_count[24..32].copy_from_slice(&u64::MAX.to_le_bytes());
+        let mut out_of_bounds = valid.clone();
+        out_of_bounds[32..40].copy_from_slice(&(valid.len() as u64).to_le_bytes());
+        let mut bad_id = valid.clone();
+        bad_id[56] = 0xff;
+        let cases = [
+            valid[..30].to_vec(),
+            valid[..60].to_vec(),
+            valid[..valid.len() - 1].to_vec(),
+            huge_count,
+            out_of_bounds,
+            bad_id,
+        ];
+        for data in cases {
+            assert!(matches!(
+                OffloadBundle::parse(&data),
+                Err(CodeObjectError::InvalidBundle(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,442 @@
+use super::result::{CodeObjectError, CodeObjectResult};
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ELFOSABI_AMDGPU_HSA: u8 = 64;
+const EM_AMDGPU: u16 = 224;
+const SHT_NOTE: u32 = 7;
+const SHT_NOBITS: u32 = 8;
+const ELF_HEADER_SIZE: usize = 64;
+const PROGRAM_HEADER_SIZE: usize = 56;
+const SECTION_HEADER_SIZE: usize = 64;
+
+/// Note type of the MessagePack `amdhsa` metadata
+pub(crate) const NT_AMDGPU_METADATA: u32 = 32;
+
+const EF_AMDGPU_MACH: u32 = 0x0ff;
+const EF_AMDGPU_FEATURE_XNACK_V3: u32 = 0x100;
+const EF_AMDGPU_FEATURE_SRAMECC_V3: u32 = 0x200;
+const EF_AMDGPU_FEATURE_XNACK_V4: u32 = 0x300;
+const EF_AMDGPU_FEATURE_SRAMECC_V4: u32 = 0xc00;
+
+/// Processor names by `EF_AMDGPU_MACH` value
+const MACHINES: &[(u32, &str)] = &[
+    (0x020, "gfx600"),
+    (0x021, "gfx601"),
+    (0x022, "gfx700"),
+    (0x023, "gfx701"),
+    (0x024, "gfx702"),
+    (0x025, "gfx703"),
+    (0x026, "gfx704"),
+    (0x028, "gfx801"),
+    (0x029, "gfx802"),
+    (0x02a, "gfx803"),
+    (0x02b, "gfx810"),
+    (0x02c, "gfx900"),
+    (0x02d, "gfx902"),
+    (0x02e, "gfx904"),
+    (0x02f, "gfx906"),
+    (0x030, "gfx908"),
+    (0x031, "gfx909"),
+    (0x032, "gfx90c"),
+    (0x033, "gfx1010"),
+    (0x034, "gfx1011"),
+    (0x035, "gfx1012"),
+    (0x036, "gfx1030"),
+    (0x037, "gfx1031"),
+    (0x038, "gfx1032"),
+    (0x039, "gfx1033"),
+    (0x03a, "gfx602"),
+    (0x03b, "gfx705"),
+    (0x03c, "gfx805"),
+    (0x03d, "gfx1035"),
+    (0x03e, "gfx1034"),
+    (0x03f, "gfx90a"),
+    (0x040, "gfx940"),
+    (0x041, "gfx1100"),
+    (0x042, "gfx1013"),
+    (0x043, "gfx1150"),
+    (0x044, "gfx1103"),
+    (0x045, "gfx1036"),
+    (0x046, "gfx1101"),
+    (0x047, "gfx1102"),
+    (0x048, "gfx1200"),
+    (0x04a, "gfx1151"),
+    (0x04b, "gfx941"),
+    (0x04c, "gfx942"),
+    (0x04e, "gfx1201"),
+    (0x04f, "gfx950"),
+];
+
+/// The parts of an AMDGPU ELF needed to describe its kernels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Elf<'a> {
+    /// `EI_ABIVERSION`, where 1 is code object v3
+    pub abi_version: u8,
+    pub flags: u32,
+    pub notes: Vec<Note<'a>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Note<'a> {
+    pub name: &'a [u8],
+    pub kind: u32,
+    pub desc: &'a [u8],
+}
+
+fn invalid(reason: &str) -> CodeObjectError {
+    CodeObjectError::InvalidElf(reason.to_string())
+}
+
+fn read<const N: usize>(data: &[u8], offset: usize) -> CodeObjectResult<[u8; N]> {
+    offset
+        .checked_add(N)
+        .and_then(|end| data.get(offset..end))
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| invalid("file is truncated"))
+}
+
+fn u16_at(data: &[u8], offset: usize) -> CodeObjectResult<u16> {
+    read(data, offset).map(u16::from_le_bytes)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> CodeObjectResult<u32> {
+    read(data, offset).map(u32::from_le_bytes)
+}
+
+fn usize_at(data: &[u8], offset: usize) -> CodeObjectResult<usize> {
+    let value = read(data, offset).map(u64::from_le_bytes)?;
+    usize::try_from(value).map_err(|_| invalid("offset is too large"))
+}
+
+/// Returns `data[offset..offset + len]`, or an error naming `what`.
+fn range<'a>(data: &'a [u8], offset: usize, len: usize, what: &str) -> CodeObjectResult<&'a [u8]> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| CodeObjectError::InvalidElf(format!("{} extends past the end", what)))
+}
+
+impl<'a> Elf<'a> {
+    /// Parses the header and note sections of a little-endian ELF64 AMDGPU object.
+    pub(crate) fn parse(data: &'a [u8]) -> CodeObjectResult<Self> {
+        if !data.starts_with(ELF_MAGIC) {
+            return Err(invalid("missing ELF magic"));
+        }
+        if data.len() < ELF_HEADER_SIZE {
+            return Err(invalid("file is truncated"));
+        }
+        if data[4] != ELFCLASS64 || data[5] != ELFDATA2LSB {
+            return Err(invalid("not a little-endian 64-bit ELF"));
+        }
+        if data[7] != ELFOSABI_AMDGPU_HSA || u16_at(data, 0x12)? != EM_AMDGPU {
+            return Err(invalid("not an AMDGPU HSA code object"));
+        }
+        let abi_version = data[8];
+        let flags = u32_at(data, 0x30)?;
+
+        let section_offset = usize_at(data, 0x28)?;
+        let section_size = usize::from(u16_at(data, 0x3a)?);
+        let section_count = usize::from(u16_at(data, 0x3c)?);
+        if section_count > 0 && section_size < SECTION_HEADER_SIZE {
+            return Err(invalid("section headers are too small"));
+        }
+        let sections = range(
+            data,
+            section_offset,
+            section_size * section_count,
+            "section header table",
+        )?;
+
+        let mut notes = Vec::new();
+        for header in sections.chunks_exact(section_size.max(1)) {
+            if u32_at(header, 4)? != SHT_NOTE {
+                continue;
+            }
+            let offset = usize_at(header, 24)?;
+            let size = usize_at(header, 32)?;
+            parse_notes(range(data, offset, size, "note section")?, &mut notes)?;
+        }
+        Ok(Self {
+            abi_version,
+            flags,
+            notes,
+        })
+    }
+
+    /// Returns the `amdhsa` metadata note, if any.
+    pub(crate) fn metadata(&self) -> Option<&'a [u8]> {
+        self.notes
+            .iter()
+            .find(|note| note.kind == NT_AMDGPU_METADATA && note.name == b"AMDGPU")
+            .map(|note| note.desc)
+    }
+
+    /// Returns the code object version, e.g. 5, if it is v3 or later.
+    pub(crate) fn code_object_version(&self) -> Option<u32> {
+        (self.abi_version >= 1).then(|| u32::from(self.abi_version) + 2)
+    }
+
+    /// Builds the target ID from the machine and feature flags.
+    pub(crate) fn target_id(&self) -> Option<String> {
+        let mach = self.flags & EF_AMDGPU_MACH;
+        let processor = MACHINES
+            .iter()
+            .find(|(value, _)| *value == mach)
+            .map(|(_, name)| *name)?;
+
+        let mut target = processor.to_string();
+        // Features are listed alphabetically, so sramecc comes first
+        if self.abi_version >= 2 {
+            for (mask, name) in [
+                (EF_AMDGPU_FEATURE_SRAMECC_V4, "sramecc"),
+                (EF_AMDGPU_FEATURE_XNACK_V4, "xnack"),
+            ] {
+                // Fields are unsupported (0), any (1), off (2) or on (3)
+                let setting = (self.flags & mask) >> mask.trailing_zeros();
+                match setting {
+                    2 => target.push_str(&format!(":{}-", name)),
+                    3 => target.push_str(&format!(":{}+", name)),
+                    _ => {}
+                }
+            }
+        } else {
+            for (mask, name) in [
+                (EF_AMDGPU_FEATURE_SRAMECC_V3, "sramecc"),
+                (EF_AMDGPU_FEATURE_XNACK_V3, "xnack"),
+            ] {
+                if self.flags & mask != 0 {
+                    target.push_str(&format!(":{}+", name));
+                }
+            }
+        }
+        Some(target)
+    }
+}
+
+/// Checks that the header tables, segments and sections all lie within `data`.
+///
+/// `hipModuleLoadData` takes no length, so an image must pass this before
+/// it is handed to the runtime.
+pub(crate) fn check_extents(data: &[u8]) -> CodeObjectResult<()> {
+    // Validates the header and the section header table
+    Elf::parse(data)?;
+
+    let program_offset = usize_at(data, 0x20)?;
+    let program_size = usize::from(u16_at(data, 0x36)?);
+    let program_count = usize::from(u16_at(data, 0x38)?);
+    if program_count > 0 && program_size < PROGRAM_HEADER_SIZE {
+        return Err(invalid("program headers are too small"));
+    }
+    let programs = range(
+        data,
+        program_offset,
+        program_size * program_count,
+        "program header table",
+    )?;
+    for header in programs.chunks_exact(program_size.max(1)) {
+        range(data, usize_at(header, 8)?, usize_at(header, 32)?, "segment")?;
+    }
+
+    let section_offset = usize_at(data, 0x28)?;
+    let section_size = usize::from(u16_at(data, 0x3a)?);
+    let section_count = usize::from(u16_at(data, 0x3c)?);
+    let sections = range(
+        data,
+        section_offset,
+        section_size * section_count,
+        "section header table",
+    )?;
+    for header in sections.chunks_exact(section_size.max(1)) {
+        // NOBITS sections such as .bss take no space in the file
+        if u32_at(header, 4)? != SHT_NOBITS {
+            range(
+                data,
+                usize_at(header, 24)?,
+                usize_at(header, 32)?,
+                "section",
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_notes<'a>(mut section: &'a [u8], notes: &mut Vec<Note<'a>>) -> CodeObjectResult<()> {
+    while !section.is_empty() {
+        let name_size = u32_at(section, 0)? as usize;
+        let desc_size = u32_at(section, 4)? as usize;
+        let kind = u32_at(section, 8)?;
+        let desc_offset = 12 + name_size.next_multiple_of(4);
+        let name = range(section, 12, name_size, "note name")?;
+        let desc = range(section, desc_offset, desc_size, "note description")?;
+        notes.push(Note {
+            // The name size includes the NUL terminator
+            name: name.strip_suffix(b"\0").unwrap_or(name),
+            kind,
+            desc,
+        });
+        let end = (desc_offset + desc_size).next_multiple_of(4);
+        section = section.get(end..).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Builds a minimal AMDGPU ELF with the given flags and notes.
+#[cfg(test)]
+pub(crate) fn build_elf(abi_version: u8, flags: u32, notes: &[(&[u8], u32, &[u8])]) -> Vec<u8> {
+    let mut note_section = Vec::new();
+    for (name, kind, desc) in notes {
+        note_section.extend_from_slice(&(name.len() as u32 + 1).to_le_bytes());
+        note_section.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+        note_section.extend_from_slice(&kind.to_le_bytes());
+        note_section.extend_from_slice(name);
+        note_section.push(0);
+        note_section.resize(note_section.len().next_multiple_of(4), 0);
+        note_section.extend_from_slice(desc);
+        note_section.resize(note_section.len().next_multiple_of(4), 0);
+    }
+
+    let note_offset = ELF_HEADER_SIZE;
+    let section_offset = (note_offset + note_section.len()).next_multiple_of(8);
+    let mut elf = vec![0u8; ELF_HEADER_SIZE];
+    elf[..4].copy_from_slice(ELF_MAGIC);
+    elf[4] = ELFCLASS64;
+    elf[5] = ELFDATA2LSB;
+    elf[6] = 1;
+    elf[7] = ELFOSABI_AMDGPU_HSA;
+    elf[8] = abi_version;
+    elf[0x10..0x12].copy_from_slice(&3u16.to_le_bytes());
+    elf[0x12..0x14].copy_from_slice(&EM_AMDGPU.to_le_bytes());
+    elf[0x28..0x30].copy_from_slice(&(section_offset as u64).to_le_bytes());
+    elf[0x30..0x34].copy_from_slice(&flags.to_le_bytes());
+    elf[0x3a..0x3c].copy_from_slice(&(SECTION_HEADER_SIZE as u16).to_le_bytes());
+    elf[0x3c..0x3e].copy_from_slice(&2u16.to_le_bytes());
+    elf.extend_from_slice(&note_section);
+    elf.resize(section_offset, 0);
+
+    // A null section followed by the note section
+    elf.extend_from_slice(&[0u8; SECTION_HEADER_SIZE]);
+    let mut header = [0u8; SECTION_HEADER_SIZE];
+    header[4..8].copy_from_slice(&SHT_NOTE.to_le_bytes());
+    header[24..32].copy_from_slice(&(note_offset as u64).to_le_bytes());
+    header[32..40].copy_from_slice(&(note_section.len() as u64).to_le_bytes());
+    elf.extend_from_slice(&header);
+    elf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_notes() {
+        let elf = build_elf(
+            3,
+            0x03f,
+            &[
+                (b"AMD", 1, b"abc"),
+                (b"AMDGPU", NT_AMDGPU_METADATA, b"\x80"),
+            ],
+        );
+        let parsed = Elf::parse(&elf).unwrap();
+        assert_eq!(parsed.abi_version, 3);
+        assert_eq!(parsed.code_object_version(), Some(5));
+        assert_eq!(parsed.notes.len(), 2);
+        assert_eq!(
+            parsed.notes[0],
+            Note {
+                name: b"AMD",
+                kind: 1,
+                desc: b"abc"
+            }
+        );
+        assert_eq!(parsed.metadata(), Some(&b"\x80"[..]));
+    }
+
+    #[test]
+    fn test_target_id_from_flags() {
+        let cases = [
+            (2, 0x03f, "gfx90a"),
+            (2, 0x03f | 0x100 | 0x400, "gfx90a"),
+            (2, 0x03f | 0x200, "gfx90a:xnack-"),
+            (3, 0x04c | 0xc00 | 0x300, "gfx942:sramecc+:xnack+"),
+            (3, 0x041 | 0x800, "gfx1100:sramecc-"),
+            (1, 0x02f | 0x100, "gfx906:xnack+"),
+            (1, 0x02f | 0x300, "gfx906:sramecc+:xnack+"),
+        ];
+        for (abi_version, flags, expected) in cases {
+            let elf = build_elf(abi_version, flags, &[]);
+            let parsed = Elf::parse(&elf).unwrap();
+            assert_eq!(parsed.target_id().as_deref(), Some(expected));
+        }
+        let unknown = build_elf(2, 0x0fe, &[]);
+        assert_eq!(Elf::parse(&unknown).unwrap().target_id(), None);
+    }
+
+    #[test]
+    fn test_invalid_elf() {
+        let valid = build_elf(2, 0x03f, &[(b"AMDGPU", NT_AMDGPU_METADATA, b"\x80")]);
+        let mut wrong_machine = valid.clone();
+        wrong_machine[0x12] = 62;
+        let mut wrong_class = valid.clone();
+        wrong_class[4] = 1;
+        let mut bad_sections = valid.clone();
+        bad_sections[0x28..0x30].copy_from_slice(&u64::MAX.to_le_bytes());
+        let cases = [
+            b"not an elf".to_vec(),
+            valid[..32].to_vec(),
+            valid[..valid.len() - 1].to_vec(),
+            wrong_machine,
+            wrong_class,
+            bad_sections,
+        ];
+        for data in cases {
+            assert!(matches!(
+                Elf::parse(&data),
+                Err(CodeObjectError::InvalidElf(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_check_extents() {
+        let valid = build_elf(2, 0x03f, &[]);
+        assert_eq!(check_extents(&valid), Ok(()));
+        assert!(check_extents(&valid[..valid.len() - 1]).is_err());
+
+        // The first section is the null section, the second the notes
+        let size_field = valid.len() - SECTION_HEADER_SIZE + 32;
+        let mut long_section = valid.clone();
+        long_section[size_field..size_field + 8].copy_from_slice(&4096u64.to_le_bytes());
+        let mut nobits = long_section.clone();
+        nobits[size_field - 28..size_field - 24].copy_from_slice(&SHT_NOBITS.to_le_bytes());
+        assert_eq!(
+            check_extents(&nobits),
+            Ok(()),
+            "NOBITS sections are not backed by the file"
+        );
+
+        // One program header whose segment runs past the end
+        let mut long_segment = valid.clone();
+        let program_offset = long_segment.len();
+        let mut header = [0u8; PROGRAM_HEADER_SIZE];
+        let file_size = program_offset + PROGRAM_HEADER_SIZE;
+        header[32..40].copy_from_slice(&(file_size as u64 + 1).to_le_bytes());
+        long_segment.extend_from_slice(&header);
+        long_segment[0x20..0x28].copy_from_slice(&(program_offset as u64).to_le_bytes());
+        long_segment[0x36..0x38].copy_from_slice(&(PROGRAM_HEADER_SIZE as u16).to_le_bytes());
+        long_segment[0x38..0x3a].copy_from_slice(&1u16.to_le_bytes());
+        let mut short_segment = long_segment.clone();
+        short_segment[program_offset + 32..program_offset + 40]
+            .copy_from_slice(&(file_size as u64).to_le_bytes());
+        assert_eq!(check_extents(&short_segment), Ok(()));
+
+        for data in [long_section, long_segment] {
+            assert!(matches!(
+                check_extents(&data),
+                Err(CodeObjectError::InvalidElf(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,500 @@
+use super::bundle::split_triple;
+use super::elf::Elf;
+use super::msgpack::{self, Value};
+use super::result::{CodeObjectError, CodeObjectResult};
+
+/// An AMDGPU code object and the kernels it defines.
+///
+/// Only code object v3 and later are supported, which store their metadata
+/// as MessagePack in an `AMDGPU` note.
+///
+/// # Examples
+/// ```no_run
+/// use hip_rs::CodeObject;
+///
+/// let data = std::fs::read("kernels.hsaco").unwrap();
+/// let code_object = CodeObject::parse(&data).unwrap();
+/// println!("{}", code_object.target_id);
+/// for kernel in &code_object.kernels {
+///     println!("{}: {} bytes of LDS", kernel.name, kernel.lds_size);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeObject {
+    /// Target ID, e.g. `gfx90a:sramecc+:xnack-`
+    pub target_id: String,
+    /// Code object version, e.g. 5
+    pub version: Option<u32>,
+    pub kernels: Vec<KernelMetadata>,
+}
+
+impl CodeObject {
+    /// Parses an AMDGPU ELF and its `amdhsa.kernels` metadata.
+    ///
+    /// # Arguments
+    /// * `data` - The ELF, e.g. a `.hsaco` file or a [`BundleEntry`](crate::BundleEntry)
+    ///
+    /// # Returns
+    /// * `Ok(CodeObject)` - The target and kernels of the code object
+    /// * `Err(CodeObjectError)` - If the ELF or its metadata is malformed
+    pub fn parse(data: &[u8]) -> CodeObjectResult<Self> {
+        let elf = Elf::parse(data)?;
+        let metadata = elf.metadata().ok_or_else(|| {
+            CodeObjectError::InvalidMetadata("no AMDGPU metadata note".to_string())
+        })?;
+        let metadata = msgpack::decode(metadata)?;
+
+        let target_id = match metadata.get("amdhsa.target") {
+            Some(target) => {
+                let target = target
+                    .as_str()
+                    .ok_or_else(|| invalid("'amdhsa.target' must be a string"))?;
+                split_triple(target).1.to_string()
+            }
+            None => elf
+                .target_id()
+                .ok_or_else(|| CodeObjectError::InvalidElf("unknown processor".to_string()))?,
+        };
+        let kernels = match metadata.get("amdhsa.kernels") {
+            Some(kernels) => kernels
+                .as_array()
+                .ok_or_else(|| invalid("'amdhsa.kernels' must be a list"))?
+                .iter()
+                .map(KernelMetadata::parse)
+                .collect::<CodeObjectResult<_>>()?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            target_id,
+            version: elf.code_object_version(),
+            kernels,
+        })
+    }
+
+    /// Looks up a kernel by name.
+    pub fn kernel(&self, name: &str) -> Option<&KernelMetadata> {
+        self.kernels.iter().find(|kernel| kernel.name == name)
+    }
+}
+
+/// Metadata of a kernel in a [`CodeObject`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KernelMetadata {
+    /// Source name of the kernel
+    pub name: String,
+    /// Symbol of the kernel descriptor, e.g. `vector_add.kd`
+    pub symbol: String,
+    /// Arguments in kernarg segment order, including hidden arguments
+    pub args: Vec<KernelArgMetadata>,
+    /// Size of the kernarg segment in bytes
+    pub kernarg_segment_size: u32,
+    pub kernarg_segment_align: u32,
+    /// Statically allocated LDS (shared memory) in bytes
+    pub lds_size: u32,
+    /// Statically allocated scratch (private memory) per work-item in bytes
+    pub scratch_size: u32,
+    pub vgpr_count: u32,
+    pub sgpr_count: u32,
+    /// Accumulation VGPRs, only used on gfx908 and later
+    pub agpr_count: u32,
+    pub vgpr_spill_count: u32,
+    pub sgpr_spill_count: u32,
+    pub wavefront_size: u32,
+    pub max_flat_workgroup_size: u32,
+    /// Whether the stack size depends on the call graph at runtime
+    pub uses_dynamic_stack: bool,
+}
+
+impl KernelMetadata {
+    /// Returns the arguments written by the caller, skipping hidden ones.
+    pub fn explicit_args(&self) -> impl Iterator<Item = &KernelArgMetadata> {
+        self.args.iter().filter(|arg| !arg.value_kind.is_hidden())
+    }
+
+    fn parse(value: &Value<'_>) -> CodeObjectResult<Self> {
+        let name = required_str(value, ".name")?.to_string();
+        let invalid = |reason: String| {
+            CodeObjectError::InvalidMetadata(format!("kernel '{}': {}", name, reason))
+        };
+        let number = |key: &str| optional_u32(value, key).map_err(&invalid);
+
+        let args = match value.get(".args") {
+            Some(args) => args
+                .as_array()
+                .ok_or_else(|| invalid("'.args' must be a list".to_string()))?
+                .iter()
+                .map(KernelArgMetadata::parse)
+                .collect::<CodeObjectResult<_>>()
+                .map_err(|error| match error {
+                    CodeObjectError::InvalidMetadata(reason) => invalid(reason),
+                    error => error,
+                })?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            symbol: required_str(value, ".symbol")?.to_string(),
+            args,
+            kernarg_segment_size: number(".kernarg_segment_size")?,
+            kernarg_segment_align: number(".kernarg_segment_align")?,
+            lds_size: number(".group_segment_fixed_size")?,
+            scratch_size: number(".private_segment_fixed_size")?,
+            vgpr_count: number(".vgpr_count")?,
+            sgpr_count: number(".sgpr_count")?,
+            agpr_count: number(".agpr_count")?,
+            vgpr_spill_count: number(".vgpr_spill_count")?,
+            sgpr_spill_count: number(".sgpr_spill_count")?,
+            wavefront_size: number(".wavefront_size")?,
+            max_flat_workgroup_size: number(".max_flat_workgroup_size")?,
+            uses_dynamic_stack: matches!(value.get(".uses_dynamic_stack"), Some(Value::Bool(true))),
+            name,
+        })
+    }
+}
+
+/// Metadata of a kernel argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelArgMetadata {
+    /// Source name, if the compiler recorded it
+    pub name: Option<String>,
+    /// Source type, e.g. `float*`, if the compiler recorded it
+    pub type_name: Option<String>,
+    /// Offset in the kernarg segment in bytes
+    pub offset: u32,
+    pub size: u32,
+    pub value_kind: ArgValueKind,
+    /// Address space of pointer arguments
+    pub address_space: Option<AddressSpace>,
+}
+
+impl KernelArgMetadata {
+    fn parse(value: &Value<'_>) -> CodeObjectResult<Self> {
+        let string = |key: &str| optional_str(value, key).map(|s| s.map(str::to_string));
+        let name = string(".name")?;
+        let invalid = |reason: String| {
+            let arg = name.as_deref().unwrap_or("<unnamed>");
+            CodeObjectError::InvalidMetadata(format!("argument '{}': {}", arg, reason))
+        };
+        let value_kind = ArgValueKind::from_name(required_str(value, ".value_kind")?);
+        let address_space = match optional_str(value, ".address_space")? {
+            Some(space) => Some(
+                AddressSpace::from_name(space)
+                    .ok_or_else(|| invalid(format!("unknown address space '{}'", space)))?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            type_name: string(".type_name")?,
+            offset: optional_u32(value, ".offset").map_err(&invalid)?,
+            size: optional_u32(value, ".size").map_err(&invalid)?,
+            value_kind,
+            address_space,
+            name,
+        })
+    }
+}
+
+/// How a kernel argument is passed, from `.value_kind`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgValueKind {
+    /// Copied into the kernarg segment
+    ByValue,
+    /// Pointer to global memory
+    GlobalBuffer,
+    /// Pointer to dynamically allocated LDS
+    DynamicSharedPointer,
+    Sampler,
+    Image,
+    Pipe,
+    Queue,
+    /// Filled in by the runtime, e.g. `hidden_block_count_x`
+    Hidden(String),
+    /// A kind this crate does not know
+    Other(String),
+}
+
+impl ArgValueKind {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "by_value" => ArgValueKind::ByValue,
+            "global_buffer" => ArgValueKind::GlobalBuffer,
+            "dynamic_shared_pointer" => ArgValueKind::DynamicSharedPointer,
+            "sampler" => ArgValueKind::Sampler,
+            "image" => ArgValueKind::Image,
+            "pipe" => ArgValueKind::Pipe,
+            "queue" => ArgValueKind::Queue,
+            hidden if hidden.starts_with("hidden_") => ArgValueKind::Hidden(hidden.to_string()),
+            other => ArgValueKind::Other(other.to_string()),
+        }
+    }
+
+    /// Returns the `.value_kind` name, e.g. `global_buffer`.
+    pub fn name(&self) -> &str {
+        match self {
+            ArgValueKind::ByValue => "by_value",
+            ArgValueKind::GlobalBuffer => "global_buffer",
+            ArgValueKind::DynamicSharedPointer => "dynamic_shared_pointer",
+            ArgValueKind::Sampler => "sampler",
+            ArgValueKind::Image => "image",
+            ArgValueKind::Pipe => "pipe",
+            ArgValueKind::Queue => "queue",
+            ArgValueKind::Hidden(name) | ArgValueKind::Other(name) => name,
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        matches!(self, ArgValueKind::Hidden(_))
+    }
+}
+
+/// Address space of a pointer argument, from `.address_space`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressSpace {
+    Private,
+    Global,
+    Constant,
+    /// LDS
+    Local,
+    Generic,
+    Region,
+}
+
+impl AddressSpace {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "private" => Some(AddressSpace::Private),
+            "global" => Some(AddressSpace::Global),
+            "constant" => Some(AddressSpace::Constant),
+            "local" => Some(AddressSpace::Local),
+            "generic" => Some(AddressSpace::Generic),
+            "region" => Some(AddressSpace::Region),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(reason: &str) -> CodeObjectError {
+    CodeObjectError::InvalidMetadata(reason.to_string())
+}
+
+fn optional_str<'a>(value: &Value<'a>, key: &str) -> CodeObjectResult<Option<&'a str>> {
+    match value.get(key) {
+        Some(field) => field
+            .as_str()
+            .map(Some)
+            .ok_or_else(|| CodeObjectError::InvalidMetadata(format!("'{}' must be a string", key))),
+        None => Ok(None),
+    }
+}
+
+fn required_str<'a>(value: &Value<'a>, key: &str) -> CodeObjectResult<&'a str> {
+    optional_str(value, key)?
+        .ok_or_else(|| CodeObjectError::InvalidMetadata(format!("missing '{}'", key)))
+}
+
+/// Reads an unsigned field, treating a missing one as 0.
+fn optional_u32(value: &Value<'_>, key: &str) -> Result<u32, String> {
+    match value.get(key) {
+        Some(field) => field
+            .as_u64()
+            .and_then(|number| u32::try_from(number).ok())
+            .ok_or_else(|| format!("'{}' must be an unsigned 32-bit integer", key)),
+        None => Ok(0),
+    }
+}
+
+/// Builds an ELF with `amdhsa` metadata for the given kernels.
+#[cfg(test)]
+pub(crate) fn build_code_object(target: &str, kernels: Vec<Value<'static>>) -> Vec<u8> {
+    let metadata = Value::Map(vec![
+        (
+            Value::Str("amdhsa.version"),
+            Value::Array(vec![Value::UInt(1), Value::UInt(2)]),
+        ),
+        (
+            Value::Str("amdhsa.target"),
+            Value::Str(Box::leak(
+                format!("amdgcn-amd-amdhsa--{}", target).into_boxed_str(),
+            )),
+        ),
+        (Value::Str("amdhsa.kernels"), Value::Array(kernels)),
+    ]);
+    let mut desc = Vec::new();
+    msgpack::encode(&metadata, &mut desc);
+    super::elf::build_elf(
+        3,
+        0x03f,
+        &[(b"AMDGPU", super::elf::NT_AMDGPU_METADATA, &desc)],
+    )
+}
+
+/// Builds the metadata map of a kernel with the given arguments.
+#[cfg(test)]
+pub(crate) fn kernel_value(
+    name: &'static str,
+    args: &[(&'static str, u64, u64, &'static str)],
+) -> Value<'static> {
+    let args = args
+        .iter()
+        .map(|&(arg, offset, size, kind)| {
+            let mut fields = vec![
+                (Value::Str(".name"), Value::Str(arg)),
+                (Value::Str(".offset"), Value::UInt(offset)),
+                (Value::Str(".size"), Value::UInt(size)),
+                (Value::Str(".value_kind"), Value::Str(kind)),
+            ];
+            if kind == "global_buffer" {
+                fields.push((Value::Str(".address_space"), Value::Str("global")));
+            }
+            Value::Map(fields)
+        })
+        .collect();
+    Value::Map(vec![
+        (Value::Str(".name"), Value::Str(name)),
+        (
+            Value::Str(".symbol"),
+            Value::Str(Box::leak(format!("{}.kd", name).into_boxed_str())),
+        ),
+        (Value::Str(".args"), Value::Array(args)),
+        (Value::Str(".kernarg_segment_size"), Value::UInt(280)),
+        (Value::Str(".kernarg_segment_align"), Value::UInt(8)),
+        (Value::Str(".group_segment_fixed_size"), Value::UInt(1024)),
+        (Value::Str(".private_segment_fixed_size"), Value::UInt(16)),
+        (Value::Str(".vgpr_count"), Value::UInt(12)),
+        (Value::Str(".sgpr_count"), Value::UInt(20)),
+        (Value::Str(".wavefront_size"), Value::UInt(64)),
+        (Value::Str(".max_flat_workgroup_size"), Value::UInt(1024)),
+        (Value::Str(".uses_dynamic_stack"), Value::Bool(false)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_object::bundle::{build_bundle, OffloadBundle};
+    use crate::code_object::elf::{build_elf, NT_AMDGPU_METADATA};
+
+    fn vector_add() -> Value<'static> {
+        kernel_value(
+            "vector_add",
+            &[
+                ("a", 0, 8, "global_buffer"),
+                ("b", 8, 8, "global_buffer"),
+                ("c", 16, 8, "global_buffer"),
+                ("n", 24, 4, "by_value"),
+                ("", 32, 4, "hidden_block_count_x"),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_parse_code_object() {
+        let data = build_code_object("gfx90a:sramecc+:xnack-", vec![vector_add()]);
+        let code_object = CodeObject::parse(&data).unwrap();
+        assert_eq!(code_object.target_id, "gfx90a:sramecc+:xnack-");
+        assert_eq!(code_object.version, Some(5));
+        assert_eq!(code_object.kernels.len(), 1);
+
+        let kernel = code_object.kernel("vector_add").unwrap();
+        assert_eq!(kernel.symbol, "vector_add.kd");
+        assert_eq!(kernel.kernarg_segment_size, 280);
+        assert_eq!(kernel.lds_size, 1024);
+        assert_eq!(kernel.scratch_size, 16);
+        assert_eq!(kernel.vgpr_count, 12);
+        assert_eq!(kernel.sgpr_count, 20);
+        assert_eq!(kernel.agpr_count, 0);
+        assert_eq!(kernel.wavefront_size, 64);
+        assert!(!kernel.uses_dynamic_stack);
+        assert_eq!(kernel.args.len(), 5);
+        assert_eq!(kernel.explicit_args().count(), 4);
+
+        let a = &kernel.args[0];
+        assert_eq!(a.name.as_deref(), Some("a"));
+        assert_eq!(a.value_kind, ArgValueKind::GlobalBuffer);
+        assert_eq!(a.address_space, Some(AddressSpace::Global));
+        let n = &kernel.args[3];
+        assert_eq!((n.offset, n.size), (24, 4));
+        assert_eq!(n.value_kind, ArgValueKind::ByValue);
+        assert_eq!(n.address_space, None);
+        assert!(kernel.args[4].value_kind.is_hidden());
+        assert_eq!(code_object.kernel("missing"), None);
+    }
+
+    #[test]
+    fn test_parse_bundled_code_objects() {
+        let gfx90a = build_code_object("gfx90a", vec![vector_add()]);
+        let gfx1100 = build_code_object("gfx1100", vec![]);
+        let data = build_bundle(&[
+            ("host-x86_64-unknown-linux-gnu-", b""),
+            ("hipv4-amdgcn-amd-amdhsa--gfx90a", &gfx90a),
+            ("hipv4-amdgcn-amd-amdhsa--gfx1100", &gfx1100),
+        ]);
+        let bundle = OffloadBundle::parse(&data).unwrap();
+        let code_objects: Vec<_> = bundle
+            .device_entries()
+            .map(|entry| entry.code_object().unwrap())
+            .collect();
+        assert_eq!(code_objects[0].target_id, "gfx90a");
+        assert_eq!(code_objects[0].kernels[0].name, "vector_add");
+        assert_eq!(code_objects[1].target_id, "gfx1100");
+        assert!(code_objects[1].kernels.is_empty());
+    }
+
+    #[test]
+    fn test_value_kinds() {
+        for name in [
+            "by_value",
+            "global_buffer",
+            "dynamic_shared_pointer",
+            "sampler",
+            "image",
+            "pipe",
+            "queue",
+            "hidden_global_offset_x",
+            "future_kind",
+        ] {
+            assert_eq!(ArgValueKind::from_name(name).name(), name);
+        }
+        assert!(ArgValueKind::from_name("hidden_none").is_hidden());
+        assert!(!ArgValueKind::from_name("future_kind").is_hidden());
+    }
+
+    #[test]
+    fn test_target_from_flags_without_metadata_target() {
+        let metadata = Value::Map(vec![(Value::Str("amdhsa.kernels"), Value::Array(vec![]))]);
+        let mut desc = Vec::new();
+        msgpack::encode(&metadata, &mut desc);
+        let data = build_elf(2, 0x04c | 0x200, &[(b"AMDGPU", NT_AMDGPU_METADATA, &desc)]);
+        let code_object = CodeObject::parse(&data).unwrap();
+        assert_eq!(code_object.target_id, "gfx942:xnack-");
+        assert_eq!(code_object.version, Some(4));
+    }
+
+    #[test]
+    fn test_invalid_metadata() {
+        let no_note = build_elf(3, 0x03f, &[]);
+        assert!(matches!(
+            CodeObject::parse(&no_note),
+            Err(CodeObjectError::InvalidMetadata(_))
+        ));
+
+        let missing_name = Value::Map(vec![(Value::Str(".symbol"), Value::Str("k.kd"))]);
+        let bad_size = kernel_value("k", &[("x", 0, 1 << 40, "by_value")]);
+        for kernel in [missing_name, bad_size] {
+            let data = build_code_object("gfx90a", vec![kernel]);
+            assert!(matches!(
+                CodeObject::parse(&data),
+                Err(CodeObjectError::InvalidMetadata(_))
+            ));
+        }
+
+        let data = build_code_object(
+            "gfx90a",
+            vec![kernel_value("k", &[("x", 0, 1 << 40, "by_value")])],
+        );
+        let error = CodeObject::parse(&data).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid code object metadata: kernel 'k': argument 'x': '.size' must be an unsigned 32-bit integer"
+        );
+    }
+}
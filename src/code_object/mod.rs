@@ -0,0 +1,15 @@
+mod bundle;
+mod elf;
+mod metadata;
+mod msgpack;
+mod result;
+mod target;
+
+pub use bundle::*;
+pub use metadata::*;
+pub use result::*;
+pub use target::*;
+
+pub(crate) use bundle::check_image;
+#[cfg(test)]
+pub(crate) use elf::build_elf;
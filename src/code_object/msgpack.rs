@@ -0,0 +1,305 @@
+use super::result::{CodeObjectError, CodeObjectResult};
+
+/// Nesting deeper than this is rejected instead of recursing further
+const MAX_DEPTH: usize = 64;
+
+/// A decoded MessagePack value.
+///
+/// Only what the AMDGPU metadata note needs is kept: extension values are
+/// decoded as [`Value::Nil`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value<'a> {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Str(&'a str),
+    Bin(&'a [u8]),
+    Array(Vec<Value<'a>>),
+    Map(Vec<(Value<'a>, Value<'a>)>),
+}
+
+impl<'a> Value<'a> {
+    /// Looks up a string key in a map.
+    pub(crate) fn get(&self, key: &str) -> Option<&Value<'a>> {
+        match self {
+            Value::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| matches!(k, Value::Str(k) if *k == key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&'a str> {
+        match self {
+            Value::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::UInt(value) => Some(value),
+            Value::Int(value) => u64::try_from(value).ok(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Value<'a>]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes a single MessagePack value that must span all of `data`.
+pub(crate) fn decode(data: &[u8]) -> CodeObjectResult<Value<'_>> {
+    let mut decoder = Decoder { data, position: 0 };
+    let value = decoder.value(0)?;
+    if decoder.position != data.len() {
+        return Err(invalid("trailing bytes after metadata"));
+    }
+    Ok(value)
+}
+
+fn invalid(reason: &str) -> CodeObjectError {
+    CodeObjectError::InvalidMetadata(reason.to_string())
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn bytes(&mut self, len: usize) -> CodeObjectResult<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| invalid("metadata is truncated"))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn uint(&mut self, size: usize) -> CodeObjectResult<u64> {
+        let bytes = self.bytes(size)?;
+        Ok(bytes
+            .iter()
+            .fold(0u64, |value, &byte| (value << 8) | u64::from(byte)))
+    }
+
+    fn len(&mut self, size: usize) -> CodeObjectResult<usize> {
+        usize::try_from(self.uint(size)?).map_err(|_| invalid("length is too large"))
+    }
+
+    fn str(&mut self, len: usize) -> CodeObjectResult<Value<'a>> {
+        let bytes = self.bytes(len)?;
+        std::str::from_utf8(bytes)
+            .map(Value::Str)
+            .map_err(|_| invalid("string is not UTF-8"))
+    }
+
+    fn array(&mut self, len: usize, depth: usize) -> CodeObjectResult<Value<'a>> {
+        // Every element takes at least one byte, which bounds the allocation
+        let mut values = Vec::with_capacity(len.min(self.data.len() - self.position));
+        for _ in 0..len {
+            values.push(self.value(depth + 1)?);
+        }
+        Ok(Value::Array(values))
+    }
+
+    fn map(&mut self, len: usize, depth: usize) -> CodeObjectResult<Value<'a>> {
+        let mut entries = Vec::with_capacity(len.min(self.data.len() - self.position));
+        for _ in 0..len {
+            let key = self.value(depth + 1)?;
+            let value = self.value(depth + 1)?;
+            entries.push((key, value));
+        }
+        Ok(Value::Map(entries))
+    }
+
+    fn ext(&mut self, len: usize) -> CodeObjectResult<Value<'a>> {
+        // One type byte followed by the payload
+        self.bytes(1 + len)?;
+        Ok(Value::Nil)
+    }
+
+    fn value(&mut self, depth: usize) -> CodeObjectResult<Value<'a>> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("metadata is nested too deeply"));
+        }
+        let marker = self.bytes(1)?[0];
+        match marker {
+            0x00..=0x7f => Ok(Value::UInt(u64::from(marker))),
+            0x80..=0x8f => self.map(usize::from(marker & 0x0f), depth),
+            0x90..=0x9f => self.array(usize::from(marker & 0x0f), depth),
+            0xa0..=0xbf => self.str(usize::from(marker & 0x1f)),
+            0xc0 => Ok(Value::Nil),
+            0xc2 => Ok(Value::Bool(false)),
+            0xc3 => Ok(Value::Bool(true)),
+            0xc4..=0xc6 => {
+                let len = self.len(1 << (marker - 0xc4))?;
+                Ok(Value::Bin(self.bytes(len)?))
+            }
+            0xc7..=0xc9 => {
+                let len = self.len(1 << (marker - 0xc7))?;
+                self.ext(len)
+            }
+            0xca => Ok(Value::Float(f64::from(
+                f32::from_bits(self.uint(4)? as u32),
+            ))),
+            0xcb => Ok(Value::Float(f64::from_bits(self.uint(8)?))),
+            0xcc..=0xcf => Ok(Value::UInt(self.uint(1 << (marker - 0xcc))?)),
+            0xd0..=0xd3 => {
+                let size = 1 << (marker - 0xd0);
+                let value = self.uint(size)?;
+                // Sign extend from the encoded width
+                let shift = 64 - 8 * size as u32;
+                Ok(Value::Int(((value << shift) as i64) >> shift))
+            }
+            0xd4..=0xd8 => self.ext(1 << (marker - 0xd4)),
+            0xd9..=0xdb => {
+                let len = self.len(1 << (marker - 0xd9))?;
+                self.str(len)
+            }
+            0xdc | 0xdd => {
+                let len = self.len(2 << (marker - 0xdc))?;
+                self.array(len, depth)
+            }
+            0xde | 0xdf => {
+                let len = self.len(2 << (marker - 0xde))?;
+                self.map(len, depth)
+            }
+            0xe0..=0xff => Ok(Value::Int(i64::from(marker as i8))),
+            0xc1 => Err(invalid("reserved MessagePack marker 0xc1")),
+        }
+    }
+}
+
+/// Encodes values for building synthetic metadata in tests.
+#[cfg(test)]
+pub(crate) fn encode(value: &Value<'_>, out: &mut Vec<u8>) {
+    match value {
+        Value::Nil => out.push(0xc0),
+        Value::Bool(value) => out.push(0xc2 | u8::from(*value)),
+        Value::Int(value) => {
+            out.push(0xd3);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        Value::UInt(value) => {
+            out.push(0xcf);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        Value::Float(value) => {
+            out.push(0xcb);
+            out.extend_from_slice(&value.to_bits().to_be_bytes());
+        }
+        Value::Str(value) => {
+            out.push(0xdb);
+            out.extend_from_slice(&(value.len() as u32).to_be_bytes());
+            out.extend_from_slice(value.as_bytes());
+        }
+        Value::Bin(value) => {
+            out.push(0xc6);
+            out.extend_from_slice(&(value.len() as u32).to_be_bytes());
+            out.extend_from_slice(value);
+        }
+        Value::Array(values) => {
+            out.push(0xdd);
+            out.extend_from_slice(&(values.len() as u32).to_be_bytes());
+            values.iter().for_each(|value| encode(value, out));
+        }
+        Value::Map(entries) => {
+            out.push(0xdf);
+            out.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+            for (key, value) in entries {
+                encode(key, out);
+                encode(value, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_compact_forms() {
+        // {"a": [1, -1, true, nil], "b": "xy"}
+        let data = [
+            0x82, 0xa1, b'a', 0x94, 0x01, 0xff, 0xc3, 0xc0, 0xa1, b'b', 0xa2, b'x', b'y',
+        ];
+        let value = decode(&data).unwrap();
+        assert_eq!(
+            value.get("a").unwrap().as_array().unwrap(),
+            &[
+                Value::UInt(1),
+                Value::Int(-1),
+                Value::Bool(true),
+                Value::Nil
+            ]
+        );
+        assert_eq!(value.get("b").unwrap().as_str(), Some("xy"));
+        assert_eq!(value.get("c"), None);
+    }
+
+    #[test]
+    fn test_decode_sized_forms() {
+        let cases: [(&[u8], Value); 7] = [
+            (&[0xcc, 0xff], Value::UInt(255)),
+            (&[0xcd, 0x01, 0x00], Value::UInt(256)),
+            (&[0xd0, 0x80], Value::Int(-128)),
+            (&[0xd1, 0xff, 0x00], Value::Int(-256)),
+            (&[0xca, 0x3f, 0x80, 0x00, 0x00], Value::Float(1.0)),
+            (&[0xd9, 0x01, b'z'], Value::Str("z")),
+            (&[0xd4, 0x01, 0x02], Value::Nil),
+        ];
+        for (data, expected) in cases {
+            assert_eq!(decode(data).unwrap(), expected, "{:x?}", data);
+        }
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let value = Value::Map(vec![
+            (Value::Str("int"), Value::Int(-5)),
+            (Value::Str("uint"), Value::UInt(u64::MAX)),
+            (Value::Str("float"), Value::Float(0.5)),
+            (Value::Str("bin"), Value::Bin(&[1, 2, 3])),
+            (
+                Value::Str("list"),
+                Value::Array(vec![Value::Nil, Value::Bool(false)]),
+            ),
+        ]);
+        let mut data = Vec::new();
+        encode(&value, &mut data);
+        assert_eq!(decode(&data).unwrap(), value);
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let cases: [&[u8]; 6] = [
+            &[],
+            &[0xc1],
+            &[0xa2, b'x'],
+            &[0x92, 0x01],
+            &[0xa1, 0xff],
+            &[0x01, 0x02],
+        ];
+        for data in cases {
+            assert!(
+                matches!(decode(data), Err(CodeObjectError::InvalidMetadata(_))),
+                "{:x?}",
+                data
+            );
+        }
+        let nested = vec![0x91; MAX_DEPTH + 2];
+        assert!(decode(&nested).is_err());
+    }
+}
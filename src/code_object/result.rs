@@ -0,0 +1,64 @@
+use std::fmt;
+
+/// Errors from reading offload bundles and code objects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodeObjectError {
+    /// The data does not start with the offload bundle magic
+    NotABundle,
+    /// The bundle is compressed, which is not supported
+    CompressedBundle,
+    /// The bundle header or an entry is malformed
+    InvalidBundle(String),
+    /// The data is not a valid AMDGPU ELF code object
+    InvalidElf(String),
+    /// The `amdhsa` metadata note is malformed
+    InvalidMetadata(String),
+    /// A target ID is malformed
+    InvalidTargetId(String),
+    /// No code object runs on the device
+    NoCompatibleTarget {
+        device: String,
+        available: Vec<String>,
+    },
+}
+
+pub type CodeObjectResult<T> = std::result::Result<T, CodeObjectError>;
+
+impl fmt::Display for CodeObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodeObjectError::NotABundle => write!(f, "not a clang offload bundle"),
+            CodeObjectError::CompressedBundle => {
+                write!(f, "compressed offload bundles are not supported")
+            }
+            CodeObjectError::InvalidBundle(reason) => {
+                write!(f, "invalid offload bundle: {}", reason)
+            }
+            CodeObjectError::InvalidElf(reason) => write!(f, "invalid code object: {}", reason),
+            CodeObjectError::InvalidMetadata(reason) => {
+                write!(f, "invalid code object metadata: {}", reason)
+            }
+            CodeObjectError::InvalidTargetId(target) => {
+                write!(f, "invalid target ID '{}'", target)
+            }
+            CodeObjectError::NoCompatibleTarget { device, available } => {
+                if available.is_empty() {
+                    write!(
+                        f,
+                        "no code object for {}, the image has no device code",
+                        device
+                    )
+                } else {
+                    write!(
+                        f,
+                        "no code object for {}, the image has code for {}",
+                        device,
+                        available.join(", ")
+                    )
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for CodeObjectError {}
@@ -0,0 +1,206 @@
+use super::bundle::split_triple;
+use super::result::{CodeObjectError, CodeObjectResult};
+use std::fmt;
+use std::str::FromStr;
+
+/// A target ID such as `gfx90a:sramecc+:xnack-`.
+///
+/// A target ID is a processor followed by target features that are either
+/// on (`+`) or off (`-`). Features that are not listed are "any": code
+/// compiled that way runs with the feature on or off.
+///
+/// # Examples
+/// ```
+/// use hip_rs::{FeatureSetting, TargetId};
+///
+/// let code: TargetId = "gfx90a:xnack-".parse().unwrap();
+/// let device: TargetId = "gfx90a:sramecc+:xnack-".parse().unwrap();
+/// assert_eq!(code.feature("sramecc"), FeatureSetting::Any);
+/// assert!(code.is_compatible_with(&device));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TargetId {
+    processor: String,
+    /// Features sorted by name
+    features: Vec<(String, bool)>,
+}
+
+/// Setting of a feature in a [`TargetId`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeatureSetting {
+    /// The feature is not listed
+    Any,
+    On,
+    Off,
+}
+
+impl TargetId {
+    /// Parses a target ID, with or without a leading triple.
+    ///
+    /// # Arguments
+    /// * `target` - e.g. `gfx942`, `gfx90a:xnack+` or `amdgcn-amd-amdhsa--gfx90a:xnack+`
+    ///
+    /// # Returns
+    /// * `Ok(TargetId)` - The processor and features
+    /// * `Err(CodeObjectError)` - If the processor is empty or a feature has no `+` or `-`
+    pub fn parse(target: &str) -> CodeObjectResult<Self> {
+        let invalid = || CodeObjectError::InvalidTargetId(target.to_string());
+        // Dashes before the first colon belong to a triple, not a feature
+        let has_triple = target.split(':').next().unwrap_or_default().contains('-');
+        let id = if has_triple {
+            split_triple(target).1
+        } else {
+            target
+        };
+
+        let mut parts = id.split(':');
+        let processor = parts.next().unwrap_or_default();
+        if processor.is_empty() || !processor.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(invalid());
+        }
+        let mut features = Vec::new();
+        for feature in parts {
+            let (name, enabled) = if let Some(name) = feature.strip_suffix('+') {
+                (name, true)
+            } else if let Some(name) = feature.strip_suffix('-') {
+                (name, false)
+            } else {
+                return Err(invalid());
+            };
+            if name.is_empty() || features.iter().any(|(other, _)| other == name) {
+                return Err(invalid());
+            }
+            features.push((name.to_string(), enabled));
+        }
+        features.sort();
+        Ok(Self {
+            processor: processor.to_string(),
+            features,
+        })
+    }
+
+    /// Returns the processor, e.g. `gfx90a`.
+    pub fn processor(&self) -> &str {
+        &self.processor
+    }
+
+    /// Returns the setting of a feature, e.g. `xnack`.
+    pub fn feature(&self, name: &str) -> FeatureSetting {
+        match self.features.iter().find(|(feature, _)| feature == name) {
+            Some((_, true)) => FeatureSetting::On,
+            Some((_, false)) => FeatureSetting::Off,
+            None => FeatureSetting::Any,
+        }
+    }
+
+    /// Checks whether code compiled for this target runs on `device`.
+    ///
+    /// The processors must match, and every feature this target sets must
+    /// have the same setting on the device. Features left as any match
+    /// either setting.
+    ///
+    /// # Arguments
+    /// * `device` - The device target ID, e.g. from [`Device::gcn_arch_name`](crate::Device::gcn_arch_name)
+    pub fn is_compatible_with(&self, device: &TargetId) -> bool {
+        self.processor == device.processor
+            && self
+                .features
+                .iter()
+                .all(|feature| device.features.contains(feature))
+    }
+
+    /// Returns how many features are set, which ranks compatible code objects.
+    pub(crate) fn specificity(&self) -> usize {
+        self.features.len()
+    }
+}
+
+impl FromStr for TargetId {
+    type Err = CodeObjectError;
+
+    fn from_str(target: &str) -> CodeObjectResult<Self> {
+        Self::parse(target)
+    }
+}
+
+impl fmt::Display for TargetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.processor)?;
+        for (name, enabled) in &self.features {
+            write!(f, ":{}{}", name, if *enabled { '+' } else { '-' })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target_id() {
+        let target = TargetId::parse("gfx90a:xnack-:sramecc+").unwrap();
+        assert_eq!(target.processor(), "gfx90a");
+        assert_eq!(target.feature("sramecc"), FeatureSetting::On);
+        assert_eq!(target.feature("xnack"), FeatureSetting::Off);
+        assert_eq!(target.feature("cumode"), FeatureSetting::Any);
+        // Features are displayed in canonical order
+        assert_eq!(target.to_string(), "gfx90a:sramecc+:xnack-");
+
+        let cases = [
+            ("gfx942", "gfx942"),
+            ("amdgcn-amd-amdhsa--gfx942", "gfx942"),
+            ("amdgcn-amd-amdhsa--gfx90a:xnack-", "gfx90a:xnack-"),
+            ("gfx1100:xnack-", "gfx1100:xnack-"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(TargetId::parse(input).unwrap().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_target_id() {
+        for target in [
+            "",
+            ":xnack+",
+            "gfx90a:xnack",
+            "gfx90a:+",
+            "gfx90a:xnack+:xnack-",
+        ] {
+            assert_eq!(
+                TargetId::parse(target),
+                Err(CodeObjectError::InvalidTargetId(target.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_compatibility() {
+        let device: TargetId = "gfx90a:sramecc+:xnack-".parse().unwrap();
+        let compatible = [
+            "gfx90a",
+            "gfx90a:xnack-",
+            "gfx90a:sramecc+",
+            "gfx90a:sramecc+:xnack-",
+        ];
+        for code in compatible {
+            let code: TargetId = code.parse().unwrap();
+            assert!(code.is_compatible_with(&device), "{}", code);
+        }
+        let incompatible = [
+            "gfx942",
+            "gfx90a:xnack+",
+            "gfx90a:sramecc-:xnack-",
+            "gfx90a:cumode+",
+        ];
+        for code in incompatible {
+            let code: TargetId = code.parse().unwrap();
+            assert!(!code.is_compatible_with(&device), "{}", code);
+        }
+        // A device without the feature only runs code that leaves it as any
+        let device: TargetId = "gfx1100".parse().unwrap();
+        assert!(!TargetId::parse("gfx1100:xnack-")
+            .unwrap()
+            .is_compatible_with(&device));
+    }
+}
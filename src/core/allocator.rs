@@ -0,0 +1,272 @@
+use super::flags::DeviceMallocFlag;
+use super::result::HipResult;
+use crate::result::ResultExt;
+use crate::sys;
+use crate::{MemPool, Stream};
+use std::ffi::c_void;
+
+/// A source of device memory for buffer types such as [`crate::MemoryPointer`].
+///
+/// Buffers are generic over their allocator the same way `Vec<T, A>` is, and
+/// return their memory to the allocator they were created with.
+///
+/// # Safety
+/// `allocate` must return a pointer to at least `bytes` bytes of device
+/// memory that stays valid until it is passed to `deallocate` on the same
+/// allocator. Buffers never call `allocate` with zero bytes.
+pub unsafe trait DeviceAllocator {
+    /// Allocates `bytes` bytes of device memory.
+    ///
+    /// # Arguments
+    /// * `bytes` - The number of bytes to allocate, never zero
+    ///
+    /// # Returns
+    /// * `Ok(*mut c_void)` - Pointer to the allocated memory
+    /// * `Err(HipError)` - If the allocation fails
+    fn allocate(&self, bytes: usize) -> HipResult<*mut c_void>;
+
+    /// Returns memory obtained from [`DeviceAllocator::allocate`].
+    ///
+    /// # Arguments
+    /// * `ptr` - A pointer returned by `allocate` on this allocator
+    /// * `bytes` - The size the memory was allocated with
+    ///
+    /// # Safety
+    /// `ptr` must have been allocated by this allocator and must not be
+    /// used after this call.
+    unsafe fn deallocate(&self, ptr: *mut c_void, bytes: usize) -> HipResult<()>;
+
+    /// Returns the stream allocations are ordered on, or `None` if
+    /// allocation and deallocation are synchronous.
+    fn stream(&self) -> Option<&Stream> {
+        None
+    }
+}
+
+unsafe impl<A: DeviceAllocator + ?Sized> DeviceAllocator for &A {
+    fn allocate(&self, bytes: usize) -> HipResult<*mut c_void> {
+        (**self).allocate(bytes)
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut c_void, bytes: usize) -> HipResult<()> {
+        (**self).deallocate(ptr, bytes)
+    }
+
+    fn stream(&self) -> Option<&Stream> {
+        (**self).stream()
+    }
+}
+
+/// Allocates with `hipMalloc` and frees with `hipFree`.
+///
+/// This is the default allocator of [`crate::MemoryPointer`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeviceMalloc;
+
+unsafe impl DeviceAllocator for DeviceMalloc {
+    fn allocate(&self, bytes: usize) -> HipResult<*mut c_void> {
+        let mut ptr = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipMalloc(&mut ptr, bytes);
+            (ptr, code).to_result()
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut c_void, _bytes: usize) -> HipResult<()> {
+        let code = sys::hipFree(ptr);
+        ((), code).to_result()
+    }
+}
+
+/// Allocates with `hipExtMallocWithFlags` and frees with `hipFree`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceMallocWithFlags {
+    flag: DeviceMallocFlag,
+}
+
+impl DeviceMallocWithFlags {
+    /// Creates an allocator passing `flag` to every allocation.
+    pub fn new(flag: DeviceMallocFlag) -> Self {
+        Self { flag }
+    }
+
+    /// Returns the allocation flag.
+    pub fn flag(&self) -> DeviceMallocFlag {
+        self.flag
+    }
+}
+
+unsafe impl DeviceAllocator for DeviceMallocWithFlags {
+    fn allocate(&self, bytes: usize) -> HipResult<*mut c_void> {
+        let mut ptr = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipExtMallocWithFlags(&mut ptr, bytes, self.flag.bits());
+            (ptr, code).to_result()
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut c_void, _bytes: usize) -> HipResult<()> {
+        let code = sys::hipFree(ptr);
+        ((), code).to_result()
+    }
+}
+
+/// Allocates from the current pool of the stream's device with
+/// `hipMallocAsync` and frees with `hipFreeAsync`, both ordered on `stream`.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamOrderedAllocator<'a> {
+    stream: &'a Stream,
+}
+
+impl<'a> StreamOrderedAllocator<'a> {
+    /// Creates an allocator ordering allocations and frees on `stream`.
+    pub fn new(stream: &'a Stream) -> Self {
+        Self { stream }
+    }
+}
+
+unsafe impl DeviceAllocator for StreamOrderedAllocator<'_> {
+    fn allocate(&self, bytes: usize) -> HipResult<*mut c_void> {
+        let mut ptr = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipMallocAsync(&mut ptr, bytes, self.stream.handle());
+            (ptr, code).to_result()
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut c_void, _bytes: usize) -> HipResult<()> {
+        let code = sys::hipFreeAsync(ptr, self.stream.handle());
+        ((), code).to_result()
+    }
+
+    fn stream(&self) -> Option<&Stream> {
+        Some(self.stream)
+    }
+}
+
+/// Allocates from an explicit [`MemPool`] with `hipMallocFromPoolAsync` and
+/// frees with `hipFreeAsync`, both ordered on `stream`.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolAllocator<'a> {
+    pool: &'a MemPool,
+    stream: &'a Stream,
+}
+
+impl<'a> PoolAllocator<'a> {
+    /// Creates an allocator drawing from `pool`, ordered on `stream`.
+    pub fn new(pool: &'a MemPool, stream: &'a Stream) -> Self {
+        Self { pool, stream }
+    }
+
+    /// Returns the pool allocations are made from.
+    pub fn pool(&self) -> &MemPool {
+        self.pool
+    }
+}
+
+unsafe impl DeviceAllocator for PoolAllocator<'_> {
+    fn allocate(&self, bytes: usize) -> HipResult<*mut c_void> {
+        let mut ptr = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipMallocFromPoolAsync(
+                &mut ptr,
+                bytes,
+                self.pool.handle(),
+                self.stream.handle(),
+            );
+            (ptr, code).to_result()
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut c_void, _bytes: usize) -> HipResult<()> {
+        let code = sys::hipFreeAsync(ptr, self.stream.handle());
+        ((), code).to_result()
+    }
+
+    fn stream(&self) -> Option<&Stream> {
+        Some(self.stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemPoolProps, MemoryPointer};
+
+    #[test]
+    fn test_device_malloc_roundtrip() {
+        let ptr = DeviceMalloc.allocate(1024).unwrap();
+        assert!(!ptr.is_null());
+        unsafe { DeviceMalloc.deallocate(ptr, 1024).unwrap() };
+        assert!(DeviceMalloc.stream().is_none());
+    }
+
+    #[test]
+    fn test_alloc_in_with_flags() {
+        let allocator = DeviceMallocWithFlags::new(DeviceMallocFlag::FINEGRAINED);
+        let ptr = MemoryPointer::<f32, _>::alloc_in(256, allocator).unwrap();
+        assert!(!ptr.as_pointer().is_null());
+        assert_eq!(ptr.size(), 256);
+        assert_eq!(ptr.allocator().flag(), DeviceMallocFlag::FINEGRAINED);
+    }
+
+    #[test]
+    fn test_alloc_in_stream_ordered() {
+        let stream = Stream::create().unwrap();
+        {
+            let ptr = MemoryPointer::<u32, _>::alloc_in(1024, StreamOrderedAllocator::new(&stream))
+                .unwrap();
+            assert!(!ptr.as_pointer().is_null());
+            assert!(ptr.allocator().stream().is_some());
+        }
+        stream.synchronize().unwrap();
+    }
+
+    #[test]
+    fn test_alloc_in_pool() {
+        let pool = MemPool::create(MemPoolProps::new()).unwrap();
+        let stream = Stream::create().unwrap();
+        let allocator = PoolAllocator::new(&pool, &stream);
+
+        let a = MemoryPointer::<f32, _>::alloc_in(256, &allocator).unwrap();
+        let b = MemoryPointer::<f32, _>::alloc_in(256, &allocator).unwrap();
+        assert_ne!(a.as_pointer(), b.as_pointer());
+        stream.synchronize().unwrap();
+        assert!(pool.stats().unwrap().used_current >= 2 * 256 * 4);
+
+        drop(a);
+        drop(b);
+        stream.synchronize().unwrap();
+    }
+
+    #[test]
+    fn test_alloc_in_zero_size() {
+        let ptr = MemoryPointer::<u8, _>::alloc_in(0, DeviceMalloc).unwrap();
+        assert!(ptr.as_pointer().is_null());
+        assert_eq!(ptr.size(), 0);
+    }
+
+    /// Fails every allocation, so any call to it is visible.
+    struct RejectingAllocator;
+
+    unsafe impl DeviceAllocator for RejectingAllocator {
+        fn allocate(&self, bytes: usize) -> HipResult<*mut c_void> {
+            panic!("allocate called with {} bytes", bytes);
+        }
+
+        unsafe fn deallocate(&self, _ptr: *mut c_void, _bytes: usize) -> HipResult<()> {
+            panic!("deallocate called for memory that was never allocated");
+        }
+    }
+
+    #[test]
+    fn test_alloc_in_zero_sized_type() {
+        let ptr = MemoryPointer::<(), _>::alloc_in(16, RejectingAllocator).unwrap();
+        assert!(ptr.as_pointer().is_null());
+        assert_eq!(ptr.size(), 16);
+        drop(ptr);
+
+        let ptr = MemoryPointer::<u8, _>::alloc_in(0, RejectingAllocator).unwrap();
+        assert_eq!(ptr.size(), 0);
+    }
+}
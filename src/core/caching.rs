@@ -0,0 +1,800 @@
+use super::allocator::{DeviceAllocator, DeviceMalloc};
+use super::result::{HipError, HipResult, HipStatus};
+use crate::{Event, Stream};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::c_void;
+use std::sync::{Mutex, MutexGuard};
+
+/// Every block is a multiple of this size
+const MIN_BLOCK_SIZE: usize = 512;
+/// Requests up to this size are served from small segments
+const SMALL_SIZE: usize = 1 << 20;
+/// Size of the segments small requests are carved from
+const SMALL_SEGMENT_SIZE: usize = 2 << 20;
+/// Size of the segments medium sized large requests are carved from
+const LARGE_SEGMENT_SIZE: usize = 20 << 20;
+/// Large requests from this size on get a segment of their own
+const MIN_LARGE_ALLOCATION: usize = 10 << 20;
+/// Dedicated segments are rounded up to a multiple of this size
+const LARGE_ROUNDING: usize = 2 << 20;
+/// One size-class bin per power of two
+const NUM_BINS: usize = usize::BITS as usize;
+
+/// Usage counters of a [`CachingAllocator`], in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheStats {
+    /// Memory handed out to live allocations, including rounding
+    pub allocated: usize,
+    /// Memory obtained from the upstream allocator and held by the cache
+    pub reserved: usize,
+    /// High-water mark of `allocated`
+    pub peak_allocated: usize,
+    /// High-water mark of `reserved`
+    pub peak_reserved: usize,
+    /// Number of segments obtained from the upstream allocator
+    pub segments: usize,
+    /// Share of the cached free memory that is not part of the largest free
+    /// block, between `0.0` (one contiguous free block) and `1.0`
+    pub fragmentation: f64,
+}
+
+/// Rounds a request up to the block granularity.
+fn round_size(bytes: usize) -> usize {
+    bytes.max(1).div_ceil(MIN_BLOCK_SIZE) * MIN_BLOCK_SIZE
+}
+
+/// Returns the size of the segment to allocate for a rounded request.
+fn segment_size(size: usize) -> usize {
+    if size <= SMALL_SIZE {
+        SMALL_SEGMENT_SIZE
+    } else if size < MIN_LARGE_ALLOCATION {
+        LARGE_SEGMENT_SIZE
+    } else {
+        size.div_ceil(LARGE_ROUNDING) * LARGE_ROUNDING
+    }
+}
+
+/// Returns the bin a block of `size` bytes is filed under.
+fn size_class(size: usize) -> usize {
+    (usize::BITS - 1 - size.leading_zeros()) as usize
+}
+
+/// Segments are kept apart by kind so small requests don't fragment large segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SegmentKind {
+    Small,
+    Large,
+}
+
+impl SegmentKind {
+    fn for_size(size: usize) -> Self {
+        if size <= SMALL_SIZE {
+            Self::Small
+        } else {
+            Self::Large
+        }
+    }
+
+    /// Returns true if the rest of a block is worth keeping as a free block.
+    fn should_split(&self, remaining: usize) -> bool {
+        match self {
+            Self::Small => remaining >= MIN_BLOCK_SIZE,
+            Self::Large => remaining > SMALL_SIZE,
+        }
+    }
+}
+
+/// Free lists are kept per stream and segment kind.
+type FreeListKey = (usize, SegmentKind);
+
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    size: usize,
+    stream: usize,
+    kind: SegmentKind,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Block {
+    size: usize,
+    segment: usize,
+    allocated: bool,
+}
+
+/// Free blocks of one stream, binned by power-of-two size class.
+#[derive(Debug)]
+struct FreeBins {
+    bins: Vec<BTreeSet<(usize, usize)>>,
+}
+
+impl FreeBins {
+    fn new() -> Self {
+        Self {
+            bins: vec![BTreeSet::new(); NUM_BINS],
+        }
+    }
+
+    fn insert(&mut self, addr: usize, size: usize) {
+        self.bins[size_class(size)].insert((size, addr));
+    }
+
+    fn remove(&mut self, addr: usize, size: usize) {
+        self.bins[size_class(size)].remove(&(size, addr));
+    }
+
+    /// Finds the smallest free block of at least `size` bytes.
+    fn best_fit(&self, size: usize) -> Option<(usize, usize)> {
+        self.bins[size_class(size)..]
+            .iter()
+            .find_map(|bin| bin.range((size, 0)..).next().copied())
+    }
+}
+
+/// Block bookkeeping over plain address ranges.
+///
+/// Contains no HIP calls, segments are handed in by the caller.
+#[derive(Debug)]
+struct BlockPool {
+    segments: BTreeMap<usize, Segment>,
+    blocks: BTreeMap<usize, Block>,
+    free: HashMap<FreeListKey, FreeBins>,
+    allocated: usize,
+    reserved: usize,
+    peak_allocated: usize,
+    peak_reserved: usize,
+}
+
+impl BlockPool {
+    fn new() -> Self {
+        Self {
+            segments: BTreeMap::new(),
+            blocks: BTreeMap::new(),
+            free: HashMap::new(),
+            allocated: 0,
+            reserved: 0,
+            peak_allocated: 0,
+            peak_reserved: 0,
+        }
+    }
+
+    fn free_list_key(&self, segment: usize) -> FreeListKey {
+        let segment = &self.segments[&segment];
+        (segment.stream, segment.kind)
+    }
+
+    fn insert_free(&mut self, addr: usize, block: Block) {
+        let key = self.free_list_key(block.segment);
+        self.free
+            .entry(key)
+            .or_insert_with(FreeBins::new)
+            .insert(addr, block.size);
+    }
+
+    fn remove_free(&mut self, addr: usize, block: Block) {
+        let key = self.free_list_key(block.segment);
+        if let Some(bins) = self.free.get_mut(&key) {
+            bins.remove(addr, block.size);
+        }
+    }
+
+    /// Registers a segment of `size` bytes at `base` as one free block owned by `stream`.
+    fn add_segment(&mut self, stream: usize, base: usize, size: usize) {
+        let kind = if size <= SMALL_SEGMENT_SIZE {
+            SegmentKind::Small
+        } else {
+            SegmentKind::Large
+        };
+        self.segments.insert(base, Segment { size, stream, kind });
+        let block = Block {
+            size,
+            segment: base,
+            allocated: false,
+        };
+        self.blocks.insert(base, block);
+        self.insert_free(base, block);
+
+        self.reserved += size;
+        self.peak_reserved = self.peak_reserved.max(self.reserved);
+    }
+
+    /// Hands out a cached block of at least `size` bytes on `stream`,
+    /// splitting off the remainder if it is large enough to be reused.
+    ///
+    /// Returns the address of the block, or `None` if no cached block fits.
+    fn allocate(&mut self, stream: usize, size: usize) -> Option<usize> {
+        let kind = SegmentKind::for_size(size);
+        let (block_size, addr) = self.free.get(&(stream, kind))?.best_fit(size)?;
+
+        let mut block = self.blocks[&addr];
+        self.remove_free(addr, block);
+
+        let remaining = block_size - size;
+        if kind.should_split(remaining) {
+            let rest = Block {
+                size: remaining,
+                segment: block.segment,
+                allocated: false,
+            };
+            self.blocks.insert(addr + size, rest);
+            self.insert_free(addr + size, rest);
+            block.size = size;
+        }
+
+        block.allocated = true;
+        self.blocks.insert(addr, block);
+
+        self.allocated += block.size;
+        self.peak_allocated = self.peak_allocated.max(self.allocated);
+        Some(addr)
+    }
+
+    /// Returns the block at `addr` to its stream's free list, merging it with
+    /// free neighbours in the same segment.
+    ///
+    /// Returns the size of the freed block, or `None` if `addr` is not a live
+    /// allocation, e.g. on a double free.
+    fn free(&mut self, addr: usize) -> Option<usize> {
+        let block = *self.blocks.get(&addr).filter(|block| block.allocated)?;
+        self.allocated -= block.size;
+
+        let mut start = addr;
+        let mut merged = Block {
+            allocated: false,
+            ..block
+        };
+
+        let next_addr = addr + block.size;
+        if let Some(next) = self.free_neighbour(next_addr, block.segment) {
+            self.remove_free(next_addr, next);
+            self.blocks.remove(&next_addr);
+            merged.size += next.size;
+        }
+
+        let previous = self
+            .blocks
+            .range(..addr)
+            .next_back()
+            .map(|(&prev_addr, &prev)| (prev_addr, prev));
+        if let Some((prev_addr, prev)) = previous {
+            if prev_addr + prev.size == addr && !prev.allocated && prev.segment == block.segment {
+                self.remove_free(prev_addr, prev);
+                self.blocks.remove(&addr);
+                start = prev_addr;
+                merged.size += prev.size;
+            }
+        }
+
+        self.blocks.insert(start, merged);
+        self.insert_free(start, merged);
+        Some(block.size)
+    }
+
+    fn free_neighbour(&self, addr: usize, segment: usize) -> Option<Block> {
+        self.blocks
+            .get(&addr)
+            .copied()
+            .filter(|block| !block.allocated && block.segment == segment)
+    }
+
+    /// Returns the size of the live allocation at `addr`.
+    fn allocation_size(&self, addr: usize) -> Option<usize> {
+        self.blocks
+            .get(&addr)
+            .filter(|block| block.allocated)
+            .map(|block| block.size)
+    }
+
+    /// Forgets all segments without live allocations and returns them as
+    /// `(base, size)` pairs so they can be released upstream.
+    fn release_free_segments(&mut self) -> Vec<(usize, usize)> {
+        let released: Vec<(usize, usize)> = self
+            .segments
+            .iter()
+            .filter(|(base, segment)| {
+                self.blocks
+                    .get(base)
+                    .is_some_and(|block| !block.allocated && block.size == segment.size)
+            })
+            .map(|(&base, segment)| (base, segment.size))
+            .collect();
+
+        for &(base, size) in &released {
+            let block = self.blocks[&base];
+            self.remove_free(base, block);
+            self.blocks.remove(&base);
+            self.segments.remove(&base);
+            self.reserved -= size;
+        }
+        self.free
+            .retain(|_, bins| bins.bins.iter().any(|bin| !bin.is_empty()));
+
+        released
+    }
+
+    fn stats(&self) -> CacheStats {
+        let free = self.reserved - self.allocated;
+        let largest_free = self
+            .free
+            .values()
+            .flat_map(|bins| bins.bins.iter().filter_map(|bin| bin.last()))
+            .map(|&(size, _)| size)
+            .max()
+            .unwrap_or(0);
+        let fragmentation = if free == 0 {
+            0.0
+        } else {
+            1.0 - largest_free as f64 / free as f64
+        };
+
+        CacheStats {
+            allocated: self.allocated,
+            reserved: self.reserved,
+            peak_allocated: self.peak_allocated,
+            peak_reserved: self.peak_reserved,
+            segments: self.segments.len(),
+            fragmentation,
+        }
+    }
+
+    fn reset_peak_stats(&mut self) {
+        self.peak_allocated = self.allocated;
+        self.peak_reserved = self.reserved;
+    }
+}
+
+/// A freed block that may still be in use on other streams.
+#[derive(Debug)]
+struct PendingFree {
+    addr: usize,
+    events: Vec<Event>,
+}
+
+#[derive(Debug)]
+struct CacheState {
+    pool: BlockPool,
+    /// Events recorded by [`CachingAllocator::record_stream`], by block address
+    stream_uses: HashMap<usize, Vec<Event>>,
+    pending: Vec<PendingFree>,
+}
+
+impl CacheState {
+    /// Returns blocks whose cross-stream uses have completed to the free lists.
+    fn process_pending(&mut self) -> HipResult<()> {
+        let mut index = 0;
+        while index < self.pending.len() {
+            let mut done = true;
+            for event in &self.pending[index].events {
+                if !event.query()? {
+                    done = false;
+                    break;
+                }
+            }
+
+            if done {
+                let pending = self.pending.swap_remove(index);
+                self.pool.free(pending.addr);
+            } else {
+                index += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for all cross-stream uses and returns the blocks to the free lists.
+    fn synchronize_pending(&mut self) -> HipResult<()> {
+        for pending in std::mem::take(&mut self.pending) {
+            for event in &pending.events {
+                event.synchronize()?;
+            }
+            self.pool.free(pending.addr);
+        }
+        Ok(())
+    }
+}
+
+/// A caching sub-allocator that keeps freed device memory for reuse instead
+/// of returning it to the upstream allocator.
+///
+/// Requests are rounded to 512 bytes and carved out of larger segments
+/// obtained from the upstream allocator, `hipMalloc` by default. Freed blocks
+/// are merged with free neighbours and filed in size-class bins per stream,
+/// so a block is only reused by the stream it was freed on. Memory used on
+/// other streams is marked with [`CachingAllocator::record_stream`] and is
+/// held back until those uses have completed.
+///
+/// Cached memory is only returned upstream by [`CachingAllocator::empty_cache`],
+/// when an upstream allocation fails, or when the allocator is dropped.
+///
+/// # Examples
+/// ```
+/// use hip_rs::{CachingAllocator, MemoryPointer, Stream};
+///
+/// let cache = CachingAllocator::new();
+/// let stream = Stream::create().unwrap();
+///
+/// let a = MemoryPointer::<f32, _>::alloc_in(1024, &cache).unwrap();
+/// let b = MemoryPointer::<f32, _>::alloc_in(1024, cache.on_stream(&stream)).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct CachingAllocator<A: DeviceAllocator = DeviceMalloc> {
+    upstream: A,
+    state: Mutex<CacheState>,
+}
+
+impl CachingAllocator {
+    /// Creates a caching allocator on top of `hipMalloc`.
+    pub fn new() -> Self {
+        Self::with_upstream(DeviceMalloc)
+    }
+}
+
+impl Default for CachingAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: DeviceAllocator> CachingAllocator<A> {
+    /// Creates a caching allocator obtaining its segments from `upstream`.
+    pub fn with_upstream(upstream: A) -> Self {
+        Self {
+            upstream,
+            state: Mutex::new(CacheState {
+                pool: BlockPool::new(),
+                stream_uses: HashMap::new(),
+                pending: Vec::new(),
+            }),
+        }
+    }
+
+    /// Returns an allocator handle that orders its allocations on `stream`.
+    ///
+    /// Blocks freed through the handle are only reused by `stream`.
+    pub fn on_stream<'a>(&'a self, stream: &'a Stream) -> CachingStreamAllocator<'a, A> {
+        CachingStreamAllocator {
+            cache: self,
+            stream,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        // The state stays consistent across panics, each update is a single step
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn allocate_on(&self, stream: usize, bytes: usize) -> HipResult<*mut c_void> {
+        let size = round_size(bytes);
+        let mut state = self.lock();
+        state.process_pending()?;
+
+        if let Some(addr) = state.pool.allocate(stream, size) {
+            return Ok(addr as *mut c_void);
+        }
+
+        let segment = segment_size(size);
+        let base = match self.upstream.allocate(segment) {
+            Ok(base) => base,
+            Err(e) if e.status == HipStatus::MemoryAllocation => {
+                // Return cached memory upstream and try once more
+                self.release_cached(&mut state)?;
+                self.upstream.allocate(segment)?
+            }
+            Err(e) => return Err(e),
+        };
+
+        state.pool.add_segment(stream, base as usize, segment);
+        let addr = state
+            .pool
+            .allocate(stream, size)
+            .expect("a fresh segment fits the request");
+        Ok(addr as *mut c_void)
+    }
+
+    fn deallocate_block(&self, ptr: *mut c_void) -> HipResult<()> {
+        let addr = ptr as usize;
+        let mut state = self.lock();
+
+        if state.pool.allocation_size(addr).is_none() {
+            return Err(HipError::from_status(HipStatus::InvalidValue));
+        }
+
+        match state.stream_uses.remove(&addr) {
+            Some(events) => state.pending.push(PendingFree { addr, events }),
+            None => {
+                state.pool.free(addr);
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks an allocation as used by work already submitted to `stream`.
+    ///
+    /// Call this after submitting the last work that uses the memory on a
+    /// stream other than the one it was allocated on. Once freed, the block
+    /// is not reused until that work has completed.
+    ///
+    /// # Arguments
+    /// * `ptr` - A pointer allocated from this cache
+    /// * `stream` - The stream the memory is used on
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the use was recorded
+    /// * `Err(HipError)` - If `ptr` is not a live allocation of this cache
+    pub fn record_stream<T>(&self, ptr: *const T, stream: &Stream) -> HipResult<()> {
+        let addr = ptr as usize;
+        let mut state = self.lock();
+
+        if state.pool.allocation_size(addr).is_none() {
+            return Err(HipError::from_status(HipStatus::InvalidValue));
+        }
+
+        let event = Event::create_with_flags(crate::EventFlags::DISABLE_TIMING)?;
+        event.record(stream)?;
+        state.stream_uses.entry(addr).or_default().push(event);
+        Ok(())
+    }
+
+    /// Returns all cached segments without live allocations to the upstream
+    /// allocator.
+    ///
+    /// Waits for pending cross-stream uses of freed blocks first.
+    pub fn empty_cache(&self) -> HipResult<()> {
+        let mut state = self.lock();
+        self.release_cached(&mut state)
+    }
+
+    fn release_cached(&self, state: &mut CacheState) -> HipResult<()> {
+        state.synchronize_pending()?;
+        for (base, size) in state.pool.release_free_segments() {
+            unsafe { self.upstream.deallocate(base as *mut c_void, size)? };
+        }
+        Ok(())
+    }
+
+    /// Returns the current usage counters of the cache.
+    pub fn stats(&self) -> CacheStats {
+        self.lock().pool.stats()
+    }
+
+    /// Resets the peak counters to the current usage.
+    pub fn reset_peak_stats(&self) {
+        self.lock().pool.reset_peak_stats();
+    }
+}
+
+/// Allocations and frees made through the cache itself use the null stream.
+unsafe impl<A: DeviceAllocator> DeviceAllocator for CachingAllocator<A> {
+    fn allocate(&self, bytes: usize) -> HipResult<*mut c_void> {
+        self.allocate_on(0, bytes)
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut c_void, _bytes: usize) -> HipResult<()> {
+        self.deallocate_block(ptr)
+    }
+}
+
+impl<A: DeviceAllocator> Drop for CachingAllocator<A> {
+    fn drop(&mut self) {
+        let state = self.state.get_mut().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = state.synchronize_pending() {
+            log::error!("CachingAllocator failed to wait for pending frees: {:?}", e);
+        }
+
+        // Live allocations borrow the cache, so every segment is free here
+        for (base, size) in state.pool.release_free_segments() {
+            if let Err(e) = unsafe { self.upstream.deallocate(base as *mut c_void, size) } {
+                log::error!("CachingAllocator failed to release a segment: {:?}", e);
+            }
+        }
+    }
+}
+
+/// A handle to a [`CachingAllocator`] that orders allocations on a stream.
+///
+/// Created with [`CachingAllocator::on_stream`].
+#[derive(Debug)]
+pub struct CachingStreamAllocator<'a, A: DeviceAllocator = DeviceMalloc> {
+    cache: &'a CachingAllocator<A>,
+    stream: &'a Stream,
+}
+
+impl<A: DeviceAllocator> Clone for CachingStreamAllocator<'_, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A: DeviceAllocator> Copy for CachingStreamAllocator<'_, A> {}
+
+unsafe impl<A: DeviceAllocator> DeviceAllocator for CachingStreamAllocator<'_, A> {
+    fn allocate(&self, bytes: usize) -> HipResult<*mut c_void> {
+        self.cache.allocate_on(self.stream.handle() as usize, bytes)
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut c_void, _bytes: usize) -> HipResult<()> {
+        self.cache.deallocate_block(ptr)
+    }
+
+    fn stream(&self) -> Option<&Stream> {
+        Some(self.stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryPointer;
+
+    const BASE: usize = 0x1000_0000;
+
+    #[test]
+    fn test_round_size_and_segment_size() {
+        assert_eq!(round_size(0), MIN_BLOCK_SIZE);
+        assert_eq!(round_size(1), MIN_BLOCK_SIZE);
+        assert_eq!(round_size(513), 1024);
+        assert_eq!(segment_size(4096), SMALL_SEGMENT_SIZE);
+        assert_eq!(segment_size(SMALL_SIZE + 512), LARGE_SEGMENT_SIZE);
+        assert_eq!(segment_size(11 << 20), 12 << 20);
+        assert_eq!(size_class(512), 9);
+        assert_eq!(size_class(1023), 9);
+        assert_eq!(size_class(1024), 10);
+    }
+
+    #[test]
+    fn test_split_and_reuse() {
+        let mut pool = BlockPool::new();
+        pool.add_segment(0, BASE, SMALL_SEGMENT_SIZE);
+
+        let a = pool.allocate(0, 1024).unwrap();
+        let b = pool.allocate(0, 2048).unwrap();
+        assert_eq!(a, BASE);
+        assert_eq!(b, BASE + 1024);
+        assert_eq!(pool.stats().allocated, 3072);
+
+        // The freed block is the best fit for a request of the same size
+        pool.free(a).unwrap();
+        assert_eq!(pool.allocate(0, 1024), Some(BASE));
+    }
+
+    #[test]
+    fn test_coalesce_neighbours() {
+        let mut pool = BlockPool::new();
+        pool.add_segment(0, BASE, SMALL_SEGMENT_SIZE);
+
+        let a = pool.allocate(0, 1024).unwrap();
+        let b = pool.allocate(0, 1024).unwrap();
+        let c = pool.allocate(0, 1024).unwrap();
+
+        pool.free(a).unwrap();
+        pool.free(c).unwrap();
+        assert!(pool.stats().fragmentation > 0.0);
+
+        // Freeing the middle block merges all three with the segment tail
+        pool.free(b).unwrap();
+        assert_eq!(pool.blocks.len(), 1);
+        let stats = pool.stats();
+        assert_eq!(stats.allocated, 0);
+        assert_eq!(stats.fragmentation, 0.0);
+    }
+
+    #[test]
+    fn test_no_coalescing_across_segments() {
+        let mut pool = BlockPool::new();
+        // Two segments that happen to be adjacent in the address space
+        pool.add_segment(0, BASE, SMALL_SEGMENT_SIZE);
+        pool.add_segment(0, BASE + SMALL_SEGMENT_SIZE, SMALL_SEGMENT_SIZE);
+
+        let half = SMALL_SEGMENT_SIZE / 2;
+        let a = pool.allocate(0, half).unwrap();
+        let b = pool.allocate(0, half).unwrap();
+        let c = pool.allocate(0, half).unwrap();
+        assert_eq!(b + half, c);
+
+        // `b` ends the first segment and `c` starts the second one
+        pool.free(b).unwrap();
+        pool.free(c).unwrap();
+        assert_eq!(pool.blocks.len(), 3);
+
+        pool.free(a).unwrap();
+        assert_eq!(pool.release_free_segments().len(), 2);
+        assert_eq!(pool.stats().reserved, 0);
+    }
+
+    #[test]
+    fn test_per_stream_free_lists() {
+        let mut pool = BlockPool::new();
+        pool.add_segment(1, BASE, SMALL_SEGMENT_SIZE);
+
+        let a = pool.allocate(1, 4096).unwrap();
+        pool.free(a).unwrap();
+
+        // Blocks freed on stream 1 are not handed to stream 2
+        assert_eq!(pool.allocate(2, 4096), None);
+        assert_eq!(pool.allocate(1, 4096), Some(a));
+    }
+
+    #[test]
+    fn test_small_and_large_segments_are_separate() {
+        let mut pool = BlockPool::new();
+        pool.add_segment(0, BASE, LARGE_SEGMENT_SIZE);
+
+        assert_eq!(pool.allocate(0, 4096), None);
+        assert_eq!(pool.allocate(0, 4 << 20), Some(BASE));
+    }
+
+    #[test]
+    fn test_large_remainders_below_threshold_are_not_split() {
+        let mut pool = BlockPool::new();
+        pool.add_segment(0, BASE, 12 << 20);
+
+        let a = pool.allocate(0, (12 << 20) - 4096).unwrap();
+        assert_eq!(pool.allocation_size(a), Some(12 << 20));
+    }
+
+    #[test]
+    fn test_double_free() {
+        let mut pool = BlockPool::new();
+        pool.add_segment(0, BASE, SMALL_SEGMENT_SIZE);
+
+        let a = pool.allocate(0, 1024).unwrap();
+        assert_eq!(pool.free(a), Some(1024));
+        assert_eq!(pool.free(a), None);
+        assert_eq!(pool.free(BASE + 512), None);
+    }
+
+    #[test]
+    fn test_peak_and_release() {
+        let mut pool = BlockPool::new();
+        pool.add_segment(0, BASE, SMALL_SEGMENT_SIZE);
+
+        let a = pool.allocate(0, 8192).unwrap();
+        // Segments with live allocations are kept
+        assert!(pool.release_free_segments().is_empty());
+        pool.free(a).unwrap();
+
+        let stats = pool.stats();
+        assert_eq!(stats.peak_allocated, 8192);
+        assert_eq!(stats.peak_reserved, SMALL_SEGMENT_SIZE);
+
+        assert_eq!(
+            pool.release_free_segments(),
+            vec![(BASE, SMALL_SEGMENT_SIZE)]
+        );
+        pool.reset_peak_stats();
+        assert_eq!(pool.stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn test_caching_allocator_reuses_memory() {
+        let cache = CachingAllocator::new();
+
+        let first = MemoryPointer::<f32, _>::alloc_in(1024, &cache).unwrap();
+        let address = first.as_pointer();
+        drop(first);
+
+        let second = MemoryPointer::<f32, _>::alloc_in(1024, &cache).unwrap();
+        assert_eq!(second.as_pointer(), address);
+        assert_eq!(cache.stats().segments, 1);
+        drop(second);
+
+        cache.empty_cache().unwrap();
+        assert_eq!(cache.stats().reserved, 0);
+    }
+
+    #[test]
+    fn test_caching_allocator_record_stream() {
+        let cache = CachingAllocator::new();
+        let stream = Stream::create().unwrap();
+        let other = Stream::create().unwrap();
+
+        let ptr = MemoryPointer::<u8, _>::alloc_in(4096, cache.on_stream(&stream)).unwrap();
+        cache.record_stream(ptr.as_pointer(), &other).unwrap();
+        drop(ptr);
+
+        other.synchronize().unwrap();
+        cache.empty_cache().unwrap();
+        assert_eq!(cache.stats().segments, 0);
+    }
+}
@@ -0,0 +1,319 @@
+use super::allocator::DeviceAllocator;
+use super::device_copy::DeviceCopy;
+use super::managed::ManagedBuffer;
+use super::memory::{memory_copy, MemoryCopyKind, MemoryPointer};
+use super::pinned::PinnedBuffer;
+use super::result::{HipError, HipResult, HipStatus};
+use super::slice::DeviceSlice;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Where the memory of a buffer type lives, known at compile time.
+///
+/// Implemented by [`HostMemory`], [`DeviceMemory`] and [`ManagedMemory`].
+pub trait MemoryLocation: sealed::Sealed {}
+
+/// Host memory, pageable or pinned.
+#[derive(Debug, Clone, Copy)]
+pub struct HostMemory;
+
+/// Device memory.
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceMemory;
+
+/// Managed memory, addressable from the host and from every device.
+#[derive(Debug, Clone, Copy)]
+pub struct ManagedMemory;
+
+impl sealed::Sealed for HostMemory {}
+impl sealed::Sealed for DeviceMemory {}
+impl sealed::Sealed for ManagedMemory {}
+impl MemoryLocation for HostMemory {}
+impl MemoryLocation for DeviceMemory {}
+impl MemoryLocation for ManagedMemory {}
+
+/// The copy direction for copies into `Self` from `Src`.
+pub trait CopyDirection<Src: MemoryLocation>: MemoryLocation {
+    /// The kind passed to `hipMemcpy`
+    const KIND: MemoryCopyKind;
+}
+
+macro_rules! copy_direction {
+    ($($dst:ty, $src:ty => $kind:ident;)*) => {
+        $(impl CopyDirection<$src> for $dst {
+            const KIND: MemoryCopyKind = MemoryCopyKind::$kind;
+        })*
+    };
+}
+
+// Managed memory lives in the unified address space, so the runtime can
+// always infer the direction of copies involving it
+copy_direction! {
+    HostMemory, HostMemory => HostToHost;
+    DeviceMemory, HostMemory => HostToDevice;
+    HostMemory, DeviceMemory => DeviceToHost;
+    DeviceMemory, DeviceMemory => DeviceToDevice;
+    ManagedMemory, HostMemory => Default;
+    ManagedMemory, DeviceMemory => Default;
+    ManagedMemory, ManagedMemory => Default;
+    HostMemory, ManagedMemory => Default;
+    DeviceMemory, ManagedMemory => Default;
+}
+
+/// A buffer that can be copied from with [`copy`].
+///
+/// # Safety
+/// `copy_pointer` must point to `copy_len` initialized elements in memory of
+/// kind `Location`.
+pub unsafe trait CopySource {
+    /// The element type
+    type Elem: DeviceCopy;
+    /// Where the memory lives
+    type Location: MemoryLocation;
+
+    /// Returns a pointer to the first element.
+    fn copy_pointer(&self) -> *const Self::Elem;
+
+    /// Returns the number of elements.
+    fn copy_len(&self) -> usize;
+}
+
+/// A buffer that can be copied into with [`copy`].
+///
+/// # Safety
+/// `copy_mut_pointer` must point to `copy_len` writable elements in memory of
+/// kind `Location`.
+pub unsafe trait CopyDestination {
+    /// The element type
+    type Elem: DeviceCopy;
+    /// Where the memory lives
+    type Location: MemoryLocation;
+
+    /// Returns a pointer to the first element.
+    fn copy_mut_pointer(&mut self) -> *mut Self::Elem;
+
+    /// Returns the number of elements.
+    fn copy_len(&self) -> usize;
+}
+
+macro_rules! impl_copy_buffer {
+    ($location:ty, [$($generics:tt)*] $buffer:ty, $pointer:expr, $mut_pointer:expr, $len:expr) => {
+        unsafe impl<$($generics)*> CopySource for $buffer {
+            type Elem = T;
+            type Location = $location;
+
+            fn copy_pointer(&self) -> *const T {
+                $pointer(self)
+            }
+
+            fn copy_len(&self) -> usize {
+                $len(self)
+            }
+        }
+
+        unsafe impl<$($generics)*> CopyDestination for $buffer {
+            type Elem = T;
+            type Location = $location;
+
+            fn copy_mut_pointer(&mut self) -> *mut T {
+                $mut_pointer(self)
+            }
+
+            fn copy_len(&self) -> usize {
+                $len(self)
+            }
+        }
+    };
+}
+
+impl_copy_buffer!(
+    HostMemory,
+    [T: DeviceCopy] [T],
+    <[T]>::as_ptr,
+    <[T]>::as_mut_ptr,
+    <[T]>::len
+);
+impl_copy_buffer!(
+    HostMemory,
+    [T: DeviceCopy] Vec<T>,
+    Vec::as_ptr,
+    Vec::as_mut_ptr,
+    Vec::len
+);
+impl_copy_buffer!(
+    HostMemory,
+    [T: DeviceCopy] PinnedBuffer<T>,
+    |buffer: &PinnedBuffer<T>| buffer.as_pointer() as *const T,
+    PinnedBuffer::as_pointer,
+    PinnedBuffer::len
+);
+impl_copy_buffer!(
+    ManagedMemory,
+    [T: DeviceCopy] ManagedBuffer<T>,
+    |buffer: &ManagedBuffer<T>| buffer.as_pointer() as *const T,
+    ManagedBuffer::as_pointer,
+    ManagedBuffer::len
+);
+impl_copy_buffer!(
+    DeviceMemory,
+    [T: DeviceCopy, A: DeviceAllocator] MemoryPointer<T, A>,
+    |pointer: &MemoryPointer<T, A>| pointer.as_pointer() as *const T,
+    MemoryPointer::as_pointer,
+    MemoryPointer::size
+);
+
+// Slices borrow memory immutably, so they can only be copied from
+unsafe impl<T: DeviceCopy> CopySource for DeviceSlice<'_, T> {
+    type Elem = T;
+    type Location = DeviceMemory;
+
+    fn copy_pointer(&self) -> *const T {
+        self.as_pointer() as *const T
+    }
+
+    fn copy_len(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Copies all elements of `src` to the start of `dst`.
+///
+/// The copy direction is derived from the buffer types, so it can never
+/// disagree with where the memory actually lives. `hipMemcpyDefault` is only
+/// used when one side is managed memory.
+///
+/// # Arguments
+/// * `dst` - The destination buffer, at least as long as `src`
+/// * `src` - The source buffer
+///
+/// # Returns
+/// * `Ok(())` - If the copy was successful
+/// * `Err(HipError)` - If `dst` is too short or the copy failed
+///
+/// # Examples
+/// ```
+/// use hip_rs::{copy, MemoryPointer};
+///
+/// let host = vec![1.0f32; 1024];
+/// let mut device = MemoryPointer::<f32>::alloc(1024).unwrap();
+/// let mut result = vec![0.0f32; 1024];
+///
+/// copy(&mut device, &host[..]).unwrap();
+/// copy(&mut result[..], &device).unwrap();
+/// assert_eq!(host, result);
+/// ```
+///
+/// Element types must match:
+/// ```compile_fail
+/// use hip_rs::{copy, MemoryPointer};
+///
+/// let host = vec![1u32; 16];
+/// let mut device = MemoryPointer::<f32>::alloc(16).unwrap();
+/// copy(&mut device, &host[..]).unwrap();
+/// ```
+pub fn copy<D, S>(dst: &mut D, src: &S) -> HipResult<()>
+where
+    D: CopyDestination + ?Sized,
+    S: CopySource<Elem = D::Elem> + ?Sized,
+    D::Location: CopyDirection<S::Location>,
+{
+    let len = src.copy_len();
+    if dst.copy_len() < len {
+        return Err(HipError::from_status(HipStatus::InvalidValue));
+    }
+    if len == 0 {
+        return Ok(());
+    }
+
+    unsafe {
+        memory_copy(
+            dst.copy_mut_pointer() as *mut std::ffi::c_void,
+            src.copy_pointer() as *const std::ffi::c_void,
+            len * std::mem::size_of::<S::Elem>(),
+            copy_kind::<D, S>(),
+        )
+    }
+}
+
+/// Returns the `hipMemcpy` kind for a copy from `S` to `D`.
+pub fn copy_kind<D, S>() -> MemoryCopyKind
+where
+    D: CopyDestination + ?Sized,
+    S: CopySource + ?Sized,
+    D::Location: CopyDirection<S::Location>,
+{
+    <D::Location as CopyDirection<S::Location>>::KIND
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_kinds() {
+        type DeviceBuffer = MemoryPointer<f32>;
+        type Slice = [f32];
+
+        assert_eq!(
+            copy_kind::<DeviceBuffer, Slice>(),
+            MemoryCopyKind::HostToDevice
+        );
+        assert_eq!(
+            copy_kind::<Slice, DeviceBuffer>(),
+            MemoryCopyKind::DeviceToHost
+        );
+        assert_eq!(
+            copy_kind::<DeviceBuffer, DeviceBuffer>(),
+            MemoryCopyKind::DeviceToDevice
+        );
+        assert_eq!(copy_kind::<Vec<f32>, Slice>(), MemoryCopyKind::HostToHost);
+        assert_eq!(
+            copy_kind::<PinnedBuffer<f32>, DeviceBuffer>(),
+            MemoryCopyKind::DeviceToHost
+        );
+        assert_eq!(
+            copy_kind::<ManagedBuffer<f32>, DeviceBuffer>(),
+            MemoryCopyKind::Default
+        );
+        assert_eq!(
+            copy_kind::<Slice, ManagedBuffer<f32>>(),
+            MemoryCopyKind::Default
+        );
+    }
+
+    #[test]
+    fn test_copy_length_mismatch() {
+        let src = [1u8; 8];
+        let mut dst = [0u8; 4];
+        let result = copy(&mut dst[..], &src[..]);
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+    }
+
+    #[test]
+    fn test_copy_roundtrip() {
+        let host: Vec<u32> = (0..1024).collect();
+        let mut device = MemoryPointer::<u32>::alloc(1024).unwrap();
+        let mut pinned = PinnedBuffer::<u32>::alloc(1024).unwrap();
+
+        copy(&mut device, &host).unwrap();
+        copy(&mut pinned, &device).unwrap();
+        assert_eq!(&pinned[..], &host[..]);
+
+        let mut tail = [0u32; 24];
+        copy(&mut tail[..], &device.slice(1000..).unwrap()).unwrap();
+        assert_eq!(&tail[..], &host[1000..]);
+    }
+
+    #[test]
+    fn test_copy_managed() {
+        let mut managed = ManagedBuffer::<f64>::alloc(16).unwrap();
+        let mut device = MemoryPointer::<f64>::alloc(16).unwrap();
+        let host = [2.5f64; 16];
+
+        copy(&mut device, &host[..]).unwrap();
+        copy(&mut managed, &device).unwrap();
+        assert_eq!(&managed[..], &host[..]);
+    }
+}
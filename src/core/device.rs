@@ -1,6 +1,6 @@
 #[allow(unused_imports)]
 use super::result::{HipError, HipResult, HipStatus};
-use super::{DeviceP2PAttribute, MemPool, PCIBusId};
+use super::{DeviceAttribute, DeviceP2PAttribute, MemPool, PCIBusId};
 use crate::result::ResultExt;
 use crate::sys;
 use semver::Version;
@@ -72,6 +72,31 @@ impl Device {
         }
     }
 
+    /// Queries a device property.
+    ///
+    /// # Arguments
+    /// * `attr` - The [`DeviceAttribute`] to query
+    ///
+    /// # Returns
+    /// * `Ok(i32)` - The attribute value
+    /// * `Err(HipError)` - If the device is invalid or the attribute is unsupported
+    ///
+    /// # Examples
+    /// ```
+    /// use hip_rs::{get_device, DeviceAttribute};
+    ///
+    /// let device = get_device().unwrap();
+    /// let max_threads = device.attribute(DeviceAttribute::MaxThreadsPerBlock).unwrap();
+    /// assert!(max_threads > 0);
+    /// ```
+    pub fn attribute(&self, attr: DeviceAttribute) -> HipResult<i32> {
+        let mut value = 0;
+        unsafe {
+            let code = sys::hipDeviceGetAttribute(&mut value, attr.into(), self.id);
+            (value, code).to_result()
+        }
+    }
+
     /// Gets the name of the device.
     ///
     ///
@@ -95,6 +120,27 @@ impl Device {
         }
     }
 
+    /// Gets the target ID of the device, e.g. `gfx90a:sramecc+:xnack-`.
+    ///
+    /// This is the architecture name code objects are compiled for, including
+    /// the feature settings the device runs with.
+    ///
+    /// # Returns
+    /// * `Result<String>` - The target ID if successful
+    ///
+    /// # Errors
+    /// Returns `HipError` if:
+    /// * The device ID is invalid
+    /// * There was an error retrieving the device properties
+    pub fn gcn_arch_name(&self) -> HipResult<String> {
+        unsafe {
+            let mut props: sys::hipDeviceProp_tR0600 = std::mem::zeroed();
+            let code = sys::hipGetDevicePropertiesR0600(&mut props, self.id);
+            let c_str = CStr::from_ptr(props.gcnArchName.as_ptr());
+            (c_str.to_string_lossy().into_owned(), code).to_result()
+        }
+    }
+
     /// Gets the UUID bytes for a HIP device.
     ///
     /// # Arguments
@@ -162,6 +208,9 @@ impl Device {
 
     /// Gets the default memory pool associated with this device.
     ///
+    /// The default pool is owned by the HIP runtime. The returned [`MemPool`]
+    /// is borrowed and does not destroy the pool when dropped.
+    ///
     /// # Returns
     /// * `Result<MemPool>` - The default memory pool for the device if successful
     ///
@@ -174,7 +223,48 @@ impl Device {
         let mut mem_pool = std::ptr::null_mut();
         unsafe {
             let code = sys::hipDeviceGetDefaultMemPool(&mut mem_pool, self.id);
-            (MemPool::from_raw(mem_pool), code).to_result()
+            (MemPool::from_borrowed(mem_pool, *self), code).to_result()
+        }
+    }
+
+    /// Gets the current memory pool of this device.
+    ///
+    /// The current pool is used by [`crate::MemoryPointer::alloc_async`]. It is the
+    /// default pool unless another pool was installed with [`Device::set_mem_pool`].
+    /// The returned [`MemPool`] is borrowed and does not destroy the pool when dropped.
+    ///
+    /// # Returns
+    /// * `Result<MemPool>` - The current memory pool for the device if successful
+    ///
+    /// # Errors
+    /// Returns `HipError` if:
+    /// * The device ID is invalid
+    /// * The operation is not supported on this device/platform
+    pub fn mem_pool(&self) -> HipResult<MemPool> {
+        let mut mem_pool = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipDeviceGetMemPool(&mut mem_pool, self.id);
+            (MemPool::from_borrowed(mem_pool, *self), code).to_result()
+        }
+    }
+
+    /// Sets the current memory pool of this device.
+    ///
+    /// Stream-ordered allocations without an explicit pool are served from
+    /// the current pool. The pool must reside on this device. Dropping an
+    /// owned pool while it is current restores the device's default pool.
+    ///
+    /// # Arguments
+    /// * `pool` - The [`MemPool`] to install
+    ///
+    /// # Errors
+    /// Returns `HipError` if:
+    /// * The device ID is invalid
+    /// * The pool does not reside on this device
+    pub fn set_mem_pool(&self, pool: &MemPool) -> HipResult<()> {
+        unsafe {
+            let code = sys::hipDeviceSetMemPool(self.id, pool.handle());
+            ((), code).to_result()
         }
     }
 }
@@ -319,6 +409,25 @@ pub fn get_device_by_pci_bus_id(mut pci_bus_id: PCIBusId) -> HipResult<Device> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{MemPoolProps, MemoryPointer, Stream};
+
+    #[test]
+    fn test_gcn_arch_name() {
+        let name = Device::new(0).gcn_arch_name().unwrap();
+        assert!(name.starts_with("gfx"), "unexpected target ID {}", name);
+    }
+
+    #[test]
+    fn test_attribute() {
+        let device = Device::new(0);
+        let max_threads = device
+            .attribute(DeviceAttribute::MaxThreadsPerBlock)
+            .unwrap();
+        let max_x = device.attribute(DeviceAttribute::MaxBlockDimX).unwrap();
+        assert!(max_threads > 0);
+        assert!(max_x > 0 && max_x <= max_threads);
+        assert!(device.attribute(DeviceAttribute::WarpSize).unwrap() > 0);
+    }
 
     #[test]
     fn test_get_default_mem_pool() {
@@ -341,6 +450,55 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_default_mem_pool_is_borrowed() {
+        let device = Device::new(0);
+
+        let first = device.get_default_mem_pool().unwrap();
+        assert!(!first.is_owned());
+        let handle = first.handle();
+        drop(first);
+
+        // The default pool must still be usable after the borrow was dropped
+        let second = device.get_default_mem_pool().unwrap();
+        assert_eq!(second.handle(), handle);
+        assert!(second.release_threshold().is_ok());
+    }
+
+    #[test]
+    fn test_set_mem_pool() {
+        let device = Device::new(0);
+        let default_pool = device.get_default_mem_pool().unwrap();
+        assert_eq!(device.mem_pool().unwrap().handle(), default_pool.handle());
+
+        let pool = MemPool::create(MemPoolProps::new()).unwrap();
+        assert!(pool.is_owned());
+        device.set_mem_pool(&pool).unwrap();
+
+        let current = device.mem_pool().unwrap();
+        assert!(!current.is_owned());
+        assert_eq!(current.handle(), pool.handle());
+
+        device.set_mem_pool(&default_pool).unwrap();
+        assert_eq!(device.mem_pool().unwrap().handle(), default_pool.handle());
+    }
+
+    #[test]
+    fn test_drop_current_mem_pool() {
+        let device = Device::new(0);
+        let default_pool = device.get_default_mem_pool().unwrap();
+        let pool = MemPool::create(MemPoolProps::new()).unwrap();
+        device.set_mem_pool(&pool).unwrap();
+
+        // Dropping the installed pool must not leave the device pointing at it
+        drop(pool);
+        assert_eq!(device.mem_pool().unwrap().handle(), default_pool.handle());
+        let stream = Stream::create().unwrap();
+        let buffer = MemoryPointer::<u8>::alloc_async(64, &stream).unwrap();
+        drop(buffer);
+        stream.synchronize().unwrap();
+    }
+
     #[test]
     fn test_get_device_by_pci_bus_id() {
         let device = Device::new(0);
@@ -0,0 +1,178 @@
+use crate::sys;
+
+/// Marker for types that can be copied to and from device memory bit for bit.
+///
+/// Device buffers such as [`crate::MemoryPointer`] only hold `DeviceCopy`
+/// types, so types with host-only meaning like `String`, `Box` or references
+/// cannot be allocated on or copied to the device.
+///
+/// `bool` and `char` are deliberately not `DeviceCopy`, since device code can
+/// write bit patterns that are invalid for them.
+///
+/// With the `derive` feature the trait can be derived for `#[repr(C)]` structs
+/// whose fields are all `DeviceCopy`. With the `bytemuck` feature any
+/// `bytemuck::Pod` type can be used through [`PodCopy`]. The `half` feature
+/// adds `half::f16` and `half::bf16`.
+///
+/// # Safety
+/// Implementors must be plain data: every bit pattern the device may write
+/// must be a valid value, the type must not contain pointers to host memory
+/// and its layout must be defined, e.g. by `#[repr(C)]`.
+///
+/// # Examples
+/// ```
+/// use hip_rs::{DeviceCopy, MemoryPointer};
+///
+/// #[derive(Clone, Copy)]
+/// #[repr(C)]
+/// struct Vertex {
+///     position: [f32; 3],
+///     id: u32,
+/// }
+///
+/// unsafe impl DeviceCopy for Vertex {}
+///
+/// let vertices = MemoryPointer::<Vertex>::alloc(16).unwrap();
+/// ```
+pub unsafe trait DeviceCopy: Copy + 'static {}
+
+/// Fills `len` elements at `pointer` with zero bytes.
+///
+/// All zeros is a valid value of every `DeviceCopy` type, since the trait
+/// requires every bit pattern to be valid.
+///
+/// # Safety
+/// `pointer` must be valid for writes of `len` elements.
+pub(crate) unsafe fn write_zeroed<T: DeviceCopy>(pointer: *mut T, len: usize) {
+    std::ptr::write_bytes(pointer, 0, len);
+}
+
+#[cfg(feature = "derive")]
+pub use hip_rs_derive::DeviceCopy;
+
+macro_rules! impl_device_copy {
+    ($($t:ty),* $(,)?) => {
+        $(unsafe impl DeviceCopy for $t {})*
+    };
+}
+
+// sys::hipblasHalf is an alias for u16
+impl_device_copy!(
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    ()
+);
+impl_device_copy!(sys::hipblasComplex, sys::hipblasDoubleComplex);
+
+unsafe impl<T: DeviceCopy, const N: usize> DeviceCopy for [T; N] {}
+
+#[cfg(feature = "half")]
+impl_device_copy!(half::f16, half::bf16);
+
+/// Wraps a `bytemuck::Pod` type so it can be stored in device buffers.
+///
+/// `PodCopy<T>` has the same layout as `T`. Slices of `T` can be viewed as
+/// slices of `PodCopy<T>` with `bytemuck::TransparentWrapper::wrap_slice`.
+///
+/// # Examples
+/// ```
+/// use bytemuck::TransparentWrapper;
+/// use hip_rs::PodCopy;
+///
+/// let host = [1.0f32, 2.0, 3.0];
+/// let wrapped: &[PodCopy<f32>] = PodCopy::wrap_slice(&host);
+/// assert_eq!(wrapped[1].0, 2.0);
+/// ```
+#[cfg(feature = "bytemuck")]
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PodCopy<T: bytemuck::Pod>(pub T);
+
+// Pod types are plain data with every bit pattern valid
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: bytemuck::Pod> DeviceCopy for PodCopy<T> {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: bytemuck::Pod> bytemuck::TransparentWrapper<T> for PodCopy<T> {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: bytemuck::Pod> bytemuck::Zeroable for PodCopy<T> {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: bytemuck::Pod> bytemuck::Pod for PodCopy<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Complex32;
+
+    fn assert_device_copy<T: DeviceCopy>() {}
+
+    #[test]
+    fn test_builtin_impls() {
+        assert_device_copy::<u8>();
+        assert_device_copy::<f64>();
+        assert_device_copy::<sys::hipblasHalf>();
+        assert_device_copy::<Complex32>();
+        assert_device_copy::<[[f32; 4]; 4]>();
+        assert_device_copy::<[Complex32; 2]>();
+    }
+
+    #[cfg(feature = "half")]
+    #[test]
+    fn test_half_impls() {
+        assert_device_copy::<half::f16>();
+        assert_device_copy::<[half::bf16; 2]>();
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive() {
+        #[derive(Clone, Copy, DeviceCopy)]
+        #[repr(C)]
+        struct Particle {
+            position: [f32; 3],
+            mass: f32,
+        }
+
+        #[derive(Clone, Copy, DeviceCopy)]
+        #[repr(transparent)]
+        struct Meters(f64);
+
+        #[derive(Clone, Copy, DeviceCopy)]
+        #[repr(C, align(16))]
+        struct Pair<T> {
+            first: T,
+            second: T,
+        }
+
+        assert_device_copy::<Particle>();
+        assert_device_copy::<Meters>();
+        assert_device_copy::<Pair<Meters>>();
+        assert_device_copy::<[Pair<u32>; 8]>();
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_pod_copy() {
+        use bytemuck::TransparentWrapper;
+
+        assert_device_copy::<PodCopy<[u16; 3]>>();
+        let values = [1u32, 2, 3];
+        let wrapped: &[PodCopy<u32>] = PodCopy::wrap_slice(&values);
+        assert_eq!(PodCopy::peel_slice(wrapped), &values);
+        assert_eq!(bytemuck::cast_slice::<_, u8>(wrapped).len(), 12);
+    }
+}
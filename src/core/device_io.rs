@@ -0,0 +1,391 @@
+use super::copy::{CopyDestination, CopySource, DeviceMemory};
+use super::memory::{memory_copy, MemoryCopyKind};
+use super::pinned::PinnedBuffer;
+use super::result::{HipError, HipResult};
+use crate::result::StatusCode;
+use std::ffi::c_void;
+use std::fmt;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+
+/// Default size of the pinned bounce buffer
+const DEFAULT_CAPACITY: usize = 1 << 20;
+
+/// Reads bytes from device memory through a pinned bounce buffer.
+///
+/// Each refill copies up to the buffer capacity from the device, so small
+/// reads are served from host memory. Seeking within the buffered window
+/// does not copy again.
+///
+/// # Examples
+/// ```
+/// use hip_rs::{DeviceReader, MemoryPointer};
+/// use std::io::Read;
+///
+/// let buffer = MemoryPointer::<u8>::alloc(16).unwrap();
+/// let mut reader = DeviceReader::new(&buffer).unwrap();
+/// let mut bytes = Vec::new();
+/// reader.read_to_end(&mut bytes).unwrap();
+/// assert_eq!(bytes.len(), 16);
+/// ```
+#[derive(Debug)]
+pub struct DeviceReader<'a> {
+    pointer: *const u8,
+    len: usize,
+    position: u64,
+    bounce: PinnedBuffer<u8>,
+    /// Device offset of the first byte held in `bounce`
+    window_start: usize,
+    window_len: usize,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+impl<'a> DeviceReader<'a> {
+    /// Creates a reader over all bytes of `src` with a 1 MiB bounce buffer.
+    pub fn new<S>(src: &'a S) -> HipResult<Self>
+    where
+        S: CopySource<Location = DeviceMemory> + ?Sized,
+    {
+        Self::with_capacity(DEFAULT_CAPACITY, src)
+    }
+
+    /// Creates a reader over all bytes of `src` with a bounce buffer of `capacity` bytes.
+    ///
+    /// # Returns
+    /// * `Ok(DeviceReader)` - The reader, positioned at the start
+    /// * `Err(HipError)` - If the bounce buffer could not be allocated
+    pub fn with_capacity<S>(capacity: usize, src: &'a S) -> HipResult<Self>
+    where
+        S: CopySource<Location = DeviceMemory> + ?Sized,
+    {
+        Ok(Self {
+            pointer: src.copy_pointer() as *const u8,
+            len: src.copy_len() * std::mem::size_of::<S::Elem>(),
+            position: 0,
+            bounce: PinnedBuffer::alloc(capacity.max(1))?,
+            window_start: 0,
+            window_len: 0,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns the size of the device buffer in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the device buffer holds no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the capacity of the bounce buffer in bytes.
+    pub fn capacity(&self) -> usize {
+        self.bounce.len()
+    }
+}
+
+impl Read for DeviceReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for DeviceReader<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.position >= self.len as u64 {
+            return Ok(&[]);
+        }
+        let position = self.position as usize;
+        let window_end = self.window_start + self.window_len;
+        if position < self.window_start || position >= window_end {
+            let n = self.bounce.len().min(self.len - position);
+            unsafe {
+                memory_copy(
+                    self.bounce.as_pointer() as *mut c_void,
+                    self.pointer.add(position) as *const c_void,
+                    n,
+                    MemoryCopyKind::DeviceToHost,
+                )
+            }
+            .map_err(io_error)?;
+            self.window_start = position;
+            self.window_len = n;
+        }
+        let offset = position - self.window_start;
+        Ok(&self.bounce[offset..self.window_len])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position = (self.position + amt as u64).min(self.len as u64);
+    }
+}
+
+impl Seek for DeviceReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = seek_position(self.position, self.len, pos)?;
+        Ok(self.position)
+    }
+}
+
+/// Writes bytes to device memory through a pinned bounce buffer.
+///
+/// Writes are collected in the bounce buffer and copied to the device when it
+/// fills, on [`Write::flush`], before seeking and when the writer is dropped.
+/// Like `Cursor<&mut [u8]>`, writes past the end of the buffer write nothing,
+/// so `write_all` fails with [`io::ErrorKind::WriteZero`].
+///
+/// Errors from the final copy on drop can only be logged; call
+/// [`Write::flush`] to observe them.
+///
+/// # Examples
+/// ```
+/// use hip_rs::{DeviceWriter, MemoryPointer};
+/// use std::io::Write;
+///
+/// let mut buffer = MemoryPointer::<u8>::alloc(5).unwrap();
+/// let mut writer = DeviceWriter::new(&mut buffer).unwrap();
+/// writer.write_all(b"hello").unwrap();
+/// writer.flush().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct DeviceWriter<'a> {
+    pointer: *mut u8,
+    len: usize,
+    position: u64,
+    bounce: PinnedBuffer<u8>,
+    /// Device offset the bytes pending in `bounce` are copied to
+    pending_start: usize,
+    pending_len: usize,
+    _marker: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> DeviceWriter<'a> {
+    /// Creates a writer over all bytes of `dst` with a 1 MiB bounce buffer.
+    pub fn new<D>(dst: &'a mut D) -> HipResult<Self>
+    where
+        D: CopyDestination<Location = DeviceMemory> + ?Sized,
+    {
+        Self::with_capacity(DEFAULT_CAPACITY, dst)
+    }
+
+    /// Creates a writer over all bytes of `dst` with a bounce buffer of `capacity` bytes.
+    ///
+    /// # Returns
+    /// * `Ok(DeviceWriter)` - The writer, positioned at the start
+    /// * `Err(HipError)` - If the bounce buffer could not be allocated
+    pub fn with_capacity<D>(capacity: usize, dst: &'a mut D) -> HipResult<Self>
+    where
+        D: CopyDestination<Location = DeviceMemory> + ?Sized,
+    {
+        Ok(Self {
+            pointer: dst.copy_mut_pointer() as *mut u8,
+            len: dst.copy_len() * std::mem::size_of::<D::Elem>(),
+            position: 0,
+            bounce: PinnedBuffer::alloc(capacity.max(1))?,
+            pending_start: 0,
+            pending_len: 0,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns the size of the device buffer in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the device buffer holds no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the capacity of the bounce buffer in bytes.
+    pub fn capacity(&self) -> usize {
+        self.bounce.len()
+    }
+
+    fn flush_pending(&mut self) -> HipResult<()> {
+        if self.pending_len == 0 {
+            return Ok(());
+        }
+        unsafe {
+            memory_copy(
+                self.pointer.add(self.pending_start) as *mut c_void,
+                self.bounce.as_pointer() as *const c_void,
+                self.pending_len,
+                MemoryCopyKind::HostToDevice,
+            )?;
+        }
+        self.pending_len = 0;
+        Ok(())
+    }
+}
+
+impl Write for DeviceWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.position >= self.len as u64 || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pending_len == self.bounce.len() {
+            self.flush_pending().map_err(io_error)?;
+        }
+        let position = self.position as usize;
+        if self.pending_len == 0 {
+            self.pending_start = position;
+        }
+
+        let n = buf
+            .len()
+            .min(self.len - position)
+            .min(self.bounce.len() - self.pending_len);
+        self.bounce[self.pending_len..self.pending_len + n].copy_from_slice(&buf[..n]);
+        self.pending_len += n;
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_pending().map_err(io_error)
+    }
+}
+
+impl Seek for DeviceWriter<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = seek_position(self.position, self.len, pos)?;
+        // Pending bytes must land at the offset they were written at
+        self.flush_pending().map_err(io_error)?;
+        self.position = position;
+        Ok(position)
+    }
+}
+
+impl Drop for DeviceWriter<'_> {
+    fn drop(&mut self) {
+        if let Err(error) = self.flush_pending() {
+            log::error!("DeviceWriter failed to flush on drop: {:?}", error);
+        }
+    }
+}
+
+/// Resolves a seek against a buffer of `len` bytes, allowing positions past the end.
+fn seek_position(current: u64, len: usize, pos: SeekFrom) -> io::Result<u64> {
+    let (base, offset) = match pos {
+        SeekFrom::Start(offset) => return Ok(offset),
+        SeekFrom::End(offset) => (len as u64, offset),
+        SeekFrom::Current(offset) => (current, offset),
+    };
+    base.checked_add_signed(offset).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        )
+    })
+}
+
+/// Carries a HipError as the source of an io::Error.
+#[derive(Debug)]
+struct HipIoError(HipError);
+
+impl fmt::Display for HipIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        StatusCode::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for HipIoError {}
+
+fn io_error(error: HipError) -> io::Error {
+    io::Error::other(HipIoError(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{copy, HipStatus, MemoryPointer};
+
+    #[test]
+    fn test_seek_position() {
+        assert_eq!(seek_position(5, 10, SeekFrom::Start(3)).unwrap(), 3);
+        assert_eq!(seek_position(5, 10, SeekFrom::Current(-2)).unwrap(), 3);
+        assert_eq!(seek_position(5, 10, SeekFrom::Current(2)).unwrap(), 7);
+        assert_eq!(seek_position(5, 10, SeekFrom::End(-1)).unwrap(), 9);
+        // Past the end is allowed, like a file
+        assert_eq!(seek_position(5, 10, SeekFrom::End(4)).unwrap(), 14);
+
+        let error = seek_position(5, 10, SeekFrom::Current(-6)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(seek_position(u64::MAX, 10, SeekFrom::Current(1)).is_err());
+    }
+
+    #[test]
+    fn test_io_error() {
+        let error = io_error(HipError::from_status(HipStatus::InvalidValue));
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+        assert_eq!(error.to_string(), "HIP status: InvalidValue (code: 1)");
+    }
+
+    #[test]
+    fn test_copy_file_into_device() {
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        let mut buffer = MemoryPointer::<u8>::alloc(data.len()).unwrap();
+        {
+            let mut writer = DeviceWriter::with_capacity(1000, &mut buffer).unwrap();
+            let copied = io::copy(&mut io::Cursor::new(&data), &mut writer).unwrap();
+            assert_eq!(copied, data.len() as u64);
+        }
+
+        let mut check = vec![0u8; data.len()];
+        copy(&mut check[..], &buffer).unwrap();
+        assert_eq!(check, data);
+
+        let mut reader = DeviceReader::with_capacity(777, &buffer).unwrap();
+        let mut result = Vec::new();
+        reader.read_to_end(&mut result).unwrap();
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn test_reader_seek() {
+        let data: Vec<u32> = (0..1000).collect();
+        let mut buffer = MemoryPointer::<u32>::alloc(data.len()).unwrap();
+        copy(&mut buffer, &data[..]).unwrap();
+
+        let mut reader = DeviceReader::with_capacity(64, &buffer).unwrap();
+        assert_eq!(reader.len(), 4000);
+        reader.seek(SeekFrom::Start(4 * 500)).unwrap();
+        let mut word = [0u8; 4];
+        reader.read_exact(&mut word).unwrap();
+        assert_eq!(u32::from_ne_bytes(word), 500);
+
+        reader.seek(SeekFrom::End(-4)).unwrap();
+        reader.read_exact(&mut word).unwrap();
+        assert_eq!(u32::from_ne_bytes(word), 999);
+        assert_eq!(reader.read(&mut word).unwrap(), 0);
+
+        reader.seek(SeekFrom::End(100)).unwrap();
+        assert_eq!(reader.read(&mut word).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_writer_seek_and_overflow() {
+        let mut buffer = MemoryPointer::<u8>::alloc(8).unwrap();
+        {
+            let mut writer = DeviceWriter::with_capacity(4, &mut buffer).unwrap();
+            writer.write_all(b"abcdefgh").unwrap();
+            let error = writer.write_all(b"i").unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+
+            writer.seek(SeekFrom::Start(2)).unwrap();
+            writer.write_all(b"XY").unwrap();
+            writer.flush().unwrap();
+        }
+
+        let mut check = [0u8; 8];
+        copy(&mut check[..], &buffer).unwrap();
+        assert_eq!(&check, b"abXYefgh");
+    }
+}
@@ -102,6 +102,54 @@ impl TryFrom<u32> for DeviceP2PAttribute {
     }
 }
 
+/// Device properties queried with [`crate::Device::attribute`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceAttribute {
+    MaxThreadsPerBlock,
+    MaxBlockDimX,
+    MaxBlockDimY,
+    MaxBlockDimZ,
+    MaxGridDimX,
+    MaxGridDimY,
+    MaxGridDimZ,
+    MaxSharedMemoryPerBlock,
+    MaxRegistersPerBlock,
+    WarpSize,
+    MultiprocessorCount,
+}
+
+impl From<DeviceAttribute> for u32 {
+    fn from(attr: DeviceAttribute) -> Self {
+        match attr {
+            DeviceAttribute::MaxThreadsPerBlock => {
+                sys::hipDeviceAttribute_t_hipDeviceAttributeMaxThreadsPerBlock
+            }
+            DeviceAttribute::MaxBlockDimX => {
+                sys::hipDeviceAttribute_t_hipDeviceAttributeMaxBlockDimX
+            }
+            DeviceAttribute::MaxBlockDimY => {
+                sys::hipDeviceAttribute_t_hipDeviceAttributeMaxBlockDimY
+            }
+            DeviceAttribute::MaxBlockDimZ => {
+                sys::hipDeviceAttribute_t_hipDeviceAttributeMaxBlockDimZ
+            }
+            DeviceAttribute::MaxGridDimX => sys::hipDeviceAttribute_t_hipDeviceAttributeMaxGridDimX,
+            DeviceAttribute::MaxGridDimY => sys::hipDeviceAttribute_t_hipDeviceAttributeMaxGridDimY,
+            DeviceAttribute::MaxGridDimZ => sys::hipDeviceAttribute_t_hipDeviceAttributeMaxGridDimZ,
+            DeviceAttribute::MaxSharedMemoryPerBlock => {
+                sys::hipDeviceAttribute_t_hipDeviceAttributeMaxSharedMemoryPerBlock
+            }
+            DeviceAttribute::MaxRegistersPerBlock => {
+                sys::hipDeviceAttribute_t_hipDeviceAttributeMaxRegistersPerBlock
+            }
+            DeviceAttribute::WarpSize => sys::hipDeviceAttribute_t_hipDeviceAttributeWarpSize,
+            DeviceAttribute::MultiprocessorCount => {
+                sys::hipDeviceAttribute_t_hipDeviceAttributeMultiprocessorCount
+            }
+        }
+    }
+}
+
 /// Unsafe implementation for converting PCIBusId to a String.
 unsafe impl UnsafeToString for PCIBusId {
     /// Converts the internal buffer to a String.
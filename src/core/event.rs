@@ -0,0 +1,168 @@
+#[allow(unused_imports)]
+use super::result::{HipError, HipResult, HipStatus};
+use super::EventFlags;
+use crate::result::ResultExt;
+use crate::sys;
+use crate::Stream;
+
+/// A handle to a HIP event used to mark points in a stream.
+///
+/// The event is destroyed when dropped.
+#[derive(Debug)]
+pub struct Event {
+    handle: sys::hipEvent_t,
+}
+
+impl Event {
+    /// Creates a new event with default flags.
+    ///
+    /// # Returns
+    /// * `Ok(Event)` - A new event
+    /// * `Err(HipError)` - If event creation fails
+    ///
+    /// # Examples
+    /// ```
+    /// use hip_rs::Event;
+    ///
+    /// let event = Event::create().unwrap();
+    /// ```
+    pub fn create() -> HipResult<Self> {
+        Self::create_with_flags(EventFlags::DEFAULT)
+    }
+
+    /// Creates a new event with the given flags.
+    ///
+    /// # Arguments
+    /// * `flags` - [`EventFlags`] controlling synchronization, timing and sharing
+    ///
+    /// # Returns
+    /// * `Ok(Event)` - A new event
+    /// * `Err(HipError)` - If the flags are invalid, e.g. `INTERPROCESS` without `DISABLE_TIMING`
+    pub fn create_with_flags(flags: EventFlags) -> HipResult<Self> {
+        let mut handle: sys::hipEvent_t = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipEventCreateWithFlags(&mut handle, flags.bits());
+            (Self { handle }, code).to_result()
+        }
+    }
+
+    /// Takes ownership of a raw event handle, destroying it on drop.
+    pub(crate) fn from_raw(handle: sys::hipEvent_t) -> Self {
+        Self { handle }
+    }
+
+    /// Returns the raw event handle.
+    pub fn handle(&self) -> sys::hipEvent_t {
+        self.handle
+    }
+
+    /// Records the event in `stream`.
+    ///
+    /// The event completes once all work submitted to `stream` before this
+    /// call has completed.
+    pub fn record(&self, stream: &Stream) -> HipResult<()> {
+        unsafe {
+            let code = sys::hipEventRecord(self.handle, stream.handle());
+            ((), code).to_result()
+        }
+    }
+
+    /// Blocks the host thread until the event has completed.
+    pub fn synchronize(&self) -> HipResult<()> {
+        unsafe {
+            let code = sys::hipEventSynchronize(self.handle);
+            ((), code).to_result()
+        }
+    }
+
+    /// Queries whether the event has completed.
+    ///
+    /// # Returns
+    /// * `Ok(true)` - All work captured by the event has completed
+    /// * `Ok(false)` - Work captured by the event is still in progress
+    /// * `Err(HipError)` - If the event handle is invalid
+    pub fn query(&self) -> HipResult<bool> {
+        let code = unsafe { sys::hipEventQuery(self.handle) };
+        let result: HipResult<()> = ((), code).to_result();
+        match result {
+            Ok(()) => Ok(true),
+            Err(e) if e.status == HipStatus::NotReady => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the time in milliseconds elapsed between `start` and this event.
+    ///
+    /// Both events must have been recorded and completed, and neither may be
+    /// created with [`EventFlags::DISABLE_TIMING`].
+    pub fn elapsed_since(&self, start: &Event) -> HipResult<f32> {
+        let mut ms: f32 = 0.0;
+        unsafe {
+            let code = sys::hipEventElapsedTime(&mut ms, start.handle, self.handle);
+            (ms, code).to_result()
+        }
+    }
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        if !self.handle.is_null() {
+            unsafe {
+                let code = sys::hipEventDestroy(self.handle);
+                if code != 0 {
+                    log::error!("Failed to destroy HIP event: {}", code);
+                }
+            }
+        }
+    }
+}
+
+// Event handles can be used from any thread
+unsafe impl Send for Event {}
+unsafe impl Sync for Event {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_create() {
+        let event = Event::create().unwrap();
+        assert!(!event.handle().is_null());
+    }
+
+    #[test]
+    fn test_event_record_and_synchronize() {
+        let stream = Stream::create().unwrap();
+        let event = Event::create().unwrap();
+
+        event.record(&stream).unwrap();
+        event.synchronize().unwrap();
+        assert!(event.query().unwrap());
+    }
+
+    #[test]
+    fn test_event_elapsed() {
+        let stream = Stream::create().unwrap();
+        let start = Event::create().unwrap();
+        let stop = Event::create().unwrap();
+
+        start.record(&stream).unwrap();
+        stop.record(&stream).unwrap();
+        stop.synchronize().unwrap();
+
+        let ms = stop.elapsed_since(&start).unwrap();
+        assert!(ms >= 0.0);
+    }
+
+    #[test]
+    fn test_stream_wait_event() {
+        let producer = Stream::create().unwrap();
+        let consumer = Stream::create().unwrap();
+        let event = Event::create_with_flags(EventFlags::DISABLE_TIMING).unwrap();
+
+        event.record(&producer).unwrap();
+        consumer.wait_event(&event).unwrap();
+        consumer.synchronize().unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 use bitflags::bitflags;
 
 bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct DeviceMallocFlag: u32 {
         const DEFAULT = 0x0;
         const FINEGRAINED = 0x1;
@@ -9,3 +10,24 @@ bitflags! {
         const CONTIGUOUS = 0x4;
     }
 }
+
+bitflags! {
+    pub struct EventFlags: u32 {
+        const DEFAULT = 0x0;
+        const BLOCKING_SYNC = 0x1;
+        const DISABLE_TIMING = 0x2;
+        const INTERPROCESS = 0x4;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct HostMallocFlags: u32 {
+        const DEFAULT = 0x0;
+        const PORTABLE = 0x1;
+        const MAPPED = 0x2;
+        const WRITE_COMBINED = 0x4;
+        const COHERENT = 0x4000_0000;
+        const NON_COHERENT = 0x8000_0000;
+    }
+}
@@ -0,0 +1,752 @@
+#[allow(unused_imports)]
+use super::result::{HipError, HipResult, HipStatus};
+use super::{
+    DeviceAllocator, DeviceCopy, Event, MemAllocationHandleType, MemPool, MemPoolProps,
+    MemoryPointer,
+};
+use crate::result::ResultExt;
+use crate::sys;
+
+#[cfg(unix)]
+use std::io;
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+/// Opaque data describing an allocation from an exportable memory pool.
+///
+/// Created with [`MemPool::export_pointer`] in the exporting process, sent to
+/// another process, and turned back into a pointer with [`MemPool::import_pointer`]
+/// on a pool imported from the same shareable handle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolPtrExportData {
+    data: [u8; Self::DATA_SIZE],
+    /// Size of the exported allocation in bytes
+    size: usize,
+}
+
+impl PoolPtrExportData {
+    /// Size of the opaque hipMemPoolPtrExportData payload
+    const DATA_SIZE: usize = 64;
+
+    /// Number of bytes produced by [`PoolPtrExportData::to_bytes`]
+    pub const SERIALIZED_SIZE: usize = Self::DATA_SIZE + 8;
+
+    /// Returns the size of the exported allocation in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Serializes the export data to a fixed-size byte array.
+    ///
+    /// The layout is the 64 byte HIP payload followed by the allocation size
+    /// as a little-endian `u64`.
+    pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_SIZE] {
+        let mut bytes = [0u8; Self::SERIALIZED_SIZE];
+        bytes[..Self::DATA_SIZE].copy_from_slice(&self.data);
+        bytes[Self::DATA_SIZE..].copy_from_slice(&(self.size as u64).to_le_bytes());
+        bytes
+    }
+
+    /// Deserializes export data produced by [`PoolPtrExportData::to_bytes`].
+    ///
+    /// # Returns
+    /// * `Ok(PoolPtrExportData)` - The decoded export data
+    /// * `Err(HipError)` - `InvalidValue` if `bytes` has the wrong length
+    pub fn from_bytes(bytes: &[u8]) -> HipResult<Self> {
+        if bytes.len() != Self::SERIALIZED_SIZE {
+            return Err(HipError::from_status(HipStatus::InvalidValue));
+        }
+
+        let mut data = [0u8; Self::DATA_SIZE];
+        data.copy_from_slice(&bytes[..Self::DATA_SIZE]);
+
+        let mut size = [0u8; 8];
+        size.copy_from_slice(&bytes[Self::DATA_SIZE..]);
+        let size = usize::try_from(u64::from_le_bytes(size))
+            .map_err(|_| HipError::from_status(HipStatus::InvalidValue))?;
+
+        Ok(Self { data, size })
+    }
+
+    fn to_sys(self) -> sys::hipMemPoolPtrExportData {
+        sys::hipMemPoolPtrExportData {
+            reserved: self.data,
+        }
+    }
+}
+
+impl MemPool {
+    /// Exports the pool as a POSIX file descriptor that can be shared with
+    /// another process.
+    ///
+    /// The pool must have been created with
+    /// [`MemAllocationHandleType::PosixFileDescriptor`] in its handle types.
+    /// The descriptor can be sent over a Unix domain socket with [`send_fd`].
+    ///
+    /// # Returns
+    /// * `Ok(OwnedFd)` - A file descriptor owned by the caller
+    /// * `Err(HipError)` - If the pool does not support POSIX file descriptors
+    #[cfg(unix)]
+    pub fn export_fd(&self) -> HipResult<OwnedFd> {
+        let mut fd: RawFd = -1;
+        unsafe {
+            let code = sys::hipMemPoolExportToShareableHandle(
+                &mut fd as *mut RawFd as *mut std::ffi::c_void,
+                self.handle(),
+                MemAllocationHandleType::PosixFileDescriptor as u32,
+                0,
+            );
+            ((), code).to_result()?;
+            Ok(OwnedFd::from_raw_fd(fd))
+        }
+    }
+
+    /// Imports a pool exported by another process with [`MemPool::export_fd`].
+    ///
+    /// The descriptor is not consumed and can be closed once the pool is imported.
+    /// The imported pool is owned and destroyed when dropped. Allocations are
+    /// not made from an imported pool directly, pointers exported by the other
+    /// process are mapped with [`MemPool::import_pointer`] instead.
+    ///
+    /// # Arguments
+    /// * `fd` - The file descriptor received from the exporting process
+    ///
+    /// # Returns
+    /// * `Ok(MemPool)` - The imported pool
+    /// * `Err(HipError)` - If the descriptor does not refer to an exported pool
+    #[cfg(unix)]
+    #[track_caller]
+    pub fn import_fd(fd: BorrowedFd<'_>) -> HipResult<Self> {
+        let mut handle = std::ptr::null_mut();
+        let props =
+            MemPoolProps::default().with_handle_types(MemAllocationHandleType::PosixFileDescriptor);
+
+        unsafe {
+            // The descriptor is passed by value, cast to a pointer
+            let code = sys::hipMemPoolImportFromShareableHandle(
+                &mut handle,
+                fd.as_raw_fd() as isize as *mut std::ffi::c_void,
+                MemAllocationHandleType::PosixFileDescriptor as u32,
+                0,
+            );
+            (Self::from_owned(handle, props), code).to_result()
+        }
+    }
+
+    /// Exports an allocation made from this pool so it can be imported by
+    /// another process.
+    ///
+    /// # Arguments
+    /// * `pointer` - An allocation made from this pool, e.g. by [`MemPool::alloc_async`]
+    ///
+    /// # Returns
+    /// * `Ok(PoolPtrExportData)` - Data to send to the importing process
+    /// * `Err(HipError)` - If the pointer was not allocated from an exportable pool
+    pub fn export_pointer<T: DeviceCopy, A: DeviceAllocator>(
+        &self,
+        pointer: &MemoryPointer<T, A>,
+    ) -> HipResult<PoolPtrExportData> {
+        if pointer.as_pointer().is_null() {
+            return Err(HipError::from_status(HipStatus::InvalidValue));
+        }
+
+        let mut export_data = sys::hipMemPoolPtrExportData {
+            reserved: [0u8; PoolPtrExportData::DATA_SIZE],
+        };
+        unsafe {
+            let code = sys::hipMemPoolExportPointer(
+                &mut export_data,
+                pointer.as_pointer() as *mut std::ffi::c_void,
+            );
+            let data = PoolPtrExportData {
+                data: export_data.reserved,
+                size: pointer.size() * std::mem::size_of::<T>(),
+            };
+            (data, code).to_result()
+        }
+    }
+
+    /// Maps an allocation exported by another process into this process.
+    ///
+    /// This pool must have been imported with [`MemPool::import_fd`] from the
+    /// pool the allocation was made from. The returned pointer must be dropped
+    /// before the exporting process frees the allocation.
+    ///
+    /// # Arguments
+    /// * `export_data` - Data produced by [`MemPool::export_pointer`]
+    ///
+    /// # Returns
+    /// * `Ok(MemoryPointer<T>)` - The imported allocation
+    /// * `Err(HipError)` - If the export data does not belong to this pool
+    #[track_caller]
+    pub fn import_pointer<T: DeviceCopy>(
+        &self,
+        export_data: &PoolPtrExportData,
+    ) -> HipResult<MemoryPointer<T>> {
+        let mut sys_data = export_data.to_sys();
+        let mut ptr = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipMemPoolImportPointer(&mut ptr, self.handle(), &mut sys_data);
+            let len = export_data.size() / std::mem::size_of::<T>().max(1);
+            (MemoryPointer::from_raw_parts(ptr as *mut T, len), code).to_result()
+        }
+    }
+}
+
+/// Sends a file descriptor over a Unix domain socket using `SCM_RIGHTS`.
+///
+/// The receiving process gets its own descriptor referring to the same
+/// object, e.g. a pool exported with [`MemPool::export_fd`].
+///
+/// # Arguments
+/// * `socket` - A connected Unix domain socket
+/// * `fd` - The descriptor to send. It stays open in the sending process.
+///
+/// # Errors
+/// Returns the OS error if `sendmsg` fails.
+#[cfg(unix)]
+pub fn send_fd(socket: &UnixStream, fd: BorrowedFd<'_>) -> io::Result<()> {
+    // At least one byte of regular data is needed for the control message to be delivered
+    let mut payload = [0u8; 1];
+    let mut iov = libc::iovec {
+        iov_base: payload.as_mut_ptr() as *mut std::ffi::c_void,
+        iov_len: payload.len(),
+    };
+
+    let mut control = ControlBuffer::new();
+
+    unsafe {
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr();
+        msg.msg_controllen = ControlBuffer::space() as _;
+
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(std::mem::size_of::<RawFd>() as u32) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, fd.as_raw_fd());
+
+        if libc::sendmsg(socket.as_raw_fd(), &msg, 0) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+/// Receives a file descriptor sent with [`send_fd`].
+///
+/// # Arguments
+/// * `socket` - A connected Unix domain socket
+///
+/// # Errors
+/// Returns the OS error if `recvmsg` fails, `UnexpectedEof` if the peer closed
+/// the socket, and `InvalidData` if the message carried no descriptor.
+#[cfg(unix)]
+pub fn recv_fd(socket: &UnixStream) -> io::Result<OwnedFd> {
+    let mut payload = [0u8; 1];
+    let mut iov = libc::iovec {
+        iov_base: payload.as_mut_ptr() as *mut std::ffi::c_void,
+        iov_len: payload.len(),
+    };
+
+    let mut control = ControlBuffer::new();
+
+    unsafe {
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr();
+        msg.msg_controllen = ControlBuffer::space() as _;
+
+        let received = libc::recvmsg(socket.as_raw_fd(), &mut msg, 0);
+        if received < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if received == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        if cmsg.is_null()
+            || (*cmsg).cmsg_level != libc::SOL_SOCKET
+            || (*cmsg).cmsg_type != libc::SCM_RIGHTS
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "message did not carry a file descriptor",
+            ));
+        }
+
+        let fd = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const RawFd);
+        Ok(OwnedFd::from_raw_fd(fd))
+    }
+}
+
+/// Control message buffer with room for a single file descriptor,
+/// aligned for `cmsghdr`.
+#[cfg(unix)]
+struct ControlBuffer([u64; 8]);
+
+#[cfg(unix)]
+impl ControlBuffer {
+    fn new() -> Self {
+        Self([0u64; 8])
+    }
+
+    fn space() -> usize {
+        unsafe { libc::CMSG_SPACE(std::mem::size_of::<RawFd>() as u32) as usize }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut std::ffi::c_void {
+        self.0.as_mut_ptr() as *mut std::ffi::c_void
+    }
+}
+
+/// Implements base64 encoding and, with the `serde` feature, serialization
+/// for a handle type with `to_bytes` and `from_bytes`.
+///
+/// Human readable formats store the handle as a base64 string, binary
+/// formats store the raw bytes.
+macro_rules! impl_handle_encoding {
+    ($name:ident) => {
+        impl $name {
+            /// Encodes the handle as a standard base64 string.
+            pub fn to_base64(&self) -> String {
+                use base64::Engine;
+                base64::engine::general_purpose::STANDARD.encode(self.to_bytes())
+            }
+
+            /// Decodes a handle from a string produced by `to_base64`.
+            ///
+            /// # Returns
+            /// * `Err(HipError)` - `InvalidValue` if the string is not valid base64
+            ///   or decodes to the wrong number of bytes
+            pub fn from_base64(encoded: &str) -> HipResult<Self> {
+                use base64::Engine;
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(encoded.trim())
+                    .map_err(|_| HipError::from_status(HipStatus::InvalidValue))?;
+                Self::from_bytes(&bytes)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                if serializer.is_human_readable() {
+                    serializer.serialize_str(&self.to_base64())
+                } else {
+                    serializer.serialize_bytes(&self.to_bytes())
+                }
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct HandleVisitor;
+
+                impl<'de> serde::de::Visitor<'de> for HandleVisitor {
+                    type Value = $name;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        write!(f, "{} as bytes or a base64 string", stringify!($name))
+                    }
+
+                    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<$name, E> {
+                        $name::from_base64(v)
+                            .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+                    }
+
+                    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<$name, E> {
+                        $name::from_bytes(v).map_err(|_| E::invalid_length(v.len(), &self))
+                    }
+
+                    fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                        self,
+                        mut seq: A,
+                    ) -> Result<$name, A::Error> {
+                        let mut bytes = Vec::new();
+                        while let Some(byte) = seq.next_element::<u8>()? {
+                            bytes.push(byte);
+                        }
+                        self.visit_bytes(&bytes)
+                    }
+                }
+
+                if deserializer.is_human_readable() {
+                    deserializer.deserialize_str(HandleVisitor)
+                } else {
+                    deserializer.deserialize_bytes(HandleVisitor)
+                }
+            }
+        }
+    };
+}
+
+/// Defines a fixed-size legacy IPC handle wrapping a `hipIpc*Handle_t`.
+macro_rules! ipc_handle {
+    ($(#[$meta:meta])* $name:ident, $sys:ty) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name([u8; Self::SIZE]);
+
+        impl $name {
+            /// Size of the handle in bytes
+            pub const SIZE: usize = 64;
+
+            /// Returns the raw bytes of the handle.
+            pub fn as_bytes(&self) -> &[u8; Self::SIZE] {
+                &self.0
+            }
+
+            /// Returns a copy of the raw bytes of the handle.
+            pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+                self.0
+            }
+
+            /// Creates a handle from raw bytes received from another process.
+            ///
+            /// # Returns
+            /// * `Err(HipError)` - `InvalidValue` if `bytes` is not exactly `SIZE` bytes long
+            pub fn from_bytes(bytes: &[u8]) -> HipResult<Self> {
+                let bytes: [u8; Self::SIZE] = bytes
+                    .try_into()
+                    .map_err(|_| HipError::from_status(HipStatus::InvalidValue))?;
+                Ok(Self(bytes))
+            }
+
+            fn from_sys(handle: $sys) -> Self {
+                Self(handle.reserved.map(|b| b as u8))
+            }
+
+            fn to_sys(self) -> $sys {
+                let mut handle: $sys = unsafe { std::mem::zeroed() };
+                handle.reserved = self.0.map(|b| b as _);
+                handle
+            }
+        }
+
+        impl_handle_encoding!($name);
+    };
+}
+
+impl_handle_encoding!(PoolPtrExportData);
+
+ipc_handle!(
+    /// Legacy IPC handle to a device allocation.
+    ///
+    /// Created with [`MemoryPointer::ipc_handle`] and opened in another
+    /// process with [`IpcMapping::open`].
+    IpcMemHandle,
+    sys::hipIpcMemHandle_t
+);
+
+ipc_handle!(
+    /// Legacy IPC handle to an event.
+    ///
+    /// Created with [`Event::ipc_handle`] and opened in another process
+    /// with [`Event::open_ipc`].
+    IpcEventHandle,
+    sys::hipIpcEventHandle_t
+);
+
+impl<T: DeviceCopy, A: DeviceAllocator> MemoryPointer<T, A> {
+    /// Gets a legacy IPC handle for this allocation.
+    ///
+    /// Only allocations made with `hipMalloc`, e.g. by [`MemoryPointer::alloc`],
+    /// can be shared this way. Pool allocations are shared with
+    /// [`MemPool::export_pointer`] instead.
+    ///
+    /// # Returns
+    /// * `Ok(IpcMemHandle)` - A handle to send to another process
+    /// * `Err(HipError)` - If the pointer is null or not a `hipMalloc` allocation
+    pub fn ipc_handle(&self) -> HipResult<IpcMemHandle> {
+        if self.as_pointer().is_null() {
+            return Err(HipError::from_status(HipStatus::InvalidValue));
+        }
+
+        let mut handle: sys::hipIpcMemHandle_t = unsafe { std::mem::zeroed() };
+        unsafe {
+            let code =
+                sys::hipIpcGetMemHandle(&mut handle, self.as_pointer() as *mut std::ffi::c_void);
+            (IpcMemHandle::from_sys(handle), code).to_result()
+        }
+    }
+}
+
+/// `hipIpcMemLazyEnablePeerAccess`, a preprocessor define not covered by the bindings
+const IPC_MEM_LAZY_ENABLE_PEER_ACCESS: u32 = 0x1;
+
+/// Device memory of another process, mapped through an [`IpcMemHandle`].
+///
+/// The mapping is closed when dropped. The memory itself stays owned by the
+/// exporting process, which must keep it alive while the mapping is open.
+#[derive(Debug)]
+pub struct IpcMapping<T> {
+    pointer: *mut T,
+}
+
+impl<T> IpcMapping<T> {
+    /// Maps the allocation referred to by `handle` into this process.
+    ///
+    /// Peer access to the device owning the allocation is enabled if needed.
+    /// A handle cannot be opened in the process that created it.
+    ///
+    /// # Arguments
+    /// * `handle` - The handle received from the exporting process
+    ///
+    /// # Returns
+    /// * `Ok(IpcMapping<T>)` - The mapped allocation
+    /// * `Err(HipError)` - If the handle is invalid or was created by this process
+    pub fn open(handle: &IpcMemHandle) -> HipResult<Self> {
+        let mut ptr = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipIpcOpenMemHandle(
+                &mut ptr,
+                handle.to_sys(),
+                IPC_MEM_LAZY_ENABLE_PEER_ACCESS,
+            );
+            (
+                Self {
+                    pointer: ptr as *mut T,
+                },
+                code,
+            )
+                .to_result()
+        }
+    }
+
+    /// Returns the raw device pointer of the mapping.
+    pub fn as_pointer(&self) -> *mut T {
+        self.pointer
+    }
+}
+
+impl<T> Drop for IpcMapping<T> {
+    fn drop(&mut self) {
+        if !self.pointer.is_null() {
+            unsafe {
+                let code = sys::hipIpcCloseMemHandle(self.pointer as *mut std::ffi::c_void);
+                if code != 0 {
+                    log::error!("Failed to close IPC memory handle: {}", code);
+                }
+            }
+        }
+    }
+}
+
+impl Event {
+    /// Gets a legacy IPC handle for this event.
+    ///
+    /// The event must have been created with both [`EventFlags::INTERPROCESS`]
+    /// and [`EventFlags::DISABLE_TIMING`].
+    ///
+    /// # Returns
+    /// * `Ok(IpcEventHandle)` - A handle to send to another process
+    /// * `Err(HipError)` - If the event was not created as an interprocess event
+    pub fn ipc_handle(&self) -> HipResult<IpcEventHandle> {
+        let mut handle: sys::hipIpcEventHandle_t = unsafe { std::mem::zeroed() };
+        unsafe {
+            let code = sys::hipIpcGetEventHandle(&mut handle, self.handle());
+            (IpcEventHandle::from_sys(handle), code).to_result()
+        }
+    }
+
+    /// Opens an event exported by another process with [`Event::ipc_handle`].
+    ///
+    /// The opened event can be waited on with [`crate::Stream::wait_event`]
+    /// and recorded, but not used for timing.
+    ///
+    /// # Arguments
+    /// * `handle` - The handle received from the exporting process
+    ///
+    /// # Returns
+    /// * `Ok(Event)` - The opened event, destroyed when dropped
+    /// * `Err(HipError)` - If the handle is invalid
+    pub fn open_ipc(handle: &IpcEventHandle) -> HipResult<Self> {
+        let mut event: sys::hipEvent_t = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipIpcOpenEventHandle(&mut event, handle.to_sys());
+            (Self::from_raw(event), code).to_result()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stream;
+
+    #[test]
+    fn test_pool_ptr_export_data_roundtrip() {
+        let mut data = [0u8; 64];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let export_data = PoolPtrExportData { data, size: 4096 };
+
+        let bytes = export_data.to_bytes();
+        assert_eq!(bytes.len(), PoolPtrExportData::SERIALIZED_SIZE);
+
+        let decoded = PoolPtrExportData::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, export_data);
+        assert_eq!(decoded.size(), 4096);
+    }
+
+    #[test]
+    fn test_pool_ptr_export_data_invalid_length() {
+        let result = PoolPtrExportData::from_bytes(&[0u8; 10]);
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_send_recv_fd() {
+        use std::io::{Read, Write};
+        use std::os::fd::AsFd;
+
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let (mut payload_tx, payload_rx) = UnixStream::pair().unwrap();
+
+        let payload_fd: OwnedFd = payload_rx.into();
+        send_fd(&sender, payload_fd.as_fd()).unwrap();
+        let received = recv_fd(&receiver).unwrap();
+        assert_ne!(received.as_raw_fd(), payload_fd.as_raw_fd());
+        drop(payload_fd);
+
+        // The received descriptor refers to the same socket
+        payload_tx.write_all(b"hip").unwrap();
+        let mut received = UnixStream::from(received);
+        let mut buffer = [0u8; 3];
+        received.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"hip");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_recv_fd_eof() {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        drop(sender);
+        let result = recv_fd(&receiver);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_ipc_mem_handle_bytes_roundtrip() {
+        let mut bytes = [0u8; IpcMemHandle::SIZE];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = 255 - i as u8;
+        }
+
+        let handle = IpcMemHandle::from_bytes(&bytes).unwrap();
+        assert_eq!(handle.as_bytes(), &bytes);
+        assert_eq!(handle.to_bytes(), bytes);
+
+        // Bytes above 127 must survive the conversion to and from c_char
+        let sys_handle = handle.to_sys();
+        assert_eq!(IpcMemHandle::from_sys(sys_handle), handle);
+    }
+
+    #[test]
+    fn test_ipc_handle_invalid_length() {
+        let result = IpcMemHandle::from_bytes(&[0u8; 63]);
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+
+        let result = IpcEventHandle::from_bytes(&[0u8; 65]);
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+    }
+
+    #[test]
+    fn test_ipc_handle_base64_roundtrip() {
+        let handle = IpcEventHandle::from_bytes(&[7u8; IpcEventHandle::SIZE]).unwrap();
+
+        let encoded = handle.to_base64();
+        assert_eq!(IpcEventHandle::from_base64(&encoded).unwrap(), handle);
+
+        assert!(IpcEventHandle::from_base64("not base64!").is_err());
+        // Valid base64, but too short
+        assert!(IpcEventHandle::from_base64("AAAA").is_err());
+    }
+
+    #[test]
+    fn test_pool_ptr_export_data_base64_roundtrip() {
+        let export_data = PoolPtrExportData {
+            data: [42u8; 64],
+            size: 1 << 20,
+        };
+        let encoded = export_data.to_base64();
+        assert_eq!(
+            PoolPtrExportData::from_base64(&encoded).unwrap(),
+            export_data
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_ipc_handle_serde_json() {
+        let handle = IpcMemHandle::from_bytes(&[200u8; IpcMemHandle::SIZE]).unwrap();
+
+        let json = serde_json::to_string(&handle).unwrap();
+        assert_eq!(json, format!("\"{}\"", handle.to_base64()));
+
+        let decoded: IpcMemHandle = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, handle);
+
+        let invalid: Result<IpcMemHandle, _> = serde_json::from_str("\"AAAA\"");
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_memory_pointer_ipc_handle() {
+        let ptr = MemoryPointer::<f32>::alloc(1024).unwrap();
+        let handle = ptr.ipc_handle().unwrap();
+        assert_ne!(handle.to_bytes(), [0u8; IpcMemHandle::SIZE]);
+    }
+
+    #[test]
+    fn test_null_pointer_ipc_handle() {
+        let ptr = MemoryPointer::<f32>::alloc(0).unwrap();
+        let result = ptr.ipc_handle();
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+    }
+
+    #[test]
+    fn test_event_ipc_handle() {
+        use crate::EventFlags;
+
+        let event = Event::create_with_flags(EventFlags::INTERPROCESS | EventFlags::DISABLE_TIMING)
+            .unwrap();
+        assert!(event.ipc_handle().is_ok());
+
+        // Timing events cannot be shared
+        let event = Event::create().unwrap();
+        assert!(event.ipc_handle().is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_mempool_export_fd() {
+        let props =
+            MemPoolProps::new().with_handle_types(MemAllocationHandleType::PosixFileDescriptor);
+        let pool = MemPool::create(props).unwrap();
+
+        let fd = pool.export_fd().unwrap();
+        assert!(fd.as_raw_fd() >= 0);
+    }
+
+    #[test]
+    fn test_mempool_export_pointer() {
+        let pool = MemPool::create(MemPoolProps::new()).unwrap();
+        let stream = Stream::create().unwrap();
+
+        let ptr = pool.alloc_async::<f32>(256, &stream).unwrap();
+        let export_data = pool.export_pointer(&ptr).unwrap();
+        assert_eq!(export_data.size(), 256 * std::mem::size_of::<f32>());
+    }
+}
@@ -0,0 +1,934 @@
+use super::allocator::DeviceAllocator;
+use super::device_copy::DeviceCopy;
+use super::device_types::DeviceAttribute;
+use super::memory::MemoryPointer;
+use super::module::{Function, FunctionAttribute};
+use super::result::{HipError, HipResult, HipStatus};
+use super::slice::DeviceSlice;
+use super::stream::Stream;
+use crate::code_object::{ArgValueKind, KernelMetadata};
+use crate::result::{ResultExt, StatusCode};
+use crate::sys;
+use std::ffi::c_void;
+use std::fmt;
+
+/// Block size used by [`LaunchConfig::for_num_elements`]
+const DEFAULT_BLOCK_SIZE: u32 = 256;
+
+/// Grid or block dimensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Dim3 {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+impl Dim3 {
+    pub fn new(x: u32, y: u32, z: u32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Returns the total number of blocks or threads, saturating at `u64::MAX`.
+    pub fn volume(&self) -> u64 {
+        (self.x as u64 * self.y as u64).saturating_mul(self.z as u64)
+    }
+}
+
+impl From<u32> for Dim3 {
+    fn from(x: u32) -> Self {
+        Self::new(x, 1, 1)
+    }
+}
+
+impl From<(u32, u32)> for Dim3 {
+    fn from((x, y): (u32, u32)) -> Self {
+        Self::new(x, y, 1)
+    }
+}
+
+impl From<(u32, u32, u32)> for Dim3 {
+    fn from((x, y, z): (u32, u32, u32)) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+/// Grid, block and dynamic shared memory for a kernel launch.
+///
+/// # Examples
+/// ```
+/// use hip_rs::{Dim3, LaunchConfig};
+///
+/// let config = LaunchConfig::for_num_elements(1000);
+/// assert_eq!(config.grid(), Dim3::new(4, 1, 1));
+/// assert_eq!(config.block(), Dim3::new(256, 1, 1));
+///
+/// let config = LaunchConfig::new((16, 16), (8, 8)).with_shared_mem_bytes(4096);
+/// assert_eq!(config.block().volume(), 64);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LaunchConfig {
+    grid: Dim3,
+    block: Dim3,
+    shared_mem_bytes: u32,
+}
+
+impl LaunchConfig {
+    /// Creates a config with the given grid and block dimensions and no dynamic shared memory.
+    pub fn new(grid: impl Into<Dim3>, block: impl Into<Dim3>) -> Self {
+        Self {
+            grid: grid.into(),
+            block: block.into(),
+            shared_mem_bytes: 0,
+        }
+    }
+
+    /// Creates a 1D config with one thread per element in blocks of 256.
+    ///
+    /// The grid has at least one block, so kernels must bounds-check their index.
+    pub fn for_num_elements(n: u32) -> Self {
+        Self::new(n.div_ceil(DEFAULT_BLOCK_SIZE).max(1), DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Creates a 1D config for `n` elements with the block size that
+    /// maximizes the kernel's occupancy on the current device.
+    ///
+    /// The suggestion assumes no dynamic shared memory. Use
+    /// [`Function::max_potential_block_size`] directly when the kernel needs some.
+    ///
+    /// # Arguments
+    /// * `function` - The kernel to launch
+    /// * `n` - Number of elements, one thread each
+    ///
+    /// # Returns
+    /// * `Ok(LaunchConfig)` - A config with at least one block
+    /// * `Err(HipError)` - If the occupancy query failed
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hip_rs::{LaunchConfig, Module};
+    ///
+    /// let module = Module::load("kernels.hsaco").unwrap();
+    /// let scale = module.function("scale").unwrap();
+    /// let config = LaunchConfig::auto_for(&scale, 1_000_000).unwrap();
+    /// ```
+    pub fn auto_for(function: &Function<'_>, n: u32) -> HipResult<Self> {
+        let block_size = function.max_potential_block_size(0, 0)?.block_size;
+        if block_size == 0 {
+            return Err(HipError::from_status(HipStatus::InvalidConfiguration));
+        }
+        Ok(Self::new(n.div_ceil(block_size).max(1), block_size))
+    }
+
+    /// Sets the dynamic shared memory per block in bytes.
+    pub fn with_shared_mem_bytes(mut self, bytes: u32) -> Self {
+        self.shared_mem_bytes = bytes;
+        self
+    }
+
+    pub fn grid(&self) -> Dim3 {
+        self.grid
+    }
+
+    pub fn block(&self) -> Dim3 {
+        self.block
+    }
+
+    pub fn shared_mem_bytes(&self) -> u32 {
+        self.shared_mem_bytes
+    }
+}
+
+/// How a kernel argument is passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelArgKind {
+    /// A value copied into the kernel argument segment
+    ByValue,
+    /// A pointer to device memory
+    GlobalPointer,
+}
+
+/// Placement of one argument in the packed argument list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KernelArgLayout {
+    /// Byte offset, aligned to `align`
+    pub offset: usize,
+    pub size: usize,
+    pub align: usize,
+    pub kind: KernelArgKind,
+}
+
+/// Storage word aligned for any argument type
+#[repr(C, align(16))]
+#[derive(Debug, Clone, Copy)]
+struct ArgWord([u8; 16]);
+
+/// Kernel arguments packed with their natural alignment, as in the kernel
+/// argument segment.
+///
+/// Usually built from a tuple through [`KernelArgList`], but can be built by
+/// hand when the number of arguments is only known at runtime.
+///
+/// # Examples
+/// ```
+/// use hip_rs::{KernelArgKind, KernelArgs, MemoryPointer};
+///
+/// let buffer = MemoryPointer::<f32>::alloc(16).unwrap();
+/// let mut args = KernelArgs::new();
+/// args.push_pointer(buffer.as_pointer());
+/// args.push(&16u32);
+/// assert_eq!(args.layout()[0].kind, KernelArgKind::GlobalPointer);
+/// assert_eq!(args.layout()[1].offset, 8);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KernelArgs {
+    storage: Vec<ArgWord>,
+    layout: Vec<KernelArgLayout>,
+    size: usize,
+}
+
+impl KernelArgs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an argument.
+    pub fn push<A: KernelArg + ?Sized>(&mut self, arg: &A) {
+        arg.push_to(self);
+    }
+
+    /// Appends a value passed by copy.
+    pub fn push_value<T: DeviceCopy>(&mut self, value: &T) {
+        let align = std::mem::align_of::<T>();
+        let size = std::mem::size_of::<T>();
+        unsafe {
+            self.push_raw(
+                value as *const T as *const u8,
+                size,
+                align,
+                KernelArgKind::ByValue,
+            )
+        }
+    }
+
+    /// Appends a pointer to device memory.
+    pub fn push_pointer<T>(&mut self, pointer: *mut T) {
+        let address = pointer as usize;
+        unsafe {
+            self.push_raw(
+                &address as *const usize as *const u8,
+                std::mem::size_of::<usize>(),
+                std::mem::align_of::<usize>(),
+                KernelArgKind::GlobalPointer,
+            )
+        }
+    }
+
+    /// Copies `size` bytes from `bytes` to the next offset aligned to `align`.
+    unsafe fn push_raw(
+        &mut self,
+        bytes: *const u8,
+        size: usize,
+        align: usize,
+        kind: KernelArgKind,
+    ) {
+        let offset = self.size.next_multiple_of(align);
+        self.size = offset + size;
+        let words = self.size.div_ceil(std::mem::size_of::<ArgWord>());
+        self.storage.resize(words, ArgWord([0; 16]));
+        let base = self.storage.as_mut_ptr() as *mut u8;
+        std::ptr::copy_nonoverlapping(bytes, base.add(offset), size);
+        self.layout.push(KernelArgLayout {
+            offset,
+            size,
+            align,
+            kind,
+        });
+    }
+
+    /// Returns the number of arguments.
+    pub fn len(&self) -> usize {
+        self.layout.len()
+    }
+
+    /// Returns true if there are no arguments.
+    pub fn is_empty(&self) -> bool {
+        self.layout.is_empty()
+    }
+
+    /// Returns the total size of the packed arguments in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the placement of each argument in order.
+    pub fn layout(&self) -> &[KernelArgLayout] {
+        &self.layout
+    }
+
+    /// Checks the arguments against the kernel's metadata.
+    ///
+    /// Hidden arguments are filled in by the runtime and skipped. Every
+    /// explicit argument must have the same size and offset, which catches
+    /// alignment differences between host and device types, and pointers
+    /// must be passed for global buffers and values for by-value arguments.
+    ///
+    /// # Arguments
+    /// * `metadata` - The kernel's [`KernelMetadata`]
+    ///
+    /// # Returns
+    /// * `Ok(())` - The arguments match
+    /// * `Err(KernelArgMismatch)` - The first argument that does not match
+    pub fn validate(&self, metadata: &KernelMetadata) -> Result<(), KernelArgMismatch> {
+        let expected: Vec<_> = metadata.explicit_args().collect();
+        if expected.len() != self.layout.len() {
+            return Err(KernelArgMismatch::Count {
+                expected: expected.len(),
+                found: self.layout.len(),
+            });
+        }
+        for (index, (arg, layout)) in expected.iter().zip(&self.layout).enumerate() {
+            let name = arg.name.clone().filter(|name| !name.is_empty());
+            let expected_kind = match arg.value_kind {
+                ArgValueKind::ByValue => Some(KernelArgKind::ByValue),
+                ArgValueKind::GlobalBuffer => Some(KernelArgKind::GlobalPointer),
+                _ => None,
+            };
+            if expected_kind.is_some_and(|kind| kind != layout.kind) {
+                return Err(KernelArgMismatch::Kind {
+                    index,
+                    name,
+                    expected: arg.value_kind.clone(),
+                    found: layout.kind,
+                });
+            }
+            if arg.size as usize != layout.size {
+                return Err(KernelArgMismatch::Size {
+                    index,
+                    name,
+                    expected: arg.size as usize,
+                    found: layout.size,
+                });
+            }
+            if arg.offset as usize != layout.offset {
+                return Err(KernelArgMismatch::Offset {
+                    index,
+                    name,
+                    expected: arg.offset as usize,
+                    found: layout.offset,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a pointer to each argument, as `hipModuleLaunchKernel` expects.
+    fn param_pointers(&mut self) -> Vec<*mut c_void> {
+        let base = self.storage.as_mut_ptr() as *mut u8;
+        self.layout
+            .iter()
+            .map(|arg| unsafe { base.add(arg.offset) as *mut c_void })
+            .collect()
+    }
+}
+
+/// The first difference between [`KernelArgs`] and a kernel's parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KernelArgMismatch {
+    /// The number of arguments differs
+    Count { expected: usize, found: usize },
+    /// A pointer was passed for a value or the other way around
+    Kind {
+        index: usize,
+        name: Option<String>,
+        expected: ArgValueKind,
+        found: KernelArgKind,
+    },
+    /// An argument has a different size in bytes
+    Size {
+        index: usize,
+        name: Option<String>,
+        expected: usize,
+        found: usize,
+    },
+    /// An argument starts at a different offset, usually because a
+    /// preceding type is aligned differently on the device
+    Offset {
+        index: usize,
+        name: Option<String>,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for KernelArgMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arg = |index: &usize, name: &Option<String>| match name {
+            Some(name) => format!("argument {} ('{}')", index, name),
+            None => format!("argument {}", index),
+        };
+        match self {
+            KernelArgMismatch::Count { expected, found } => write!(
+                f,
+                "the kernel takes {} arguments but {} were passed",
+                expected, found
+            ),
+            KernelArgMismatch::Kind {
+                index,
+                name,
+                expected,
+                found,
+            } => {
+                let found = match found {
+                    KernelArgKind::ByValue => "a value",
+                    KernelArgKind::GlobalPointer => "a device pointer",
+                };
+                write!(
+                    f,
+                    "{} is {} but the kernel expects {}",
+                    arg(index, name),
+                    found,
+                    expected.name()
+                )
+            }
+            KernelArgMismatch::Size {
+                index,
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} is {} bytes but the kernel expects {} bytes",
+                arg(index, name),
+                found,
+                expected
+            ),
+            KernelArgMismatch::Offset {
+                index,
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} is at offset {} but the kernel expects offset {}, check the alignment of the preceding arguments",
+                arg(index, name),
+                found,
+                expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KernelArgMismatch {}
+
+/// Errors from [`Function::launch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LaunchError {
+    /// The configuration exceeds a limit or the launch failed
+    Hip(HipError),
+    /// The arguments do not match the kernel's metadata
+    Arguments {
+        kernel: String,
+        mismatch: KernelArgMismatch,
+    },
+}
+
+impl fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LaunchError::Hip(error) => StatusCode::fmt(error, f),
+            LaunchError::Arguments { kernel, mismatch } => {
+                write!(f, "invalid arguments for kernel '{}': {}", kernel, mismatch)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LaunchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LaunchError::Arguments { mismatch, .. } => Some(mismatch),
+            _ => None,
+        }
+    }
+}
+
+impl From<HipError> for LaunchError {
+    fn from(error: HipError) -> Self {
+        LaunchError::Hip(error)
+    }
+}
+
+/// A value that can be passed as a kernel argument.
+///
+/// [`DeviceCopy`] values are passed by copy; device buffers and slices are
+/// passed as pointers to their first element.
+///
+/// Buffers must be borrowed. Moving one into the argument list would free
+/// it before the kernel runs:
+/// ```compile_fail,E0277
+/// use hip_rs::{launch, MemoryPointer, Module, Stream};
+///
+/// let module = Module::load("kernels.hsaco").unwrap();
+/// let fill = module.function("fill").unwrap();
+/// let stream = Stream::create().unwrap();
+/// let buffer = MemoryPointer::<f32>::alloc(1024).unwrap();
+///
+/// unsafe {
+///     launch!(fill<<<4, 256, 0, stream>>>(buffer, 1024u32)).unwrap();
+/// }
+/// ```
+pub trait KernelArg {
+    fn push_to(&self, args: &mut KernelArgs);
+}
+
+impl<T: DeviceCopy> KernelArg for T {
+    fn push_to(&self, args: &mut KernelArgs) {
+        args.push_value(self);
+    }
+}
+
+impl<T: DeviceCopy> KernelArg for DeviceSlice<'_, T> {
+    fn push_to(&self, args: &mut KernelArgs) {
+        args.push_pointer(self.as_pointer());
+    }
+}
+
+// Buffers are passed by reference so they outlive the launch
+impl<T: DeviceCopy> KernelArg for &DeviceSlice<'_, T> {
+    fn push_to(&self, args: &mut KernelArgs) {
+        args.push_pointer(self.as_pointer());
+    }
+}
+
+impl<T: DeviceCopy, A: DeviceAllocator> KernelArg for &MemoryPointer<T, A> {
+    fn push_to(&self, args: &mut KernelArgs) {
+        args.push_pointer(self.as_pointer());
+    }
+}
+
+impl<T: DeviceCopy, A: DeviceAllocator> KernelArg for &mut MemoryPointer<T, A> {
+    fn push_to(&self, args: &mut KernelArgs) {
+        args.push_pointer(self.as_pointer());
+    }
+}
+
+/// A complete argument list: a tuple of [`KernelArg`]s or prebuilt [`KernelArgs`].
+pub trait KernelArgList {
+    fn into_kernel_args(self) -> KernelArgs;
+}
+
+impl KernelArgList for KernelArgs {
+    fn into_kernel_args(self) -> KernelArgs {
+        self
+    }
+}
+
+macro_rules! impl_kernel_arg_list {
+    ($($name:ident),*) => {
+        impl<$($name: KernelArg),*> KernelArgList for ($($name,)*) {
+            #[allow(non_snake_case, unused_mut)]
+            fn into_kernel_args(self) -> KernelArgs {
+                let ($($name,)*) = self;
+                let mut args = KernelArgs::new();
+                $(args.push(&$name);)*
+                args
+            }
+        }
+    };
+}
+
+impl_kernel_arg_list!();
+impl_kernel_arg_list!(A);
+impl_kernel_arg_list!(A, B);
+impl_kernel_arg_list!(A, B, C);
+impl_kernel_arg_list!(A, B, C, D);
+impl_kernel_arg_list!(A, B, C, D, E);
+impl_kernel_arg_list!(A, B, C, D, E, F);
+impl_kernel_arg_list!(A, B, C, D, E, F, G);
+impl_kernel_arg_list!(A, B, C, D, E, F, G, H);
+impl_kernel_arg_list!(A, B, C, D, E, F, G, H, I);
+impl_kernel_arg_list!(A, B, C, D, E, F, G, H, I, J);
+impl_kernel_arg_list!(A, B, C, D, E, F, G, H, I, J, K);
+impl_kernel_arg_list!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// Launch limits of a kernel on the device its module was loaded on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LaunchLimits {
+    max_threads_per_block: u64,
+    max_block: Dim3,
+    max_grid: Dim3,
+    max_shared_mem_bytes: u32,
+}
+
+impl LaunchLimits {
+    fn query(function: &Function<'_>) -> HipResult<Self> {
+        let device = function.device();
+        let attribute = |attr| device.attribute(attr).map(|value| value.max(0) as u32);
+        let device_threads = attribute(DeviceAttribute::MaxThreadsPerBlock)?;
+        let function_threads = function.attribute(FunctionAttribute::MaxThreadsPerBlock)?;
+        let static_shared = function.attribute(FunctionAttribute::SharedSizeBytes)?;
+        Ok(Self {
+            max_threads_per_block: device_threads.min(function_threads.max(0) as u32) as u64,
+            max_block: Dim3::new(
+                attribute(DeviceAttribute::MaxBlockDimX)?,
+                attribute(DeviceAttribute::MaxBlockDimY)?,
+                attribute(DeviceAttribute::MaxBlockDimZ)?,
+            ),
+            max_grid: Dim3::new(
+                attribute(DeviceAttribute::MaxGridDimX)?,
+                attribute(DeviceAttribute::MaxGridDimY)?,
+                attribute(DeviceAttribute::MaxGridDimZ)?,
+            ),
+            max_shared_mem_bytes: attribute(DeviceAttribute::MaxSharedMemoryPerBlock)?
+                .saturating_sub(static_shared.max(0) as u32),
+        })
+    }
+
+    /// Checks that `config` can be launched, rejecting empty or oversized dimensions.
+    fn check(&self, config: &LaunchConfig) -> HipResult<()> {
+        let within = |dim: Dim3, max: Dim3| {
+            dim.volume() > 0 && dim.x <= max.x && dim.y <= max.y && dim.z <= max.z
+        };
+        if !within(config.block, self.max_block)
+            || config.block.volume() > self.max_threads_per_block
+            || !within(config.grid, self.max_grid)
+            || config.shared_mem_bytes > self.max_shared_mem_bytes
+        {
+            return Err(HipError::from_status(HipStatus::InvalidConfiguration));
+        }
+        Ok(())
+    }
+}
+
+impl Function<'_> {
+    /// Enqueues the kernel on `stream`.
+    ///
+    /// The grid, block and shared memory are checked against the limits of
+    /// the device and the kernel before launching. In debug builds the
+    /// arguments are also checked against the kernel's metadata, when the
+    /// module's code object could be read, see [`KernelArgs::validate`].
+    ///
+    /// # Arguments
+    /// * `config` - The [`LaunchConfig`]
+    /// * `stream` - The stream to launch on
+    /// * `args` - A tuple of kernel arguments, or prebuilt [`KernelArgs`]
+    ///
+    /// # Returns
+    /// * `Ok(())` - The kernel was enqueued
+    /// * `Err(LaunchError)` - If the arguments do not match the kernel, the config exceeds a limit
+    ///   (`InvalidConfiguration`) or the launch failed
+    ///
+    /// # Safety
+    /// The arguments must match the kernel's parameters in number, order and
+    /// type, and every memory access the kernel makes with them must be valid
+    /// until it completes.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hip_rs::{LaunchConfig, MemoryPointer, Module, Stream};
+    ///
+    /// let module = Module::load("kernels.hsaco").unwrap();
+    /// let scale = module.function("scale").unwrap();
+    /// let stream = Stream::create().unwrap();
+    /// let mut data = MemoryPointer::<f32>::alloc(1000).unwrap();
+    ///
+    /// let config = LaunchConfig::for_num_elements(1000);
+    /// unsafe { scale.launch(&config, &stream, (&mut data, 2.0f32, 1000u32)) }.unwrap();
+    /// stream.synchronize().unwrap();
+    /// ```
+    pub unsafe fn launch<A: KernelArgList>(
+        &self,
+        config: &LaunchConfig,
+        stream: &Stream,
+        args: A,
+    ) -> Result<(), LaunchError> {
+        let limits = match self.limits.get() {
+            Some(limits) => *limits,
+            None => {
+                let limits = LaunchLimits::query(self)?;
+                *self.limits.get_or_init(|| limits)
+            }
+        };
+        limits.check(config)?;
+
+        let mut args = args.into_kernel_args();
+        if cfg!(debug_assertions) {
+            if let Some(metadata) = self.metadata() {
+                args.validate(metadata)
+                    .map_err(|mismatch| LaunchError::Arguments {
+                        kernel: self.name().to_string(),
+                        mismatch,
+                    })?;
+            }
+        }
+        let mut params = args.param_pointers();
+        let code = sys::hipModuleLaunchKernel(
+            self.handle(),
+            config.grid.x,
+            config.grid.y,
+            config.grid.z,
+            config.block.x,
+            config.block.y,
+            config.block.z,
+            config.shared_mem_bytes,
+            stream.handle(),
+            params.as_mut_ptr(),
+            std::ptr::null_mut(),
+        );
+        let result: HipResult<()> = ((), code).to_result();
+        Ok(result?)
+    }
+}
+
+/// Launches a kernel with CUDA-style syntax.
+///
+/// Takes either a [`LaunchConfig`] or grid, block and shared memory size,
+/// followed by the stream. Expands to [`Function::launch`], so it must be
+/// used in an `unsafe` block.
+///
+/// # Examples
+/// ```no_run
+/// use hip_rs::{launch, LaunchConfig, MemoryPointer, Module, Stream};
+///
+/// let module = Module::load("kernels.hsaco").unwrap();
+/// let add = module.function("vector_add").unwrap();
+/// let stream = Stream::create().unwrap();
+/// let a = MemoryPointer::<f32>::alloc(1024).unwrap();
+/// let b = MemoryPointer::<f32>::alloc(1024).unwrap();
+/// let mut c = MemoryPointer::<f32>::alloc(1024).unwrap();
+///
+/// unsafe {
+///     launch!(add<<<4, 256, 0, stream>>>(&a, &b, &mut c, 1024u32)).unwrap();
+///     let config = LaunchConfig::for_num_elements(1024);
+///     launch!(add<<<config, stream>>>(&a, &b, &mut c, 1024u32)).unwrap();
+/// }
+/// ```
+#[macro_export]
+macro_rules! launch {
+    ($function:ident <<<$config:expr, $stream:ident>>>($($arg:expr),* $(,)?)) => {
+        $function.launch(&$config, &$stream, ($($arg,)*))
+    };
+    ($function:ident <<<$grid:expr, $block:expr, $shared:expr, $stream:ident>>>($($arg:expr),* $(,)?)) => {
+        $function.launch(
+            &$crate::LaunchConfig::new($grid, $block).with_shared_mem_bytes($shared),
+            &$stream,
+            ($($arg,)*),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_object::KernelArgMetadata;
+
+    fn limits() -> LaunchLimits {
+        LaunchLimits {
+            max_threads_per_block: 1024,
+            max_block: Dim3::new(1024, 1024, 64),
+            max_grid: Dim3::new(u32::MAX, 65535, 65535),
+            max_shared_mem_bytes: 65536,
+        }
+    }
+
+    #[test]
+    fn test_dim3() {
+        assert_eq!(Dim3::from(8), Dim3::new(8, 1, 1));
+        assert_eq!(Dim3::from((8, 4)), Dim3::new(8, 4, 1));
+        assert_eq!(Dim3::from((8, 4, 2)).volume(), 64);
+        assert_eq!(
+            Dim3::new(u32::MAX, u32::MAX, 1).volume(),
+            (u32::MAX as u64).pow(2)
+        );
+        assert_eq!(Dim3::new(u32::MAX, u32::MAX, 2).volume(), u64::MAX);
+    }
+
+    #[test]
+    fn test_for_num_elements() {
+        assert_eq!(
+            LaunchConfig::for_num_elements(256).grid(),
+            Dim3::new(1, 1, 1)
+        );
+        assert_eq!(
+            LaunchConfig::for_num_elements(257).grid(),
+            Dim3::new(2, 1, 1)
+        );
+        assert_eq!(LaunchConfig::for_num_elements(0).grid(), Dim3::new(1, 1, 1));
+        assert_eq!(
+            LaunchConfig::for_num_elements(u32::MAX).grid().x,
+            u32::MAX.div_ceil(256)
+        );
+    }
+
+    #[test]
+    fn test_check_limits() {
+        let limits = limits();
+        assert!(limits.check(&LaunchConfig::new(100, 1024)).is_ok());
+        assert!(limits.check(&LaunchConfig::new((10, 10), (32, 32))).is_ok());
+
+        let invalid = [
+            LaunchConfig::new(1, 1025),
+            LaunchConfig::new(1, (64, 32)),
+            LaunchConfig::new(1, (1, 1, 128)),
+            LaunchConfig::new(1, 0),
+            LaunchConfig::new(0, 256),
+            LaunchConfig::new((1, 70000), 256),
+            LaunchConfig::new(1, 256).with_shared_mem_bytes(65537),
+        ];
+        for config in invalid {
+            let error = limits.check(&config).unwrap_err();
+            assert_eq!(
+                error.status,
+                HipStatus::InvalidConfiguration,
+                "{:?}",
+                config
+            );
+        }
+    }
+
+    #[test]
+    fn test_kernel_args_layout() {
+        let pointer = 0x1000usize as *mut f32;
+        let mut args = KernelArgs::new();
+        args.push(&1u8);
+        args.push(&unsafe { DeviceSlice::from_raw_parts(pointer, 4) });
+        args.push(&2u16);
+        args.push(&[3.0f32; 3]);
+        args.push(&4u64);
+
+        let layout: Vec<_> = args
+            .layout()
+            .iter()
+            .map(|arg| (arg.offset, arg.size, arg.kind))
+            .collect();
+        assert_eq!(
+            layout,
+            vec![
+                (0, 1, KernelArgKind::ByValue),
+                (8, 8, KernelArgKind::GlobalPointer),
+                (16, 2, KernelArgKind::ByValue),
+                (20, 12, KernelArgKind::ByValue),
+                (32, 8, KernelArgKind::ByValue),
+            ]
+        );
+        assert_eq!(args.size(), 40);
+
+        let params = args.param_pointers();
+        unsafe {
+            assert_eq!(*(params[0] as *const u8), 1);
+            assert_eq!(*(params[1] as *const usize), 0x1000);
+            assert_eq!(*(params[2] as *const u16), 2);
+            assert_eq!(*(params[3] as *const [f32; 3]), [3.0; 3]);
+            assert_eq!(*(params[4] as *const u64), 4);
+        }
+    }
+
+    #[test]
+    fn test_tuple_args() {
+        let buffer = 0x2000usize as *mut u32;
+        let slice = unsafe { DeviceSlice::from_raw_parts(buffer, 8) };
+        let args = (&slice, 8u32, 0.5f64).into_kernel_args();
+        assert_eq!(args.len(), 3);
+        assert_eq!(args.layout()[0].kind, KernelArgKind::GlobalPointer);
+        assert_eq!(args.layout()[2].offset, 16);
+        assert!(().into_kernel_args().is_empty());
+    }
+
+    fn kernel(args: &[(&str, u32, u32, &str)]) -> KernelMetadata {
+        KernelMetadata {
+            name: "scale".to_string(),
+            args: args
+                .iter()
+                .map(|&(name, offset, size, kind)| KernelArgMetadata {
+                    name: Some(name.to_string()),
+                    type_name: None,
+                    offset,
+                    size,
+                    value_kind: ArgValueKind::from_name(kind),
+                    address_space: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_args() {
+        let metadata = kernel(&[
+            ("data", 0, 8, "global_buffer"),
+            ("factor", 8, 4, "by_value"),
+            ("n", 12, 4, "by_value"),
+            ("", 16, 8, "hidden_global_offset_x"),
+        ]);
+        let pointer = 0x1000usize as *mut f32;
+        let data = unsafe { DeviceSlice::from_raw_parts(pointer, 4) };
+        assert_eq!(
+            (&data, 2.0f32, 4u32).into_kernel_args().validate(&metadata),
+            Ok(())
+        );
+
+        let cases = [
+            (
+                (&data, 2.0f32).into_kernel_args(),
+                KernelArgMismatch::Count {
+                    expected: 3,
+                    found: 2,
+                },
+            ),
+            (
+                (0x1000u64, 2.0f32, 4u32).into_kernel_args(),
+                KernelArgMismatch::Kind {
+                    index: 0,
+                    name: Some("data".to_string()),
+                    expected: ArgValueKind::GlobalBuffer,
+                    found: KernelArgKind::ByValue,
+                },
+            ),
+            (
+                (&data, 2.0f64, 4u32).into_kernel_args(),
+                KernelArgMismatch::Size {
+                    index: 1,
+                    name: Some("factor".to_string()),
+                    expected: 4,
+                    found: 8,
+                },
+            ),
+            (
+                (&data, 2u16, 0u16, 4u32).into_kernel_args(),
+                KernelArgMismatch::Count {
+                    expected: 3,
+                    found: 4,
+                },
+            ),
+        ];
+        for (args, expected) in cases {
+            assert_eq!(args.validate(&metadata), Err(expected));
+        }
+    }
+
+    #[test]
+    fn test_validate_alignment() {
+        // A float4 is 16 byte aligned on the device but [f32; 4] is not
+        let metadata = kernel(&[("flag", 0, 4, "by_value"), ("value", 16, 16, "by_value")]);
+        let args = (1u32, [0.0f32; 4]).into_kernel_args();
+        let mismatch = args.validate(&metadata).unwrap_err();
+        assert_eq!(
+            mismatch,
+            KernelArgMismatch::Offset {
+                index: 1,
+                name: Some("value".to_string()),
+                expected: 16,
+                found: 4,
+            }
+        );
+        let error = LaunchError::Arguments {
+            kernel: "scale".to_string(),
+            mismatch,
+        };
+        assert_eq!(
+            error.to_string(),
+            "invalid arguments for kernel 'scale': argument 1 ('value') is at offset 4 but the \
+             kernel expects offset 16, check the alignment of the preceding arguments"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use super::device_copy::{write_zeroed, DeviceCopy};
+use super::result::{HipError, HipResult, HipStatus};
+use crate::result::ResultExt;
+use crate::sys;
+use std::ops::{Deref, DerefMut};
+
+/// `hipMemAttachGlobal`, a preprocessor define not covered by the bindings
+const MEM_ATTACH_GLOBAL: u32 = 0x1;
+
+/// Managed memory allocated with `hipMallocManaged`.
+///
+/// Managed memory is accessible from the host and from every device through
+/// the same pointer and migrates on demand. The buffer dereferences to a
+/// slice on the host; synchronize with the device before touching memory
+/// that kernels may still be using.
+///
+/// # Examples
+/// ```
+/// use hip_rs::ManagedBuffer;
+///
+/// let mut buffer = ManagedBuffer::<i32>::from_slice(&[1, 2, 3]).unwrap();
+/// buffer[0] = 10;
+/// assert_eq!(&buffer[..], &[10, 2, 3]);
+/// ```
+#[derive(Debug)]
+pub struct ManagedBuffer<T: DeviceCopy> {
+    pointer: *mut T,
+    len: usize,
+}
+
+impl<T: DeviceCopy> ManagedBuffer<T> {
+    /// Allocates a zero-initialized buffer of `len` elements.
+    ///
+    /// # Arguments
+    /// * `len` - The number of elements
+    ///
+    /// # Returns
+    /// * `Ok(ManagedBuffer<T>)` - The allocated buffer
+    /// * `Err(HipError)` - If the size overflows or the allocation fails
+    #[track_caller]
+    pub fn alloc(len: usize) -> HipResult<Self> {
+        let bytes = len
+            .checked_mul(std::mem::size_of::<T>())
+            .ok_or(HipError::from_status(HipStatus::InvalidValue))?;
+        // Empty buffers and zero-sized types need no memory
+        if bytes == 0 {
+            return Ok(Self {
+                pointer: std::ptr::NonNull::dangling().as_ptr(),
+                len,
+            });
+        }
+
+        let mut ptr = std::ptr::null_mut();
+        let code = unsafe { sys::hipMallocManaged(&mut ptr, bytes, MEM_ATTACH_GLOBAL) };
+        let result: HipResult<()> = ((), code).to_result();
+        result?;
+
+        unsafe { write_zeroed(ptr as *mut T, len) };
+
+        #[cfg(feature = "track-allocations")]
+        super::tracking::register(
+            super::tracking::AllocationKind::ManagedMemory,
+            ptr as usize,
+            bytes,
+            crate::get_device().ok(),
+        );
+
+        Ok(Self {
+            pointer: ptr as *mut T,
+            len,
+        })
+    }
+
+    /// Allocates a buffer holding a copy of `data`.
+    #[track_caller]
+    pub fn from_slice(data: &[T]) -> HipResult<Self> {
+        let mut buffer = Self::alloc(data.len())?;
+        buffer.copy_from_slice(data);
+        Ok(buffer)
+    }
+
+    /// Returns the number of elements in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the buffer holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the pointer, valid on the host and on the device.
+    pub fn as_pointer(&self) -> *mut T {
+        self.pointer
+    }
+}
+
+impl<T: DeviceCopy> Deref for ManagedBuffer<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.pointer, self.len) }
+    }
+}
+
+impl<T: DeviceCopy> DerefMut for ManagedBuffer<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.pointer, self.len) }
+    }
+}
+
+impl<T: DeviceCopy> Drop for ManagedBuffer<T> {
+    fn drop(&mut self) {
+        // Nothing was allocated, see `alloc`
+        if self.len * std::mem::size_of::<T>() == 0 {
+            return;
+        }
+
+        #[cfg(feature = "track-allocations")]
+        super::tracking::unregister(
+            super::tracking::AllocationKind::ManagedMemory,
+            self.pointer as usize,
+        );
+
+        unsafe {
+            let code = sys::hipFree(self.pointer as *mut std::ffi::c_void);
+            if code != 0 {
+                log::error!(
+                    "ManagedBuffer failed to free memory: {:?}",
+                    HipError::new(code)
+                );
+            }
+        }
+    }
+}
+
+// The buffer owns its memory like a Box<[T]>
+unsafe impl<T: DeviceCopy + Send> Send for ManagedBuffer<T> {}
+unsafe impl<T: DeviceCopy + Sync> Sync for ManagedBuffer<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_managed_alloc() {
+        let buffer = ManagedBuffer::<f32>::alloc(1024).unwrap();
+        assert_eq!(buffer.len(), 1024);
+        assert!(buffer.iter().all(|&value| value == 0.0));
+
+        let info = crate::PointerInfo::query(buffer.as_pointer()).unwrap();
+        assert_eq!(info.memory_type, crate::MemoryType::Managed);
+    }
+
+    #[test]
+    fn test_managed_zero_len() {
+        let buffer = ManagedBuffer::<u8>::alloc(0).unwrap();
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_managed_zero_sized_type() {
+        let buffer = ManagedBuffer::<()>::alloc(8).unwrap();
+        assert_eq!(buffer.len(), 8);
+        assert_eq!(&buffer[..], &[(); 8]);
+    }
+}
@@ -1,15 +1,26 @@
+use super::allocator::{
+    DeviceAllocator, DeviceMalloc, DeviceMallocWithFlags, PoolAllocator, StreamOrderedAllocator,
+};
+use super::copy::copy;
+use super::device_copy::DeviceCopy;
 use super::flags::DeviceMallocFlag;
 use super::result::{HipError, HipResult, HipStatus};
 use crate::result::ResultExt;
 use crate::sys;
-use crate::Stream;
+use crate::{Device, Stream};
 
 /// A wrapper for device memory allocated on the GPU.
 /// Automatically frees the memory when dropped.
-#[derive(Debug, Clone)]
-pub struct MemoryPointer<T> {
+///
+/// The memory is obtained from and returned to a [`DeviceAllocator`],
+/// `hipMalloc` by default.
+///
+/// Not `Clone`: copies would return the same memory to the allocator twice.
+#[derive(Debug)]
+pub struct MemoryPointer<T, A: DeviceAllocator = DeviceMalloc> {
     pointer: *mut T,
     size: usize,
+    allocator: A,
 }
 
 #[derive(Debug, Clone)]
@@ -56,6 +67,160 @@ pub struct MemLocation {
     pub id: i32,                // Device ID or other identifier
 }
 
+impl MemLocation {
+    /// Creates a location describing memory resident on `device`.
+    pub fn device(device: Device) -> Self {
+        Self {
+            type_: MemLocationType::Device,
+            id: device.id(),
+        }
+    }
+
+    /// Convert to the raw HIP hipMemLocation structure
+    pub(crate) fn to_sys(&self) -> sys::hipMemLocation {
+        sys::hipMemLocation {
+            type_: self.type_ as u32,
+            id: self.id,
+        }
+    }
+}
+
+/// Access permissions a device has to memory from a pool or a virtual memory mapping.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemAccessFlags {
+    /// No access. Not allowed for the device the pool resides on.
+    None = 0,
+    /// Read-only access
+    Read = 1,
+    /// Read and write access
+    ReadWrite = 3,
+}
+
+impl From<MemAccessFlags> for u32 {
+    fn from(flags: MemAccessFlags) -> Self {
+        match flags {
+            MemAccessFlags::None => sys::hipMemAccessFlags_hipMemAccessFlagsProtNone,
+            MemAccessFlags::Read => sys::hipMemAccessFlags_hipMemAccessFlagsProtRead,
+            MemAccessFlags::ReadWrite => sys::hipMemAccessFlags_hipMemAccessFlagsProtReadWrite,
+        }
+    }
+}
+
+impl TryFrom<u32> for MemAccessFlags {
+    type Error = HipError;
+
+    fn try_from(value: sys::hipMemAccessFlags) -> HipResult<Self> {
+        match value {
+            sys::hipMemAccessFlags_hipMemAccessFlagsProtNone => Ok(Self::None),
+            sys::hipMemAccessFlags_hipMemAccessFlagsProtRead => Ok(Self::Read),
+            sys::hipMemAccessFlags_hipMemAccessFlagsProtReadWrite => Ok(Self::ReadWrite),
+            _ => Err(HipError::from_status(HipStatus::InvalidValue)),
+        }
+    }
+}
+
+/// Attributes of a memory pool that can be queried or modified.
+///
+/// The reuse policies are boolean attributes, all other attributes are
+/// byte counts. The high-water marks can only be reset to zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemPoolAttribute {
+    /// Allow reuse of memory freed on another stream when there is an event
+    /// dependency between the free and the allocation
+    ReuseFollowEventDependencies,
+    /// Allow reuse of memory whose free has already completed, without an
+    /// explicit dependency
+    ReuseAllowOpportunistic,
+    /// Allow the runtime to insert dependencies between streams to reuse memory
+    ReuseAllowInternalDependencies,
+    /// Amount of reserved memory in bytes to hold onto before trying to
+    /// release memory back to the OS
+    ReleaseThreshold,
+    /// Amount of backing memory currently allocated for the pool
+    ReservedMemCurrent,
+    /// High-water mark of backing memory allocated for the pool
+    ReservedMemHigh,
+    /// Amount of memory from the pool that is currently in use
+    UsedMemCurrent,
+    /// High-water mark of memory from the pool that was in use
+    UsedMemHigh,
+}
+
+impl MemPoolAttribute {
+    /// Returns true if the attribute is stored as an `int` flag rather than a `u64`
+    fn is_flag(&self) -> bool {
+        matches!(
+            self,
+            Self::ReuseFollowEventDependencies
+                | Self::ReuseAllowOpportunistic
+                | Self::ReuseAllowInternalDependencies
+        )
+    }
+}
+
+impl From<MemPoolAttribute> for u32 {
+    fn from(attr: MemPoolAttribute) -> Self {
+        match attr {
+            MemPoolAttribute::ReuseFollowEventDependencies => {
+                sys::hipMemPoolAttr_hipMemPoolReuseFollowEventDependencies
+            }
+            MemPoolAttribute::ReuseAllowOpportunistic => {
+                sys::hipMemPoolAttr_hipMemPoolReuseAllowOpportunistic
+            }
+            MemPoolAttribute::ReuseAllowInternalDependencies => {
+                sys::hipMemPoolAttr_hipMemPoolReuseAllowInternalDependencies
+            }
+            MemPoolAttribute::ReleaseThreshold => {
+                sys::hipMemPoolAttr_hipMemPoolAttrReleaseThreshold
+            }
+            MemPoolAttribute::ReservedMemCurrent => {
+                sys::hipMemPoolAttr_hipMemPoolAttrReservedMemCurrent
+            }
+            MemPoolAttribute::ReservedMemHigh => sys::hipMemPoolAttr_hipMemPoolAttrReservedMemHigh,
+            MemPoolAttribute::UsedMemCurrent => sys::hipMemPoolAttr_hipMemPoolAttrUsedMemCurrent,
+            MemPoolAttribute::UsedMemHigh => sys::hipMemPoolAttr_hipMemPoolAttrUsedMemHigh,
+        }
+    }
+}
+
+impl TryFrom<u32> for MemPoolAttribute {
+    type Error = HipError;
+
+    fn try_from(value: sys::hipMemPoolAttr) -> HipResult<Self> {
+        match value {
+            sys::hipMemPoolAttr_hipMemPoolReuseFollowEventDependencies => {
+                Ok(Self::ReuseFollowEventDependencies)
+            }
+            sys::hipMemPoolAttr_hipMemPoolReuseAllowOpportunistic => {
+                Ok(Self::ReuseAllowOpportunistic)
+            }
+            sys::hipMemPoolAttr_hipMemPoolReuseAllowInternalDependencies => {
+                Ok(Self::ReuseAllowInternalDependencies)
+            }
+            sys::hipMemPoolAttr_hipMemPoolAttrReleaseThreshold => Ok(Self::ReleaseThreshold),
+            sys::hipMemPoolAttr_hipMemPoolAttrReservedMemCurrent => Ok(Self::ReservedMemCurrent),
+            sys::hipMemPoolAttr_hipMemPoolAttrReservedMemHigh => Ok(Self::ReservedMemHigh),
+            sys::hipMemPoolAttr_hipMemPoolAttrUsedMemCurrent => Ok(Self::UsedMemCurrent),
+            sys::hipMemPoolAttr_hipMemPoolAttrUsedMemHigh => Ok(Self::UsedMemHigh),
+            _ => Err(HipError::from_status(HipStatus::InvalidValue)),
+        }
+    }
+}
+
+/// Snapshot of the memory usage of a pool, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemPoolStats {
+    /// Backing memory currently allocated for the pool
+    pub reserved_current: u64,
+    /// High-water mark of backing memory allocated for the pool
+    pub reserved_high: u64,
+    /// Memory currently handed out to allocations
+    pub used_current: u64,
+    /// High-water mark of memory handed out to allocations
+    pub used_high: u64,
+}
+
 impl Default for MemPoolProps {
     fn default() -> Self {
         Self {
@@ -109,10 +274,7 @@ impl MemPoolProps {
 
         props.allocType = self.alloc_type as u32;
         props.handleTypes = self.handle_types as u32;
-        props.location = sys::hipMemLocation {
-            type_: self.location.type_ as u32,
-            id: self.location.id,
-        };
+        props.location = self.location.to_sys();
         props.maxSize = self.max_size;
 
         #[cfg(target_os = "windows")]
@@ -134,17 +296,6 @@ impl MemPoolProps {
     }
 }
 
-/// Converts a typed pointer to a void pointer.
-///
-/// # Arguments
-/// * `ptr` - A mutable pointer to a mutable pointer of type T
-///
-/// # Returns
-/// A void pointer equivalent of the input pointer
-fn to_void_pointer<T>(ptr: *mut *mut T) -> *mut *mut std::ffi::c_void {
-    ptr as *mut *mut std::ffi::c_void
-}
-
 /// Copies data between memory locations.
 ///
 /// # Arguments
@@ -163,7 +314,7 @@ fn to_void_pointer<T>(ptr: *mut *mut T) -> *mut *mut std::ffi::c_void {
 /// * `Err(HipError)` if the operation failed
 ///
 /// TODO: Implement peer-to-peer capability
-unsafe fn memory_copy(
+pub(crate) unsafe fn memory_copy(
     dst: *mut std::ffi::c_void,
     src: *const std::ffi::c_void,
     size: usize,
@@ -173,32 +324,55 @@ unsafe fn memory_copy(
     ((), code).to_result()
 }
 
-impl<T> MemoryPointer<T> {
-    /// Private function that holds common logic for the
-    /// memory allocation functions.
-    ///
-    /// Takes the size to allocate and
-    fn allocate_with_fn<F>(size: usize, alloc_fn: F) -> HipResult<Self>
-    where
-        F: FnOnce(*mut *mut std::ffi::c_void, usize) -> u32,
-    {
-        // Handle zero size allocation according to spec
-        if size == 0 {
-            return Ok(MemoryPointer {
-                pointer: std::ptr::null_mut(),
-                size: 0,
-            });
-        }
-
-        let mut ptr = std::ptr::null_mut();
-        let code = alloc_fn(to_void_pointer(&mut ptr), size * std::mem::size_of::<T>());
+/// Enqueues a copy of `size` bytes from `src` to `dst` on `stream`.
+///
+/// # Safety
+/// * The src and dst memory regions must not overlap
+/// * Both regions must stay valid until the copy completes, which must be
+///   awaited through the stream or an event recorded on it
+/// * Host memory must be pinned for the copy to be asynchronous
+pub(crate) unsafe fn memory_copy_async(
+    dst: *mut std::ffi::c_void,
+    src: *const std::ffi::c_void,
+    size: usize,
+    kind: MemoryCopyKind,
+    stream: &Stream,
+) -> HipResult<()> {
+    let code = sys::hipMemcpyAsync(dst, src, size, kind.into(), stream.handle());
+    ((), code).to_result()
+}
 
+impl<T: DeviceCopy> MemoryPointer<T> {
+    /// Takes ownership of a device pointer holding `size` elements.
+    ///
+    /// The pointer is released with `hipFree` when the MemoryPointer is dropped.
+    #[track_caller]
+    pub(crate) fn from_raw_parts(pointer: *mut T, size: usize) -> Self {
         let pointer = Self {
-            pointer: ptr as *mut T,
+            pointer,
             size,
+            allocator: DeviceMalloc,
         };
+        #[cfg(feature = "track-allocations")]
+        pointer.track();
+        pointer
+    }
 
-        (pointer, code).to_result()
+    /// Allocates `size` elements with `allocator` and hands ownership of the
+    /// memory to `hipFree`.
+    ///
+    /// Only valid for allocators whose memory `hipFree` can release.
+    #[track_caller]
+    fn allocate_freed_by_hip<B: DeviceAllocator>(size: usize, allocator: B) -> HipResult<Self> {
+        let allocated = MemoryPointer::alloc_in(size, allocator)?;
+        // Ownership moves to the new pointer, including its tracking entry
+        let pointer = Self {
+            pointer: allocated.pointer,
+            size: allocated.size,
+            allocator: DeviceMalloc,
+        };
+        std::mem::forget(allocated);
+        Ok(pointer)
     }
 
     /// Allocates memory on a HIP device/accelerator.
@@ -216,8 +390,9 @@ impl<T> MemoryPointer<T> {
     /// * `Ok(MemoryPointer)` - Handle to allocated device memory
     /// * `Err(HipError)` - Error occurred during allocation
     /// ```
+    #[track_caller]
     pub fn alloc(size: usize) -> HipResult<Self> {
-        Self::allocate_with_fn(size, |ptr, size| unsafe { sys::hipMalloc(ptr, size) })
+        Self::alloc_in(size, DeviceMalloc)
     }
 
     /// Allocates memory on the default accelerator with specified allocation flags.
@@ -235,10 +410,9 @@ impl<T> MemoryPointer<T> {
     /// * If size is 0, returns null pointer with success status
     /// * Invalid flags will result in hipErrorInvalidValue error
     ///
+    #[track_caller]
     pub fn alloc_with_flag(size: usize, flag: DeviceMallocFlag) -> HipResult<Self> {
-        Self::allocate_with_fn(size, |ptr, size| unsafe {
-            sys::hipExtMallocWithFlags(ptr, size, flag.bits())
-        })
+        Self::allocate_freed_by_hip(size, DeviceMallocWithFlags::new(flag))
     }
 
     /// Asynchronously allocates memory from a memory pool on a specified stream.
@@ -260,10 +434,80 @@ impl<T> MemoryPointer<T> {
     ///
     /// let ptr = MemoryPointer::<f32>::alloc_async(1024, &stream).unwrap();
     /// ```
+    #[track_caller]
     pub fn alloc_async(size: usize, stream: &Stream) -> HipResult<Self> {
-        Self::allocate_with_fn(size, |ptr, size| unsafe {
-            sys::hipMallocAsync(ptr, size, stream.handle())
-        })
+        Self::allocate_freed_by_hip(size, StreamOrderedAllocator::new(stream))
+    }
+}
+
+impl<T: DeviceCopy, A: DeviceAllocator> MemoryPointer<T, A> {
+    /// Allocates `size` elements of device memory from `allocator`.
+    ///
+    /// The memory is returned to `allocator` when the MemoryPointer is dropped.
+    /// If 0 is passed for `size`, a null pointer is returned without calling
+    /// the allocator.
+    ///
+    /// # Arguments
+    /// * `size` - The number of elements to allocate
+    /// * `allocator` - The [`DeviceAllocator`] to allocate from
+    ///
+    /// # Returns
+    /// * `Ok(MemoryPointer<T, A>)` - Handle to allocated device memory
+    /// * `Err(HipError)` - If the size overflows or the allocation fails
+    ///
+    /// # Examples
+    /// ```
+    /// use hip_rs::{MemoryPointer, Stream, StreamOrderedAllocator};
+    ///
+    /// let stream = Stream::create().unwrap();
+    /// let allocator = StreamOrderedAllocator::new(&stream);
+    ///
+    /// let ptr = MemoryPointer::<f32, _>::alloc_in(1024, allocator).unwrap();
+    /// ```
+    #[track_caller]
+    pub fn alloc_in(size: usize, allocator: A) -> HipResult<Self> {
+        let bytes = size
+            .checked_mul(std::mem::size_of::<T>())
+            .ok_or(HipError::from_status(HipStatus::InvalidValue))?;
+        // Nothing to allocate for zero elements or zero-sized types, and
+        // allocators are never asked for zero bytes
+        if bytes == 0 {
+            return Ok(Self {
+                pointer: std::ptr::null_mut(),
+                size,
+                allocator,
+            });
+        }
+
+        let pointer = allocator.allocate(bytes)? as *mut T;
+
+        let pointer = Self {
+            pointer,
+            size,
+            allocator,
+        };
+        #[cfg(feature = "track-allocations")]
+        pointer.track();
+        Ok(pointer)
+    }
+
+    /// Registers the allocation with the allocation tracker.
+    #[cfg(feature = "track-allocations")]
+    #[track_caller]
+    fn track(&self) {
+        if !self.pointer.is_null() {
+            super::tracking::register(
+                super::tracking::AllocationKind::DeviceMemory,
+                self.pointer as usize,
+                self.size * std::mem::size_of::<T>(),
+                crate::get_device().ok(),
+            );
+        }
+    }
+
+    /// Returns the allocator the memory was allocated from.
+    pub fn allocator(&self) -> &A {
+        &self.allocator
     }
 
     /// Returns the raw memory pointer.
@@ -276,42 +520,30 @@ impl<T> MemoryPointer<T> {
         self.size
     }
 
-    /// Copies data from this memory pointer to another destination memory pointer.
+    /// Copies this buffer into another device buffer.
+    ///
+    /// The copy kind is inferred like in [`copy`](crate::copy).
     ///
     /// # Arguments
-    /// * `destination` - The destination memory pointer to copy data to
-    /// * `kind` - The type of memory copy operation to perform
+    /// * `destination` - The buffer to copy into, at least as large as this one
     ///
     /// # Returns
     /// * `Ok(())` if the copy was successful
-    /// * `Err(HipError)` if the operation failed
-    ///
-    /// # Safety Guarantees
-    /// - Checks that neither pointer is null
-    /// - Validates that destination has sufficient size
-    /// - Ensures proper size alignment
-    pub fn copy_to(&self, destination: &MemoryPointer<T>, kind: MemoryCopyKind) -> HipResult<()> {
-        // Check for null pointers
-        if self.pointer.is_null() || destination.pointer.is_null() {
-            return Err(HipError::from_status(HipStatus::InvalidValue));
-        }
-
-        // Check that destination has sufficient size
-        if destination.size < self.size {
-            return Err(HipError::from_status(HipStatus::InvalidValue));
-        }
-
-        // Calculate total bytes to copy
-        let bytes_to_copy = self.size * std::mem::size_of::<T>();
-
-        unsafe {
-            memory_copy(
-                destination.pointer as *mut std::ffi::c_void,
-                self.pointer as *const std::ffi::c_void,
-                bytes_to_copy,
-                kind,
-            )
-        }
+    /// * `Err(HipError)` if the destination is too small or the copy failed
+    ///
+    /// # Examples
+    /// ```
+    /// use hip_rs::MemoryPointer;
+    ///
+    /// let source = MemoryPointer::<u32>::alloc(1024).unwrap();
+    /// let mut destination = MemoryPointer::<u32>::alloc(1024).unwrap();
+    /// source.copy_to(&mut destination).unwrap();
+    /// ```
+    pub fn copy_to<B: DeviceAllocator>(
+        &self,
+        destination: &mut MemoryPointer<T, B>,
+    ) -> HipResult<()> {
+        copy(destination, self)
     }
 
     /// Fills the allocated memory with a specified value.
@@ -348,41 +580,82 @@ impl<T> MemoryPointer<T> {
 }
 
 // The Drop trait does not return anything by design
-impl<T> Drop for MemoryPointer<T> {
+impl<T, A: DeviceAllocator> Drop for MemoryPointer<T, A> {
     fn drop(&mut self) {
-        unsafe {
-            let code = sys::hipFree(self.pointer as *mut std::ffi::c_void);
-            if code != 0 {
-                let error = HipError::new(code);
-                log::error!("MemoryPointer failed to free memory: {:?}", error);
-            }
+        if self.pointer.is_null() {
+            return;
+        }
+
+        #[cfg(feature = "track-allocations")]
+        super::tracking::unregister(
+            super::tracking::AllocationKind::DeviceMemory,
+            self.pointer as usize,
+        );
+
+        let bytes = self.size * std::mem::size_of::<T>();
+        let result = unsafe {
+            self.allocator
+                .deallocate(self.pointer as *mut std::ffi::c_void, bytes)
+        };
+        if let Err(error) = result {
+            log::error!("MemoryPointer failed to free memory: {:?}", error);
         }
     }
 }
 
 /// Represents a HIP memory pool handle
+///
+/// A pool is either owned or borrowed. Owned pools are created with
+/// [`MemPool::create`] and destroyed when dropped. Borrowed pools, such as a
+/// device's default pool or its current pool, belong to the HIP runtime and
+/// are never destroyed by this wrapper.
 #[derive(Debug)]
 pub struct MemPool {
     handle: sys::hipMemPool_t,
     props: MemPoolProps,
+    owned: bool,
 }
 
 impl MemPool {
-    /// Creates a new MemPool from a raw handle
-    pub(crate) fn from_raw(handle: sys::hipMemPool_t) -> Self {
+    /// Creates an owned MemPool from a raw handle, destroying it on drop.
+    #[track_caller]
+    pub(crate) fn from_owned(handle: sys::hipMemPool_t, props: MemPoolProps) -> Self {
+        #[cfg(feature = "track-allocations")]
+        if !handle.is_null() {
+            super::tracking::register(
+                super::tracking::AllocationKind::MemPool,
+                handle as usize,
+                props.max_size,
+                Some(Device::new(props.location.id)),
+            );
+        }
+
         Self {
             handle,
-            props: MemPoolProps::default(),
+            props,
+            owned: true,
         }
     }
 
+    /// Creates a borrowed MemPool from a raw handle owned by the runtime.
+    ///
+    /// The handle is not destroyed when the returned MemPool is dropped.
+    pub(crate) fn from_borrowed(handle: sys::hipMemPool_t, device: Device) -> Self {
+        Self {
+            handle,
+            props: MemPoolProps::default().with_location(MemLocationType::Device, device.id()),
+            owned: false,
+        }
+    }
+
+    #[track_caller]
     pub fn create(props: MemPoolProps) -> HipResult<Self> {
         let mut handle = std::ptr::null_mut();
         let sys_props = props.to_sys_props();
 
         unsafe {
             let code = sys::hipMemPoolCreate(&mut handle, &sys_props);
-            (Self { handle, props }, code).to_result()
+            (Self::from_owned(handle, props), code).to_result()
         }
     }
 
@@ -391,6 +664,14 @@ impl MemPool {
         self.handle.is_null()
     }
 
+    /// Returns true if this MemPool owns the pool and destroys it on drop.
+    ///
+    /// Pools returned by [`Device::get_default_mem_pool`] and
+    /// [`Device::mem_pool`] are borrowed from the runtime.
+    pub fn is_owned(&self) -> bool {
+        self.owned
+    }
+
     /// Gets the raw handle to the memory pool
     pub fn handle(&self) -> sys::hipMemPool_t {
         self.handle
@@ -400,11 +681,237 @@ impl MemPool {
     pub fn props(&self) -> &MemPoolProps {
         &self.props
     }
+
+    /// Reads a pool attribute.
+    ///
+    /// Boolean attributes are returned as `0` or `1`.
+    ///
+    /// # Arguments
+    /// * `attr` - The [`MemPoolAttribute`] to query
+    ///
+    /// # Returns
+    /// * `Ok(u64)` - The attribute value
+    /// * `Err(HipError)` - If the pool or attribute is invalid
+    pub fn get_attribute(&self, attr: MemPoolAttribute) -> HipResult<u64> {
+        unsafe {
+            if attr.is_flag() {
+                let mut value: i32 = 0;
+                let code = sys::hipMemPoolGetAttribute(
+                    self.handle,
+                    attr.into(),
+                    &mut value as *mut i32 as *mut std::ffi::c_void,
+                );
+                (value as u64, code).to_result()
+            } else {
+                let mut value: u64 = 0;
+                let code = sys::hipMemPoolGetAttribute(
+                    self.handle,
+                    attr.into(),
+                    &mut value as *mut u64 as *mut std::ffi::c_void,
+                );
+                (value, code).to_result()
+            }
+        }
+    }
+
+    /// Modifies a pool attribute.
+    ///
+    /// Boolean attributes treat any non-zero value as `true`.
+    /// [`MemPoolAttribute::ReservedMemHigh`] and [`MemPoolAttribute::UsedMemHigh`]
+    /// can only be reset to `0`, and the current usage attributes are read-only.
+    ///
+    /// # Arguments
+    /// * `attr` - The [`MemPoolAttribute`] to modify
+    /// * `value` - The new value
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the attribute was set
+    /// * `Err(HipError)` - If the attribute is read-only or the value is invalid
+    pub fn set_attribute(&self, attr: MemPoolAttribute, value: u64) -> HipResult<()> {
+        unsafe {
+            let code = if attr.is_flag() {
+                let mut value: i32 = (value != 0) as i32;
+                sys::hipMemPoolSetAttribute(
+                    self.handle,
+                    attr.into(),
+                    &mut value as *mut i32 as *mut std::ffi::c_void,
+                )
+            } else {
+                let mut value = value;
+                sys::hipMemPoolSetAttribute(
+                    self.handle,
+                    attr.into(),
+                    &mut value as *mut u64 as *mut std::ffi::c_void,
+                )
+            };
+            ((), code).to_result()
+        }
+    }
+
+    /// Gets the number of reserved bytes the pool holds onto before releasing
+    /// memory back to the OS on synchronization.
+    pub fn release_threshold(&self) -> HipResult<u64> {
+        self.get_attribute(MemPoolAttribute::ReleaseThreshold)
+    }
+
+    /// Sets the number of reserved bytes the pool holds onto before releasing
+    /// memory back to the OS on synchronization.
+    ///
+    /// The default threshold is `0`, which makes the pool return all unused
+    /// memory on every synchronization. Use `u64::MAX` to never release memory.
+    pub fn set_release_threshold(&self, bytes: u64) -> HipResult<()> {
+        self.set_attribute(MemPoolAttribute::ReleaseThreshold, bytes)
+    }
+
+    /// Returns true if the pool may reuse memory freed on another stream
+    /// when there is an event dependency between the free and the allocation.
+    pub fn reuse_follow_event_dependencies(&self) -> HipResult<bool> {
+        self.get_attribute(MemPoolAttribute::ReuseFollowEventDependencies)
+            .map(|value| value != 0)
+    }
+
+    /// Enables or disables reuse of memory across event dependencies.
+    pub fn set_reuse_follow_event_dependencies(&self, enable: bool) -> HipResult<()> {
+        self.set_attribute(
+            MemPoolAttribute::ReuseFollowEventDependencies,
+            enable as u64,
+        )
+    }
+
+    /// Returns true if the pool may reuse memory whose free has already
+    /// completed, without an explicit dependency.
+    pub fn reuse_allow_opportunistic(&self) -> HipResult<bool> {
+        self.get_attribute(MemPoolAttribute::ReuseAllowOpportunistic)
+            .map(|value| value != 0)
+    }
+
+    /// Enables or disables opportunistic reuse of freed memory.
+    pub fn set_reuse_allow_opportunistic(&self, enable: bool) -> HipResult<()> {
+        self.set_attribute(MemPoolAttribute::ReuseAllowOpportunistic, enable as u64)
+    }
+
+    /// Returns true if the runtime may insert dependencies between streams
+    /// to reuse memory.
+    pub fn reuse_allow_internal_dependencies(&self) -> HipResult<bool> {
+        self.get_attribute(MemPoolAttribute::ReuseAllowInternalDependencies)
+            .map(|value| value != 0)
+    }
+
+    /// Enables or disables reuse through runtime inserted dependencies.
+    pub fn set_reuse_allow_internal_dependencies(&self, enable: bool) -> HipResult<()> {
+        self.set_attribute(
+            MemPoolAttribute::ReuseAllowInternalDependencies,
+            enable as u64,
+        )
+    }
+
+    /// Reads the current and high-water memory usage of the pool.
+    ///
+    /// # Returns
+    /// * `Ok(MemPoolStats)` - The usage counters in bytes
+    /// * `Err(HipError)` - If any of the attributes could not be read
+    pub fn stats(&self) -> HipResult<MemPoolStats> {
+        Ok(MemPoolStats {
+            reserved_current: self.get_attribute(MemPoolAttribute::ReservedMemCurrent)?,
+            reserved_high: self.get_attribute(MemPoolAttribute::ReservedMemHigh)?,
+            used_current: self.get_attribute(MemPoolAttribute::UsedMemCurrent)?,
+            used_high: self.get_attribute(MemPoolAttribute::UsedMemHigh)?,
+        })
+    }
+
+    /// Resets the reserved and used high-water marks to the current usage.
+    pub fn reset_high_watermarks(&self) -> HipResult<()> {
+        self.set_attribute(MemPoolAttribute::ReservedMemHigh, 0)?;
+        self.set_attribute(MemPoolAttribute::UsedMemHigh, 0)
+    }
+
+    /// Releases memory back to the OS until the pool holds at most `bytes`
+    /// of unused reserved memory.
+    ///
+    /// Memory backing outstanding allocations is never released.
+    ///
+    /// # Arguments
+    /// * `bytes` - The minimum number of reserved bytes to keep
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the pool was trimmed
+    /// * `Err(HipError)` - If the pool handle is invalid
+    pub fn trim_to(&self, bytes: usize) -> HipResult<()> {
+        unsafe {
+            let code = sys::hipMemPoolTrimTo(self.handle, bytes);
+            ((), code).to_result()
+        }
+    }
+
+    /// Controls which devices can access memory allocated from the pool.
+    ///
+    /// The device the pool resides on always has read/write access and
+    /// cannot be given fewer permissions.
+    ///
+    /// # Arguments
+    /// * `access` - Pairs of device and the access it should have
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the access permissions were updated
+    /// * `Err(HipError)` - If a device is invalid or peer access is not supported
+    pub fn set_access(&self, access: &[(Device, MemAccessFlags)]) -> HipResult<()> {
+        let descs: Vec<sys::hipMemAccessDesc> = access
+            .iter()
+            .map(|(device, flags)| sys::hipMemAccessDesc {
+                location: MemLocation::device(*device).to_sys(),
+                flags: (*flags).into(),
+            })
+            .collect();
+
+        unsafe {
+            let code = sys::hipMemPoolSetAccess(self.handle, descs.as_ptr(), descs.len());
+            ((), code).to_result()
+        }
+    }
+
+    /// Gets the access `device` has to memory allocated from the pool.
+    pub fn get_access(&self, device: Device) -> HipResult<MemAccessFlags> {
+        let mut flags = sys::hipMemAccessFlags_hipMemAccessFlagsProtNone;
+        let mut location = MemLocation::device(device).to_sys();
+        let code = unsafe { sys::hipMemPoolGetAccess(&mut flags, self.handle, &mut location) };
+        ((), code).to_result()?;
+        MemAccessFlags::try_from(flags)
+    }
+
+    /// Allocates `len` elements from this pool, ordered on `stream`.
+    ///
+    /// The memory can be used by work submitted to `stream` after this call.
+    /// The pool is not destroyed while allocations from it are outstanding,
+    /// so the returned pointer may outlive the `MemPool`.
+    ///
+    /// # Arguments
+    /// * `len` - The number of elements to allocate
+    /// * `stream` - The stream on which to perform the allocation
+    ///
+    /// # Returns
+    /// * `Ok(MemoryPointer<T>)` - Successfully allocated memory pointer
+    /// * `Err(HipError)` - If allocation fails
+    #[track_caller]
+    pub fn alloc_async<T: DeviceCopy>(
+        &self,
+        len: usize,
+        stream: &Stream,
+    ) -> HipResult<MemoryPointer<T>> {
+        MemoryPointer::allocate_freed_by_hip(len, PoolAllocator::new(self, stream))
+    }
 }
 
 impl Drop for MemPool {
     fn drop(&mut self) {
-        if !self.handle.is_null() {
+        // Borrowed pools belong to the runtime and must not be destroyed
+        if self.owned && !self.handle.is_null() {
+            #[cfg(feature = "track-allocations")]
+            super::tracking::unregister(
+                super::tracking::AllocationKind::MemPool,
+                self.handle as usize,
+            );
+
+            self.uninstall();
             unsafe {
                 let code = sys::hipMemPoolDestroy(self.handle);
                 if code != 0 {
@@ -415,6 +922,31 @@ impl Drop for MemPool {
     }
 }
 
+impl MemPool {
+    /// Restores the device's default pool if this pool is its current one,
+    /// so stream-ordered allocations never use a destroyed pool.
+    fn uninstall(&self) {
+        if !matches!(self.props.location.type_, MemLocationType::Device) {
+            return;
+        }
+        let device = self.props.location.id;
+        let mut current = std::ptr::null_mut();
+        unsafe {
+            if sys::hipDeviceGetMemPool(&mut current, device) != 0 || current != self.handle {
+                return;
+            }
+            let mut default = std::ptr::null_mut();
+            let mut code = sys::hipDeviceGetDefaultMemPool(&mut default, device);
+            if code == 0 {
+                code = sys::hipDeviceSetMemPool(device, default);
+            }
+            if code != 0 {
+                log::error!("Failed to restore the default memory pool: {}", code);
+            }
+        }
+    }
+}
+
 // Implement Send and Sync since MemPool can be safely shared between threads
 unsafe impl Send for MemPool {}
 unsafe impl Sync for MemPool {}
@@ -520,6 +1052,77 @@ mod tests {
         assert!(!pool.is_null());
     }
 
+    #[test]
+    fn test_mempool_attribute_roundtrip() {
+        let attrs = [
+            MemPoolAttribute::ReuseFollowEventDependencies,
+            MemPoolAttribute::ReuseAllowOpportunistic,
+            MemPoolAttribute::ReuseAllowInternalDependencies,
+            MemPoolAttribute::ReleaseThreshold,
+            MemPoolAttribute::ReservedMemCurrent,
+            MemPoolAttribute::ReservedMemHigh,
+            MemPoolAttribute::UsedMemCurrent,
+            MemPoolAttribute::UsedMemHigh,
+        ];
+        for attr in attrs {
+            let raw: u32 = attr.into();
+            assert_eq!(MemPoolAttribute::try_from(raw).unwrap(), attr);
+        }
+        assert!(MemPoolAttribute::try_from(999).is_err());
+    }
+
+    #[test]
+    fn test_mempool_release_threshold() {
+        let pool = MemPool::create(MemPoolProps::new()).unwrap();
+
+        pool.set_release_threshold(u64::MAX).unwrap();
+        assert_eq!(pool.release_threshold().unwrap(), u64::MAX);
+
+        pool.set_release_threshold(0).unwrap();
+        assert_eq!(pool.release_threshold().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_mempool_reuse_flags() {
+        let pool = MemPool::create(MemPoolProps::new()).unwrap();
+
+        pool.set_reuse_allow_opportunistic(false).unwrap();
+        assert!(!pool.reuse_allow_opportunistic().unwrap());
+
+        pool.set_reuse_follow_event_dependencies(true).unwrap();
+        assert!(pool.reuse_follow_event_dependencies().unwrap());
+    }
+
+    #[test]
+    fn test_mempool_alloc_async_and_stats() {
+        let pool = MemPool::create(MemPoolProps::new()).unwrap();
+        pool.set_release_threshold(u64::MAX).unwrap();
+        let stream = Stream::create().unwrap();
+
+        let len = 1024;
+        let ptr = pool.alloc_async::<f32>(len, &stream).unwrap();
+        assert!(!ptr.as_pointer().is_null());
+        assert_eq!(ptr.size(), len);
+
+        let stats = pool.stats().unwrap();
+        assert!(stats.used_current >= (len * std::mem::size_of::<f32>()) as u64);
+        assert!(stats.reserved_current >= stats.used_current);
+
+        drop(ptr);
+        pool.trim_to(0).unwrap();
+        pool.reset_high_watermarks().unwrap();
+    }
+
+    #[test]
+    fn test_mempool_access() {
+        let pool = MemPool::create(MemPoolProps::new()).unwrap();
+        let device = Device::new(0);
+
+        pool.set_access(&[(device, MemAccessFlags::ReadWrite)])
+            .unwrap();
+        assert_eq!(pool.get_access(device).unwrap(), MemAccessFlags::ReadWrite);
+    }
+
     // #[test]
     // fn test_mempool_drop() {
     //     let props = MemPoolProps::new();
@@ -633,28 +1236,17 @@ mod tests {
 
     #[test]
     fn test_copy_to() {
-        // Create source memory pointer
         let src_size = 1024;
         let src = MemoryPointer::<u32>::alloc(src_size).unwrap();
+        let mut dst = MemoryPointer::<u32>::alloc(src_size).unwrap();
+        assert!(src.copy_to(&mut dst).is_ok());
 
-        // Create destination memory pointer
-        let dst = MemoryPointer::<u32>::alloc(src_size).unwrap();
-
-        // Test device to device copy
-        let result = src.copy_to(&dst, MemoryCopyKind::DeviceToDevice);
-        assert!(result.is_ok());
-
-        // Test with null pointer
-        let null_ptr = MemoryPointer::<u32> {
-            pointer: std::ptr::null_mut(),
-            size: 0,
-        };
-        let result = src.copy_to(&null_ptr, MemoryCopyKind::DeviceToDevice);
-        assert!(result.is_err());
+        // Test with an empty destination
+        let mut empty = MemoryPointer::<u32>::alloc(0).unwrap();
+        assert!(src.copy_to(&mut empty).is_err());
 
         // Test with insufficient destination size
-        let small_dst = MemoryPointer::<u32>::alloc(src_size / 2).unwrap();
-        let result = src.copy_to(&small_dst, MemoryCopyKind::DeviceToDevice);
-        assert!(result.is_err());
+        let mut small_dst = MemoryPointer::<u32>::alloc(src_size / 2).unwrap();
+        assert!(src.copy_to(&mut small_dst).is_err());
     }
 }
@@ -1,20 +1,68 @@
+mod allocator;
+mod caching;
+mod copy;
 mod device;
+mod device_copy;
+mod device_io;
 mod device_types;
+mod event;
 mod flags;
 mod hip_call;
 mod init;
+mod ipc;
+mod launch;
+mod managed;
 mod memory;
+mod module;
+#[cfg(feature = "npy")]
+mod npy;
+#[cfg(feature = "npy")]
+mod npz;
+mod occupancy;
+mod pinned;
+mod pointer;
 mod result;
+#[cfg(feature = "safetensors")]
+mod safetensors;
+mod slice;
 mod stream;
+#[cfg(feature = "track-allocations")]
+pub(crate) mod tracking;
+mod transfer;
+mod vmm;
 
 // use crate::sys::*;
 // Re-export core functionality
+pub use allocator::*;
+pub use caching::*;
+pub use copy::*;
 pub use device::*;
+pub use device_copy::*;
+pub use device_io::*;
 pub use device_types::*;
+pub use event::*;
 pub use flags::*;
 #[allow(unused_imports)]
 pub use hip_call::*;
 pub use init::*;
+pub use ipc::*;
+pub use launch::*;
+pub use managed::*;
 pub use memory::*;
+pub use module::*;
+#[cfg(feature = "npy")]
+pub use npy::*;
+#[cfg(feature = "npy")]
+pub use npz::*;
+pub use occupancy::*;
+pub use pinned::*;
+pub use pointer::*;
 pub use result::*;
+#[cfg(feature = "safetensors")]
+pub use safetensors::*;
+pub use slice::*;
 pub use stream::*;
+#[cfg(feature = "track-allocations")]
+pub use tracking::*;
+pub use transfer::*;
+pub use vmm::*;
@@ -0,0 +1,697 @@
+use super::device::{get_device, Device};
+use super::launch::LaunchLimits;
+use super::result::{HipError, HipResult, HipStatus};
+use crate::code_object::{
+    check_image, select_code_object, CodeObject, CodeObjectError, KernelMetadata, OffloadBundle,
+    TargetId,
+};
+use crate::result::{ResultExt, StatusCode};
+use crate::sys;
+use std::ffi::{c_void, CString};
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// A loaded code object holding device kernels.
+///
+/// The module is unloaded when dropped. Use [`Module::unload`] to observe
+/// unload errors.
+///
+/// In debug builds the code object's metadata is read when loading, so
+/// launches can check their arguments against the kernel's parameters.
+///
+/// # Examples
+/// ```no_run
+/// use hip_rs::Module;
+///
+/// let module = Module::load("kernels.hsaco").unwrap();
+/// let function = module.function("vector_add").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Module {
+    handle: sys::hipModule_t,
+    metadata: Option<CodeObject>,
+    device: Device,
+}
+
+impl Module {
+    /// Loads a code object from a file.
+    ///
+    /// # Arguments
+    /// * `path` - Path to a `.hsaco` code object or offload bundle
+    ///
+    /// # Returns
+    /// * `Ok(Module)` - The loaded module
+    /// * `Err(HipError)` - If the path contains a NUL byte (or is not Unicode outside unix), the file is missing or it holds no code for the current device
+    pub fn load<P: AsRef<Path>>(path: P) -> HipResult<Self> {
+        let c_path = path_to_cstring(path.as_ref())
+            .ok_or_else(|| HipError::from_status(HipStatus::InvalidValue))?;
+        // Modules are loaded into the current device's context
+        let device = get_device()?;
+        let mut handle: sys::hipModule_t = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipModuleLoad(&mut handle, c_path.as_ptr());
+            let metadata = match code {
+                0 if cfg!(debug_assertions) => std::fs::read(path)
+                    .ok()
+                    .and_then(|image| debug_metadata(&image)),
+                _ => None,
+            };
+            (
+                Self {
+                    handle,
+                    metadata,
+                    device,
+                },
+                code,
+            )
+                .to_result()
+        }
+    }
+
+    /// Loads a code object from memory, e.g. one embedded with `include_bytes!`.
+    ///
+    /// The runtime is only given a pointer, so code objects and bundle
+    /// headers are checked to lie within the slice first. Compressed bundles
+    /// and non-AMDGPU entries such as SPIR-V are passed through unchecked.
+    ///
+    /// # Arguments
+    /// * `image` - A `.hsaco` code object or offload bundle
+    ///
+    /// # Returns
+    /// * `Ok(Module)` - The loaded module
+    /// * `Err(HipError)` - `InvalidValue` if the image is malformed or truncated, or if it holds no code for the current device
+    pub fn load_data(image: &[u8]) -> HipResult<Self> {
+        check_loadable(image)?;
+        // Modules are loaded into the current device's context
+        let device = get_device()?;
+        let mut handle: sys::hipModule_t = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipModuleLoadData(&mut handle, image.as_ptr() as *const c_void);
+            let metadata = (code == 0).then(|| debug_metadata(image)).flatten();
+            (
+                Self {
+                    handle,
+                    metadata,
+                    device,
+                },
+                code,
+            )
+                .to_result()
+        }
+    }
+
+    /// Loads a code object from memory, passing JIT options to the loader.
+    ///
+    /// The image is checked like in [`Module::load_data`].
+    ///
+    /// # Arguments
+    /// * `image` - A `.hsaco` code object or offload bundle
+    /// * `options` - [`JitOptions`] for the loader
+    ///
+    /// # Returns
+    /// * `Ok(Module)` - The loaded module
+    /// * `Err(HipError)` - `InvalidValue` if the image is malformed or truncated, or if it holds no code for the current device
+    pub fn load_data_ex(image: &[u8], options: &JitOptions) -> HipResult<Self> {
+        check_loadable(image)?;
+        let (mut keys, mut values) = options.to_raw();
+        // Modules are loaded into the current device's context
+        let device = get_device()?;
+        let mut handle: sys::hipModule_t = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipModuleLoadDataEx(
+                &mut handle,
+                image.as_ptr() as *const c_void,
+                keys.len() as u32,
+                keys.as_mut_ptr(),
+                values.as_mut_ptr(),
+            );
+            let metadata = (code == 0).then(|| debug_metadata(image)).flatten();
+            (
+                Self {
+                    handle,
+                    metadata,
+                    device,
+                },
+                code,
+            )
+                .to_result()
+        }
+    }
+
+    /// Loads the code object in `image` that best matches the current device.
+    ///
+    /// The runtime picks from offload bundles itself, but only reports that
+    /// no code was found. This selects the entry up front following the
+    /// target ID rules, so a mismatch names the device and the targets the
+    /// image was built for.
+    ///
+    /// # Arguments
+    /// * `image` - An offload bundle or `.hsaco` code object
+    ///
+    /// # Returns
+    /// * `Ok(Module)` - The loaded module
+    /// * `Err(ModuleLoadError)` - If the image has no code for the device or loading fails
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hip_rs::Module;
+    ///
+    /// let image = std::fs::read("kernels.hipfb").unwrap();
+    /// match Module::load_for_device(&image) {
+    ///     Ok(module) => println!("loaded {:?}", module),
+    ///     // e.g. "no code object for gfx1030, the image has code for gfx90a, gfx942"
+    ///     Err(error) => eprintln!("{}", error),
+    /// }
+    /// ```
+    pub fn load_for_device(image: &[u8]) -> Result<Self, ModuleLoadError> {
+        let target = TargetId::parse(&get_device()?.gcn_arch_name()?)?;
+        Self::load_for_target(image, &target)
+    }
+
+    /// Loads the code object in `image` that best matches `target`.
+    ///
+    /// # Arguments
+    /// * `image` - An offload bundle or `.hsaco` code object
+    /// * `target` - The device target ID, e.g. `gfx90a:sramecc+:xnack-`
+    ///
+    /// # Returns
+    /// * `Ok(Module)` - The loaded module
+    /// * `Err(ModuleLoadError)` - If the image has no code for the target or loading fails
+    pub fn load_for_target(image: &[u8], target: &TargetId) -> Result<Self, ModuleLoadError> {
+        let code = select_code_object(image, target)?;
+        Ok(Self::load_data(code)?)
+    }
+
+    /// Returns the raw module handle.
+    pub fn handle(&self) -> sys::hipModule_t {
+        self.handle
+    }
+
+    /// Returns the device the module was loaded on.
+    pub fn device(&self) -> Device {
+        self.device
+    }
+
+    /// Returns the metadata of the loaded code object.
+    ///
+    /// Only read in debug builds, and `None` if the image could not be parsed.
+    pub fn metadata(&self) -> Option<&CodeObject> {
+        self.metadata.as_ref()
+    }
+
+    /// Looks up a kernel by its symbol name.
+    ///
+    /// Kernels compiled as C++ have mangled names unless declared `extern "C"`.
+    ///
+    /// # Arguments
+    /// * `name` - The kernel symbol
+    ///
+    /// # Returns
+    /// * `Ok(Function)` - The kernel, borrowing the module
+    /// * `Err(HipError)` - If no kernel has that name
+    pub fn function(&self, name: &str) -> HipResult<Function<'_>> {
+        let symbol =
+            CString::new(name).map_err(|_| HipError::from_status(HipStatus::InvalidValue))?;
+        let mut handle: sys::hipFunction_t = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipModuleGetFunction(&mut handle, self.handle, symbol.as_ptr());
+            let function = Function {
+                handle,
+                name: name.to_string(),
+                device: self.device,
+                metadata: self
+                    .metadata
+                    .as_ref()
+                    .and_then(|code_object| code_object.kernel(name))
+                    .cloned(),
+                limits: OnceLock::new(),
+                _module: PhantomData,
+            };
+            (function, code).to_result()
+        }
+    }
+
+    /// Unloads the module, returning any error instead of logging it.
+    pub fn unload(mut self) -> HipResult<()> {
+        let handle = std::mem::replace(&mut self.handle, std::ptr::null_mut());
+        unsafe {
+            let code = sys::hipModuleUnload(handle);
+            ((), code).to_result()
+        }
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        if !self.handle.is_null() {
+            unsafe {
+                let code = sys::hipModuleUnload(self.handle);
+                if code != 0 {
+                    log::error!("Failed to unload HIP module: {}", code);
+                }
+            }
+        }
+    }
+}
+
+/// Rejects images the runtime would read past the end of.
+fn check_loadable(image: &[u8]) -> HipResult<()> {
+    check_image(image).map_err(|_| HipError::from_status(HipStatus::InvalidValue))
+}
+
+/// Converts a path for the runtime, which takes a C string.
+#[cfg(unix)]
+fn path_to_cstring(path: &Path) -> Option<CString> {
+    use std::os::unix::ffi::OsStrExt;
+    CString::new(path.as_os_str().as_bytes()).ok()
+}
+
+/// Converts a path for the runtime. Only Unicode paths can be passed on
+/// platforms without byte paths.
+#[cfg(not(unix))]
+fn path_to_cstring(path: &Path) -> Option<CString> {
+    path.to_str().and_then(|path| CString::new(path).ok())
+}
+
+/// Reads the metadata of the code object the runtime will load from `image`,
+/// in debug builds only.
+fn debug_metadata(image: &[u8]) -> Option<CodeObject> {
+    if !cfg!(debug_assertions) {
+        return None;
+    }
+    let code = if OffloadBundle::is_bundle(image) {
+        let target = TargetId::parse(&get_device().ok()?.gcn_arch_name().ok()?).ok()?;
+        select_code_object(image, &target).ok()?
+    } else {
+        image
+    };
+    CodeObject::parse(code).ok()
+}
+
+// Modules may be used and unloaded from any thread
+unsafe impl Send for Module {}
+unsafe impl Sync for Module {}
+
+/// Errors from [`Module::load_for_device`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleLoadError {
+    /// Querying the device or loading the module failed
+    Hip(HipError),
+    /// The image is malformed or has no code for the device
+    CodeObject(CodeObjectError),
+}
+
+impl fmt::Display for ModuleLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModuleLoadError::Hip(error) => StatusCode::fmt(error, f),
+            ModuleLoadError::CodeObject(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ModuleLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ModuleLoadError::CodeObject(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<HipError> for ModuleLoadError {
+    fn from(error: HipError) -> Self {
+        ModuleLoadError::Hip(error)
+    }
+}
+
+impl From<CodeObjectError> for ModuleLoadError {
+    fn from(error: CodeObjectError) -> Self {
+        ModuleLoadError::CodeObject(error)
+    }
+}
+
+/// A kernel in a loaded [`Module`].
+///
+/// The function borrows its module, so it cannot outlive the loaded code.
+#[derive(Debug, Clone)]
+pub struct Function<'a> {
+    handle: sys::hipFunction_t,
+    name: String,
+    device: Device,
+    metadata: Option<KernelMetadata>,
+    /// Block and grid limits on the module's device, queried on first launch
+    pub(crate) limits: OnceLock<LaunchLimits>,
+    _module: PhantomData<&'a Module>,
+}
+
+impl Function<'_> {
+    /// Returns the raw function handle.
+    pub fn handle(&self) -> sys::hipFunction_t {
+        self.handle
+    }
+
+    /// Returns the device the function's module was loaded on.
+    pub fn device(&self) -> Device {
+        self.device
+    }
+
+    /// Returns the symbol name the function was looked up by.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the kernel's metadata, if its module has any.
+    ///
+    /// See [`Module::metadata`].
+    pub fn metadata(&self) -> Option<&KernelMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Queries a property of the compiled kernel.
+    ///
+    /// # Arguments
+    /// * `attr` - The [`FunctionAttribute`] to query
+    ///
+    /// # Returns
+    /// * `Ok(i32)` - The attribute value
+    /// * `Err(HipError)` - If the attribute is unsupported
+    pub fn attribute(&self, attr: FunctionAttribute) -> HipResult<i32> {
+        let mut value = 0;
+        unsafe {
+            let code = sys::hipFuncGetAttribute(&mut value, attr.into(), self.handle);
+            (value, code).to_result()
+        }
+    }
+}
+
+/// Kernel properties queried with [`Function::attribute`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionAttribute {
+    /// Largest block the kernel can be launched with, which may be lower than the device limit
+    MaxThreadsPerBlock,
+    /// Statically allocated shared memory in bytes
+    SharedSizeBytes,
+    ConstSizeBytes,
+    /// Private memory per thread in bytes
+    LocalSizeBytes,
+    NumRegs,
+    /// Largest dynamic shared memory allocation the kernel can be launched with
+    MaxDynamicSharedSizeBytes,
+}
+
+impl From<FunctionAttribute> for u32 {
+    fn from(attr: FunctionAttribute) -> Self {
+        match attr {
+            FunctionAttribute::MaxThreadsPerBlock => {
+                sys::hipFunction_attribute_HIP_FUNC_ATTRIBUTE_MAX_THREADS_PER_BLOCK
+            }
+            FunctionAttribute::SharedSizeBytes => {
+                sys::hipFunction_attribute_HIP_FUNC_ATTRIBUTE_SHARED_SIZE_BYTES
+            }
+            FunctionAttribute::ConstSizeBytes => {
+                sys::hipFunction_attribute_HIP_FUNC_ATTRIBUTE_CONST_SIZE_BYTES
+            }
+            FunctionAttribute::LocalSizeBytes => {
+                sys::hipFunction_attribute_HIP_FUNC_ATTRIBUTE_LOCAL_SIZE_BYTES
+            }
+            FunctionAttribute::NumRegs => sys::hipFunction_attribute_HIP_FUNC_ATTRIBUTE_NUM_REGS,
+            FunctionAttribute::MaxDynamicSharedSizeBytes => {
+                sys::hipFunction_attribute_HIP_FUNC_ATTRIBUTE_MAX_DYNAMIC_SHARED_SIZE_BYTES
+            }
+        }
+    }
+}
+
+unsafe impl Send for Function<'_> {}
+unsafe impl Sync for Function<'_> {}
+
+/// Options passed to [`Module::load_data_ex`].
+///
+/// These mirror the CUDA JIT options. The ROCm loader accepts them but may
+/// ignore options that do not apply to precompiled AMDGPU code objects.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JitOptions {
+    max_registers: Option<u32>,
+    threads_per_block: Option<u32>,
+    optimization_level: Option<u32>,
+    generate_debug_info: bool,
+    generate_line_info: bool,
+    log_verbose: bool,
+}
+
+impl JitOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the number of registers a thread may use.
+    pub fn with_max_registers(mut self, registers: u32) -> Self {
+        self.max_registers = Some(registers);
+        self
+    }
+
+    /// Sets the number of threads per block to optimize for.
+    pub fn with_threads_per_block(mut self, threads: u32) -> Self {
+        self.threads_per_block = Some(threads);
+        self
+    }
+
+    /// Sets the optimization level, from 0 to 4.
+    pub fn with_optimization_level(mut self, level: u32) -> Self {
+        self.optimization_level = Some(level);
+        self
+    }
+
+    pub fn with_debug_info(mut self, enable: bool) -> Self {
+        self.generate_debug_info = enable;
+        self
+    }
+
+    pub fn with_line_info(mut self, enable: bool) -> Self {
+        self.generate_line_info = enable;
+        self
+    }
+
+    pub fn with_verbose_log(mut self, enable: bool) -> Self {
+        self.log_verbose = enable;
+        self
+    }
+
+    /// Returns the option keys and values, with each value stored in the
+    /// pointer itself as the JIT API expects for scalar options.
+    fn to_raw(&self) -> (Vec<sys::hipJitOption>, Vec<*mut c_void>) {
+        let options = [
+            (
+                sys::hipJitOption_hipJitOptionMaxRegisters,
+                self.max_registers,
+            ),
+            (
+                sys::hipJitOption_hipJitOptionThreadsPerBlock,
+                self.threads_per_block,
+            ),
+            (
+                sys::hipJitOption_hipJitOptionOptimizationLevel,
+                self.optimization_level,
+            ),
+            (
+                sys::hipJitOption_hipJitOptionGenerateDebugInfo,
+                self.generate_debug_info.then_some(1),
+            ),
+            (
+                sys::hipJitOption_hipJitOptionGenerateLineInfo,
+                self.generate_line_info.then_some(1),
+            ),
+            (
+                sys::hipJitOption_hipJitOptionLogVerbose,
+                self.log_verbose.then_some(1),
+            ),
+        ];
+        options
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, value? as usize as *mut c_void)))
+            .unzip()
+    }
+}
+
+/// Embeds an offload bundle and generates a struct that loads it.
+///
+/// The bundle is `$OUT_DIR/<name>.hipfb` as written by `hip_rs_build`, or
+/// any byte slice expression after `=`. The struct gets `load()`, which
+/// picks the code object for the current device with
+/// [`Module::load_for_device`], `module()`, and a method returning the
+/// [`Function`] for each listed kernel. Kernels must be declared `extern "C"`.
+///
+/// # Examples
+/// ```ignore
+/// // kernels.hipfb written by hip_rs_build in build.rs
+/// hip_rs::include_kernels!(pub struct Kernels("kernels") { vector_add, scale });
+///
+/// let kernels = Kernels::load().unwrap();
+/// let add = kernels.vector_add().unwrap();
+/// ```
+#[macro_export]
+macro_rules! include_kernels {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident($bundle:literal) $({ $($kernel:ident),* $(,)? })?) => {
+        $crate::include_kernels!(
+            $(#[$meta])*
+            $vis struct $name = include_bytes!(concat!(env!("OUT_DIR"), "/", $bundle, ".hipfb"))
+            $(, { $($kernel),* })?
+        );
+    };
+    ($(#[$meta:meta])* $vis:vis struct $name:ident = $image:expr $(, { $($kernel:ident),* $(,)? })?) => {
+        $(#[$meta])*
+        $vis struct $name {
+            module: $crate::Module,
+        }
+
+        impl $name {
+            /// The embedded code object or offload bundle
+            pub const IMAGE: &'static [u8] = $image;
+
+            /// Loads the code object in the embedded image that best matches the current device.
+            pub fn load() -> ::std::result::Result<Self, $crate::ModuleLoadError> {
+                Ok(Self {
+                    module: $crate::Module::load_for_device(Self::IMAGE)?,
+                })
+            }
+
+            pub fn module(&self) -> &$crate::Module {
+                &self.module
+            }
+
+            pub fn into_module(self) -> $crate::Module {
+                self.module
+            }
+
+            $($(
+                #[allow(non_snake_case)]
+                pub fn $kernel(&self) -> $crate::HipResult<$crate::Function<'_>> {
+                    self.module.function(stringify!($kernel))
+                }
+            )*)?
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jit_options_to_raw() {
+        let (keys, values) = JitOptions::new().to_raw();
+        assert!(keys.is_empty() && values.is_empty());
+
+        let options = JitOptions::new()
+            .with_max_registers(64)
+            .with_optimization_level(3)
+            .with_line_info(true);
+        let (keys, values) = options.to_raw();
+        assert_eq!(
+            keys,
+            vec![
+                sys::hipJitOption_hipJitOptionMaxRegisters,
+                sys::hipJitOption_hipJitOptionOptimizationLevel,
+                sys::hipJitOption_hipJitOptionGenerateLineInfo,
+            ]
+        );
+        let values: Vec<usize> = values.into_iter().map(|value| value as usize).collect();
+        assert_eq!(values, vec![64, 3, 1]);
+    }
+
+    #[test]
+    fn test_module_records_device() {
+        let source = r#"extern "C" __global__ void noop() {}"#;
+        let module = crate::Program::new(source)
+            .compile()
+            .unwrap()
+            .load()
+            .unwrap();
+        let device = get_device().unwrap();
+        assert_eq!(module.device(), device);
+        assert_eq!(module.function("noop").unwrap().device(), device);
+    }
+
+    #[test]
+    fn test_load_invalid_path() {
+        let result = Module::load("kernel\0.hsaco");
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+        assert_eq!(
+            Module::load_data(&[]).unwrap_err().status,
+            HipStatus::InvalidValue
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_non_utf8_path() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"kernels\xff.hsaco"));
+        assert_eq!(
+            path_to_cstring(path).unwrap().as_bytes(),
+            b"kernels\xff.hsaco"
+        );
+        assert_eq!(path_to_cstring(Path::new("kernel\0.hsaco")), None);
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let result = Module::load("/nonexistent/kernels.hsaco");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_load_truncated_image() {
+        let code = crate::code_object::build_elf(2, 0x03f, &[]);
+        // The section header table is last, so dropping a byte cuts it short
+        let cases: [&[u8]; 2] = [&code[..code.len() - 1], &[0u8; 64]];
+        for image in cases {
+            assert_eq!(
+                Module::load_data(image).unwrap_err().status,
+                HipStatus::InvalidValue
+            );
+            assert_eq!(
+                Module::load_data_ex(image, &JitOptions::new())
+                    .unwrap_err()
+                    .status,
+                HipStatus::InvalidValue
+            );
+        }
+    }
+
+    crate::include_kernels!(
+        /// Kernels from an invalid image
+        struct InvalidKernels = &[0u8; 64], { vector_add, scale }
+    );
+
+    #[test]
+    fn test_load_for_target_mismatch() {
+        let target: TargetId = "gfx1030".parse().unwrap();
+        let image = b"__CLANG_OFFLOAD_BUNDLE__\0\0\0\0\0\0\0\0";
+        let error = Module::load_for_target(image, &target).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "no code object for gfx1030, the image has no device code"
+        );
+        assert!(matches!(
+            Module::load_for_target(&[0u8; 64], &target),
+            Err(ModuleLoadError::CodeObject(CodeObjectError::InvalidElf(_)))
+        ));
+    }
+
+    #[test]
+    fn test_include_kernels() {
+        assert_eq!(InvalidKernels::IMAGE, &[0u8; 64]);
+        assert!(InvalidKernels::load().is_err());
+        // The accessors exist for every listed kernel
+        let _ = InvalidKernels::vector_add;
+        let _ = InvalidKernels::scale;
+        let _ = InvalidKernels::module;
+        let _ = InvalidKernels::into_module;
+    }
+}
@@ -0,0 +1,1105 @@
+use super::allocator::DeviceAllocator;
+use super::copy::copy;
+use super::device_copy::{write_zeroed, DeviceCopy};
+use super::memory::MemoryPointer;
+use super::result::HipError;
+use crate::result::StatusCode;
+use crate::sys;
+use crate::Complex32;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// The header is padded so the data starts at a multiple of this
+const HEADER_ALIGNMENT: usize = 64;
+
+/// Data is read in chunks of this many bytes, so a header that claims more
+/// elements than the file holds fails at the end of the data instead of
+/// allocating the whole array up front
+const READ_CHUNK_BYTES: usize = 1 << 24;
+
+/// Errors from reading or writing NumPy files.
+#[derive(Debug)]
+pub enum NpyError {
+    /// Reading or writing the file failed
+    Io(io::Error),
+    /// Copying to or from the device failed
+    Hip(HipError),
+    /// The file is not a valid or supported .npy file
+    Format(String),
+    /// The file holds a different element type than requested
+    DtypeMismatch { expected: NpyDtype, found: NpyDtype },
+    /// The shape does not match the number of elements
+    ShapeMismatch { shape: Vec<usize>, len: usize },
+    /// An .npz archive has no array with the given name
+    MissingArray(String),
+}
+
+pub type NpyResult<T> = std::result::Result<T, NpyError>;
+
+impl fmt::Display for NpyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NpyError::Io(error) => write!(f, "I/O error: {}", error),
+            NpyError::Hip(error) => StatusCode::fmt(error, f),
+            NpyError::Format(message) => write!(f, "invalid .npy file: {}", message),
+            NpyError::DtypeMismatch { expected, found } => write!(
+                f,
+                "expected dtype {} but found {}",
+                expected.descr(),
+                found.descr()
+            ),
+            NpyError::ShapeMismatch { shape, len } => {
+                write!(f, "shape {:?} does not match {} elements", shape, len)
+            }
+            NpyError::MissingArray(name) => write!(f, "no array named '{}' in archive", name),
+        }
+    }
+}
+
+impl std::error::Error for NpyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NpyError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for NpyError {
+    fn from(error: io::Error) -> Self {
+        NpyError::Io(error)
+    }
+}
+
+impl From<HipError> for NpyError {
+    fn from(error: HipError) -> Self {
+        NpyError::Hip(error)
+    }
+}
+
+/// The element types supported in .npy files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NpyDtype {
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    Float16,
+    Float32,
+    Float64,
+    Complex64,
+    Complex128,
+}
+
+impl NpyDtype {
+    const ALL: [NpyDtype; 13] = [
+        NpyDtype::Int8,
+        NpyDtype::Int16,
+        NpyDtype::Int32,
+        NpyDtype::Int64,
+        NpyDtype::UInt8,
+        NpyDtype::UInt16,
+        NpyDtype::UInt32,
+        NpyDtype::UInt64,
+        NpyDtype::Float16,
+        NpyDtype::Float32,
+        NpyDtype::Float64,
+        NpyDtype::Complex64,
+        NpyDtype::Complex128,
+    ];
+
+    fn kind(self) -> char {
+        match self {
+            NpyDtype::Int8 | NpyDtype::Int16 | NpyDtype::Int32 | NpyDtype::Int64 => 'i',
+            NpyDtype::UInt8 | NpyDtype::UInt16 | NpyDtype::UInt32 | NpyDtype::UInt64 => 'u',
+            NpyDtype::Float16 | NpyDtype::Float32 | NpyDtype::Float64 => 'f',
+            NpyDtype::Complex64 | NpyDtype::Complex128 => 'c',
+        }
+    }
+
+    /// Returns the size of one element in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            NpyDtype::Int8 | NpyDtype::UInt8 => 1,
+            NpyDtype::Int16 | NpyDtype::UInt16 | NpyDtype::Float16 => 2,
+            NpyDtype::Int32 | NpyDtype::UInt32 | NpyDtype::Float32 => 4,
+            NpyDtype::Int64 | NpyDtype::UInt64 | NpyDtype::Float64 | NpyDtype::Complex64 => 8,
+            NpyDtype::Complex128 => 16,
+        }
+    }
+
+    /// Returns the little endian type string, e.g. `<f4` or `|u1`.
+    pub fn descr(self) -> String {
+        let order = if self.size() == 1 { '|' } else { '<' };
+        format!("{}{}{}", order, self.kind(), self.size())
+    }
+
+    /// Parses a type string, returning the dtype and whether it is big endian.
+    fn parse_descr(descr: &str) -> NpyResult<(Self, bool)> {
+        let unsupported = || NpyError::Format(format!("unsupported dtype '{}'", descr));
+        let mut chars = descr.chars();
+        let big_endian = match chars.next() {
+            Some('<') | Some('|') => false,
+            Some('>') => true,
+            Some('=') => cfg!(target_endian = "big"),
+            _ => return Err(unsupported()),
+        };
+        let kind = chars.next().ok_or_else(unsupported)?;
+        let size: usize = chars.as_str().parse().map_err(|_| unsupported())?;
+
+        let dtype = Self::ALL
+            .into_iter()
+            .find(|dtype| dtype.kind() == kind && dtype.size() == size)
+            .ok_or_else(unsupported)?;
+        Ok((dtype, big_endian))
+    }
+
+    /// Size of the scalars that make up an element, which are byte swapped
+    /// individually.
+    fn scalar_size(self) -> usize {
+        match self {
+            NpyDtype::Complex64 | NpyDtype::Complex128 => self.size() / 2,
+            _ => self.size(),
+        }
+    }
+}
+
+/// Element types that can be stored in .npy files.
+///
+/// Raw half precision values stored as `u16`, like `hipblasHalf`, are saved as
+/// `<u2`. Enable the `half` feature to use `half::f16` for `<f2` arrays.
+///
+/// # Safety
+/// `Self` must have the size and byte layout of a little endian `DTYPE`
+/// element, without padding.
+pub unsafe trait NpyElement: DeviceCopy {
+    /// The dtype of the element
+    const DTYPE: NpyDtype;
+}
+
+macro_rules! impl_npy_element {
+    ($($t:ty => $dtype:ident),* $(,)?) => {
+        $(unsafe impl NpyElement for $t {
+            const DTYPE: NpyDtype = NpyDtype::$dtype;
+        })*
+    };
+}
+
+impl_npy_element!(
+    i8 => Int8,
+    i16 => Int16,
+    i32 => Int32,
+    i64 => Int64,
+    u8 => UInt8,
+    u16 => UInt16,
+    u32 => UInt32,
+    u64 => UInt64,
+    f32 => Float32,
+    f64 => Float64,
+    Complex32 => Complex64,
+    sys::hipblasComplex => Complex64,
+    sys::hipblasDoubleComplex => Complex128,
+);
+
+#[cfg(feature = "half")]
+impl_npy_element!(half::f16 => Float16);
+
+/// The header of a .npy file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NpyHeader {
+    pub dtype: NpyDtype,
+    /// True if the data is stored column-major
+    pub fortran_order: bool,
+    pub shape: Vec<usize>,
+    /// True if the data is stored big endian. Written files are always little endian.
+    pub big_endian: bool,
+}
+
+impl NpyHeader {
+    /// Creates a little endian header.
+    pub fn new(dtype: NpyDtype, shape: Vec<usize>, fortran_order: bool) -> Self {
+        Self {
+            dtype,
+            fortran_order,
+            shape,
+            big_endian: false,
+        }
+    }
+
+    /// Returns the number of elements, which is 1 for a scalar with shape `()`.
+    ///
+    /// Saturates at `usize::MAX` for shapes too large to be read.
+    pub fn num_elements(&self) -> usize {
+        self.shape
+            .iter()
+            .try_fold(1usize, |product, &dim| product.checked_mul(dim))
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Reads the header from the start of a .npy file, leaving `reader` at
+    /// the start of the data.
+    ///
+    /// # Arguments
+    /// * `reader` - The reader positioned at the magic string
+    ///
+    /// # Returns
+    /// * `Ok(NpyHeader)` - The parsed header
+    /// * `Err(NpyError)` - If reading fails or the header is invalid
+    pub fn read_from<R: Read>(reader: &mut R) -> NpyResult<Self> {
+        let mut preamble = [0u8; 8];
+        reader.read_exact(&mut preamble)?;
+        if &preamble[..6] != MAGIC {
+            return Err(NpyError::Format("missing magic string".to_string()));
+        }
+
+        let header_len = match preamble[6] {
+            1 => {
+                let mut len = [0u8; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_le_bytes(len) as usize
+            }
+            2 | 3 => {
+                let mut len = [0u8; 4];
+                reader.read_exact(&mut len)?;
+                u32::from_le_bytes(len) as usize
+            }
+            major => {
+                return Err(NpyError::Format(format!(
+                    "unsupported version {}.{}",
+                    major, preamble[7]
+                )))
+            }
+        };
+
+        let mut header = vec![0u8; header_len];
+        reader.read_exact(&mut header)?;
+        let header = std::str::from_utf8(&header)
+            .map_err(|_| NpyError::Format("header is not valid text".to_string()))?;
+        Self::parse_dict(header)
+    }
+
+    /// Writes the magic string and header, padded so the data that follows
+    /// is aligned to 64 bytes.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> NpyResult<()> {
+        if self.big_endian {
+            return Err(NpyError::Format(
+                "writing big endian data is not supported".to_string(),
+            ));
+        }
+
+        let mut dict = self.to_dict();
+        // Version 1.0 stores the header length in two bytes, 2.0 in four
+        let (version, preamble_len) = if dict.len() + 1 + 10 <= u16::MAX as usize {
+            (1u8, 10)
+        } else {
+            (2u8, 12)
+        };
+        let unpadded = preamble_len + dict.len() + 1;
+        let padding = (HEADER_ALIGNMENT - unpadded % HEADER_ALIGNMENT) % HEADER_ALIGNMENT;
+        dict.push_str(&" ".repeat(padding));
+        dict.push('\n');
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[version, 0])?;
+        if version == 1 {
+            writer.write_all(&(dict.len() as u16).to_le_bytes())?;
+        } else {
+            let len = u32::try_from(dict.len())
+                .map_err(|_| NpyError::Format("header is too large".to_string()))?;
+            writer.write_all(&len.to_le_bytes())?;
+        }
+        writer.write_all(dict.as_bytes())?;
+        Ok(())
+    }
+
+    fn to_dict(&self) -> String {
+        let shape = match self.shape.as_slice() {
+            [] => "()".to_string(),
+            [len] => format!("({},)", len),
+            dims => format!(
+                "({})",
+                dims.iter()
+                    .map(|dim| dim.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        format!(
+            "{{'descr': '{}', 'fortran_order': {}, 'shape': {}, }}",
+            self.dtype.descr(),
+            if self.fortran_order { "True" } else { "False" },
+            shape
+        )
+    }
+
+    fn parse_dict(text: &str) -> NpyResult<Self> {
+        let mut parser = DictParser { rest: text };
+        let mut descr = None;
+        let mut fortran_order = None;
+        let mut shape = None;
+
+        parser.expect('{')?;
+        loop {
+            if parser.eat('}') {
+                break;
+            }
+            let key = parser.string()?;
+            parser.expect(':')?;
+            match key.as_str() {
+                "descr" => descr = Some(parser.string()?),
+                "fortran_order" => fortran_order = Some(parser.boolean()?),
+                "shape" => shape = Some(parser.tuple()?),
+                _ => return Err(NpyError::Format(format!("unexpected key '{}'", key))),
+            }
+            if !parser.eat(',') {
+                parser.expect('}')?;
+                break;
+            }
+        }
+
+        let missing = |key: &str| NpyError::Format(format!("header is missing '{}'", key));
+        let (dtype, big_endian) = NpyDtype::parse_descr(&descr.ok_or_else(|| missing("descr"))?)?;
+        Ok(Self {
+            dtype,
+            fortran_order: fortran_order.ok_or_else(|| missing("fortran_order"))?,
+            shape: shape.ok_or_else(|| missing("shape"))?,
+            big_endian,
+        })
+    }
+}
+
+/// Parser for the Python dict literal in .npy headers.
+struct DictParser<'a> {
+    rest: &'a str,
+}
+
+impl DictParser<'_> {
+    fn error(&self, expected: &str) -> NpyError {
+        let found: String = self.rest.chars().take(16).collect();
+        NpyError::Format(format!("expected {} at '{}'", expected, found))
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> NpyResult<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{}'", c)))
+        }
+    }
+
+    fn string(&mut self) -> NpyResult<String> {
+        self.skip_whitespace();
+        let quote = match self.rest.chars().next() {
+            Some(quote @ ('\'' | '"')) => quote,
+            _ => return Err(self.error("a string")),
+        };
+        let end = self.rest[1..]
+            .find(quote)
+            .ok_or_else(|| self.error("a closing quote"))?;
+        let value = self.rest[1..end + 1].to_string();
+        self.rest = &self.rest[end + 2..];
+        Ok(value)
+    }
+
+    fn boolean(&mut self) -> NpyResult<bool> {
+        self.skip_whitespace();
+        if let Some(rest) = self.rest.strip_prefix("True") {
+            self.rest = rest;
+            Ok(true)
+        } else if let Some(rest) = self.rest.strip_prefix("False") {
+            self.rest = rest;
+            Ok(false)
+        } else {
+            Err(self.error("True or False"))
+        }
+    }
+
+    fn tuple(&mut self) -> NpyResult<Vec<usize>> {
+        self.expect('(')?;
+        let mut values = Vec::new();
+        loop {
+            if self.eat(')') {
+                break;
+            }
+            self.skip_whitespace();
+            let digits = self
+                .rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(self.rest.len());
+            let value = self.rest[..digits]
+                .parse()
+                .map_err(|_| self.error("a dimension"))?;
+            values.push(value);
+            self.rest = &self.rest[digits..];
+            // Python 2 wrote long integers with an L suffix
+            self.eat('L');
+            if !self.eat(',') {
+                self.expect(')')?;
+                break;
+            }
+        }
+        Ok(values)
+    }
+}
+
+/// An array read from or to be written to a .npy file, held in host memory.
+///
+/// # Examples
+/// ```
+/// use hip_rs::NpyArray;
+///
+/// let array = NpyArray::new(vec![1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0], vec![2, 3], false).unwrap();
+///
+/// let mut bytes = Vec::new();
+/// array.write_to(&mut bytes).unwrap();
+/// let read = NpyArray::<f32>::read_from(&mut &bytes[..]).unwrap();
+/// assert_eq!(read, array);
+///
+/// // Column-major layout, as used by hipBLAS
+/// let column_major = read.into_fortran_order();
+/// assert_eq!(column_major.data(), &[1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpyArray<T> {
+    data: Vec<T>,
+    shape: Vec<usize>,
+    fortran_order: bool,
+}
+
+impl<T: NpyElement> NpyArray<T> {
+    /// Creates an array from elements laid out in C or Fortran order.
+    ///
+    /// # Arguments
+    /// * `data` - The elements
+    /// * `shape` - The dimensions, whose product must equal `data.len()`
+    /// * `fortran_order` - True if `data` is column-major
+    ///
+    /// # Returns
+    /// * `Ok(NpyArray<T>)` - The array
+    /// * `Err(NpyError::ShapeMismatch)` - If the shape does not match the data
+    pub fn new(data: Vec<T>, shape: Vec<usize>, fortran_order: bool) -> NpyResult<Self> {
+        check_shape(&shape, data.len())?;
+        Ok(Self {
+            data,
+            shape,
+            fortran_order,
+        })
+    }
+
+    /// Creates a one dimensional array.
+    pub fn from_vec(data: Vec<T>) -> Self {
+        let shape = vec![data.len()];
+        Self {
+            data,
+            shape,
+            fortran_order: false,
+        }
+    }
+
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<T> {
+        self.data
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn is_fortran_order(&self) -> bool {
+        self.fortran_order
+    }
+
+    /// Returns the header describing this array.
+    pub fn header(&self) -> NpyHeader {
+        NpyHeader::new(T::DTYPE, self.shape.clone(), self.fortran_order)
+    }
+
+    /// Returns the array with its elements laid out column-major.
+    pub fn into_fortran_order(self) -> Self {
+        self.into_order(true)
+    }
+
+    /// Returns the array with its elements laid out row-major.
+    pub fn into_c_order(self) -> Self {
+        self.into_order(false)
+    }
+
+    fn into_order(self, fortran_order: bool) -> Self {
+        if self.fortran_order == fortran_order || self.shape.len() < 2 {
+            return Self {
+                fortran_order,
+                ..self
+            };
+        }
+        Self {
+            data: reorder(&self.data, &self.shape, fortran_order),
+            shape: self.shape,
+            fortran_order,
+        }
+    }
+
+    /// Reads an array in .npy format.
+    ///
+    /// # Returns
+    /// * `Ok(NpyArray<T>)` - The array, converted to little endian
+    /// * `Err(NpyError)` - If reading fails, the file is invalid or holds another dtype
+    pub fn read_from<R: Read>(reader: &mut R) -> NpyResult<Self> {
+        let header = NpyHeader::read_from(reader)?;
+        if header.dtype != T::DTYPE {
+            return Err(NpyError::DtypeMismatch {
+                expected: T::DTYPE,
+                found: header.dtype,
+            });
+        }
+
+        let mut data = read_elements::<T, R>(reader, header.num_elements())?;
+        if header.big_endian {
+            for scalar in as_bytes_mut(&mut data).chunks_exact_mut(T::DTYPE.scalar_size()) {
+                scalar.reverse();
+            }
+        }
+
+        Ok(Self {
+            data,
+            shape: header.shape,
+            fortran_order: header.fortran_order,
+        })
+    }
+
+    /// Writes the array in .npy format.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> NpyResult<()> {
+        self.header().write_to(writer)?;
+        writer.write_all(as_bytes(&self.data))?;
+        Ok(())
+    }
+
+    /// Reads an array from a .npy file.
+    pub fn read<P: AsRef<Path>>(path: P) -> NpyResult<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Writes the array to a .npy file, replacing any existing file.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> NpyResult<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<T: NpyElement, A: DeviceAllocator> MemoryPointer<T, A> {
+    /// Copies the buffer to the host as an array with the given shape.
+    ///
+    /// # Arguments
+    /// * `shape` - The dimensions, whose product must equal the buffer size
+    /// * `fortran_order` - True if the buffer is column-major, as for hipBLAS matrices
+    ///
+    /// # Returns
+    /// * `Ok(NpyArray<T>)` - The array
+    /// * `Err(NpyError)` - If the shape does not match or the copy fails
+    pub fn to_npy_array(&self, shape: &[usize], fortran_order: bool) -> NpyResult<NpyArray<T>> {
+        check_shape(shape, self.size())?;
+        let mut data = zeroed_vec::<T>(self.size())?;
+        copy(&mut data[..], self)?;
+        NpyArray::new(data, shape.to_vec(), fortran_order)
+    }
+
+    /// Saves the buffer as a one dimensional array in a .npy file.
+    ///
+    /// # Examples
+    /// ```
+    /// use hip_rs::{copy, MemoryPointer};
+    ///
+    /// let path = std::env::temp_dir().join("hip_rs_save_npy_example.npy");
+    /// let mut buffer = MemoryPointer::<f32>::alloc(4).unwrap();
+    /// copy(&mut buffer, &[1.0f32, 2.0, 3.0, 4.0][..]).unwrap();
+    ///
+    /// buffer.save_npy(&path).unwrap();
+    /// let loaded = MemoryPointer::<f32>::load_npy(&path).unwrap();
+    /// assert_eq!(loaded.size(), 4);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn save_npy<P: AsRef<Path>>(&self, path: P) -> NpyResult<()> {
+        self.save_npy_with_shape(path, &[self.size()], false)
+    }
+
+    /// Saves the buffer to a .npy file with the given shape.
+    ///
+    /// # Arguments
+    /// * `path` - The file to write
+    /// * `shape` - The dimensions, whose product must equal the buffer size
+    /// * `fortran_order` - True if the buffer is column-major, as for hipBLAS matrices
+    pub fn save_npy_with_shape<P: AsRef<Path>>(
+        &self,
+        path: P,
+        shape: &[usize],
+        fortran_order: bool,
+    ) -> NpyResult<()> {
+        self.to_npy_array(shape, fortran_order)?.write(path)
+    }
+}
+
+impl<T: NpyElement> MemoryPointer<T> {
+    /// Allocates a buffer holding the elements of `array`, in the array's order.
+    pub fn from_npy_array(array: &NpyArray<T>) -> NpyResult<Self> {
+        let mut pointer = Self::alloc(array.data.len())?;
+        copy(&mut pointer, &array.data[..])?;
+        Ok(pointer)
+    }
+
+    /// Loads a .npy file into a new buffer.
+    ///
+    /// The elements are copied in the order they are stored in the file. Use
+    /// [`MemoryPointer::load_npy_fortran`] to get a column-major buffer.
+    ///
+    /// # Returns
+    /// * `Ok(MemoryPointer<T>)` - The buffer
+    /// * `Err(NpyError)` - If the file is invalid, holds another dtype or the copy fails
+    pub fn load_npy<P: AsRef<Path>>(path: P) -> NpyResult<Self> {
+        Self::from_npy_array(&NpyArray::read(path)?)
+    }
+
+    /// Loads a .npy file into a new column-major buffer, as expected by hipBLAS.
+    ///
+    /// # Returns
+    /// * `Ok((MemoryPointer<T>, Vec<usize>))` - The buffer and the shape of the array
+    /// * `Err(NpyError)` - If the file is invalid, holds another dtype or the copy fails
+    pub fn load_npy_fortran<P: AsRef<Path>>(path: P) -> NpyResult<(Self, Vec<usize>)> {
+        let array = NpyArray::read(path)?.into_fortran_order();
+        Ok((Self::from_npy_array(&array)?, array.shape))
+    }
+}
+
+fn check_shape(shape: &[usize], len: usize) -> NpyResult<()> {
+    let elements = shape
+        .iter()
+        .try_fold(1usize, |product, &dim| product.checked_mul(dim));
+    if elements != Some(len) {
+        return Err(NpyError::ShapeMismatch {
+            shape: shape.to_vec(),
+            len,
+        });
+    }
+    Ok(())
+}
+
+fn check_alloc_size<T>(len: usize) -> NpyResult<()> {
+    match len.checked_mul(std::mem::size_of::<T>()) {
+        Some(bytes) if bytes <= isize::MAX as usize => Ok(()),
+        _ => Err(NpyError::Format("array is too large".to_string())),
+    }
+}
+
+fn reserve<T>(data: &mut Vec<T>, additional: usize) -> NpyResult<()> {
+    data.try_reserve_exact(additional)
+        .map_err(|_| NpyError::Format("not enough memory for the array".to_string()))
+}
+
+/// Appends `additional` zeroed elements to `data`.
+fn extend_zeroed<T: DeviceCopy>(data: &mut Vec<T>, additional: usize) -> NpyResult<()> {
+    reserve(data, additional)?;
+    let len = data.len();
+    unsafe {
+        write_zeroed(data.as_mut_ptr().add(len), additional);
+        data.set_len(len + additional);
+    }
+    Ok(())
+}
+
+fn zeroed_vec<T: DeviceCopy>(len: usize) -> NpyResult<Vec<T>> {
+    check_alloc_size::<T>(len)?;
+    let mut data = Vec::new();
+    extend_zeroed(&mut data, len)?;
+    Ok(data)
+}
+
+/// Reads `len` elements, growing the buffer as data arrives.
+fn read_elements<T: NpyElement, R: Read>(reader: &mut R, len: usize) -> NpyResult<Vec<T>> {
+    check_alloc_size::<T>(len)?;
+    let chunk = (READ_CHUNK_BYTES / std::mem::size_of::<T>().max(1)).max(1);
+    let mut data = Vec::new();
+    while data.len() < len {
+        let start = data.len();
+        let count = chunk.min(len - start);
+        // Grow geometrically so large arrays are not copied once per chunk
+        if data.capacity() - start < count {
+            reserve(&mut data, count.max(start).min(len - start))?;
+        }
+        extend_zeroed(&mut data, count)?;
+        reader.read_exact(as_bytes_mut(&mut data[start..]))?;
+    }
+    Ok(data)
+}
+
+fn as_bytes<T: NpyElement>(data: &[T]) -> &[u8] {
+    // NpyElement types have no padding
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
+
+fn as_bytes_mut<T: NpyElement>(data: &mut [T]) -> &mut [u8] {
+    unsafe {
+        std::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, std::mem::size_of_val(data))
+    }
+}
+
+/// Converts elements between row-major and column-major layout.
+fn reorder<T: Copy>(data: &[T], shape: &[usize], to_fortran: bool) -> Vec<T> {
+    // Strides of the source layout, which is the opposite of the target
+    let ndim = shape.len();
+    let mut strides = vec![1; ndim];
+    if to_fortran {
+        for k in (0..ndim.saturating_sub(1)).rev() {
+            strides[k] = strides[k + 1] * shape[k + 1];
+        }
+    } else {
+        for k in 1..ndim {
+            strides[k] = strides[k - 1] * shape[k - 1];
+        }
+    }
+
+    // Walk the target layout in memory order and gather from the source
+    let mut index = vec![0; ndim];
+    let mut out = Vec::with_capacity(data.len());
+    for _ in 0..data.len() {
+        let offset: usize = index.iter().zip(&strides).map(|(i, s)| i * s).sum();
+        out.push(data[offset]);
+
+        let mut advance = |k: usize| {
+            index[k] += 1;
+            if index[k] < shape[k] {
+                return true;
+            }
+            index[k] = 0;
+            false
+        };
+        if to_fortran {
+            (0..ndim).any(&mut advance);
+        } else {
+            (0..ndim).rev().any(&mut advance);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Header written by `np.save` for `np.zeros((3, 4), dtype=np.float32)`
+    fn numpy_header() -> Vec<u8> {
+        let dict = "{'descr': '<f4', 'fortran_order': False, 'shape': (3, 4), }";
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        let padded = format!("{:<117}\n", dict);
+        bytes.extend_from_slice(&(padded.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(padded.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_dtype_descr() {
+        assert_eq!(NpyDtype::Float32.descr(), "<f4");
+        assert_eq!(NpyDtype::UInt8.descr(), "|u1");
+        assert_eq!(NpyDtype::Complex128.descr(), "<c16");
+        assert_eq!(
+            NpyDtype::parse_descr("<c8").unwrap(),
+            (NpyDtype::Complex64, false)
+        );
+        assert_eq!(
+            NpyDtype::parse_descr(">i2").unwrap(),
+            (NpyDtype::Int16, true)
+        );
+        assert_eq!(
+            NpyDtype::parse_descr("|i1").unwrap(),
+            (NpyDtype::Int8, false)
+        );
+        assert!(NpyDtype::parse_descr("<f3").is_err());
+        assert!(NpyDtype::parse_descr("|b1").is_err());
+        assert!(NpyDtype::parse_descr("<U10").is_err());
+    }
+
+    #[test]
+    fn test_parse_numpy_header() {
+        let bytes = numpy_header();
+        assert_eq!(bytes.len(), 128);
+
+        let header = NpyHeader::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(header, NpyHeader::new(NpyDtype::Float32, vec![3, 4], false));
+        assert_eq!(header.num_elements(), 12);
+    }
+
+    #[test]
+    fn test_header_matches_numpy() {
+        let mut bytes = Vec::new();
+        NpyHeader::new(NpyDtype::Float32, vec![3, 4], false)
+            .write_to(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes, numpy_header());
+    }
+
+    #[test]
+    fn test_parse_header_variants() {
+        let cases = [
+            (
+                "{'shape': (5,), 'fortran_order': True, 'descr': '<f8'}",
+                vec![5],
+                true,
+            ),
+            (
+                "{\"descr\": \"<i4\", \"fortran_order\": False, \"shape\": ()}",
+                vec![],
+                false,
+            ),
+            (
+                "{'descr': '<u2', 'fortran_order': False, 'shape': (2L, 3L), }",
+                vec![2, 3],
+                false,
+            ),
+            (
+                "{ 'descr' : '<c8' , 'fortran_order' : False , 'shape' : ( 0 , 7 ) }",
+                vec![0, 7],
+                false,
+            ),
+        ];
+
+        for (dict, shape, fortran_order) in cases {
+            let header = NpyHeader::parse_dict(dict).unwrap();
+            assert_eq!(header.shape, shape, "{}", dict);
+            assert_eq!(header.fortran_order, fortran_order, "{}", dict);
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_headers() {
+        let cases = [
+            "{'descr': '<f4', 'fortran_order': False}",
+            "{'descr': '<f4', 'fortran_order': 0, 'shape': (1,)}",
+            "{'descr': [('x', '<f4')], 'fortran_order': False, 'shape': (1,)}",
+            "{'descr': '<f4', 'fortran_order': False, 'shape': (1,), 'extra': 1}",
+            "{'descr': '<f4', 'fortran_order': False, 'shape': (-1,)}",
+            "{'descr': '<f4",
+        ];
+
+        for dict in cases {
+            assert!(
+                matches!(NpyHeader::parse_dict(dict), Err(NpyError::Format(_))),
+                "{}",
+                dict
+            );
+        }
+
+        let result = NpyHeader::read_from(&mut &b"\x93NUMPX\x01\x00\x00\x00"[..]);
+        assert!(matches!(result, Err(NpyError::Format(_))));
+        let result = NpyHeader::read_from(&mut &b"\x93NUMPY\x04\x00\x00\x00"[..]);
+        assert!(matches!(result, Err(NpyError::Format(_))));
+    }
+
+    #[test]
+    fn test_header_alignment() {
+        for ndim in 0..20 {
+            let mut bytes = Vec::new();
+            NpyHeader::new(NpyDtype::Int64, vec![7; ndim], true)
+                .write_to(&mut bytes)
+                .unwrap();
+            assert_eq!(bytes.len() % HEADER_ALIGNMENT, 0);
+            assert_eq!(bytes.last(), Some(&b'\n'));
+
+            let header = NpyHeader::read_from(&mut &bytes[..]).unwrap();
+            assert_eq!(header.shape, vec![7; ndim]);
+        }
+    }
+
+    #[test]
+    fn test_large_header_uses_version_2() {
+        let shape = vec![1; 30000];
+        let mut bytes = Vec::new();
+        NpyHeader::new(NpyDtype::UInt8, shape.clone(), false)
+            .write_to(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes[6], 2);
+        assert_eq!(bytes.len() % HEADER_ALIGNMENT, 0);
+        assert_eq!(NpyHeader::read_from(&mut &bytes[..]).unwrap().shape, shape);
+    }
+
+    #[test]
+    fn test_array_roundtrip() {
+        let array =
+            NpyArray::new((0..24).map(|i| i as f64).collect(), vec![2, 3, 4], true).unwrap();
+        let mut bytes = Vec::new();
+        array.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 128 + 24 * 8);
+
+        let read = NpyArray::<f64>::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(read, array);
+    }
+
+    #[test]
+    fn test_array_complex_roundtrip() {
+        let array = NpyArray::from_vec(vec![Complex32::new(1.0, -2.0), Complex32::new(0.5, 3.0)]);
+        let mut bytes = Vec::new();
+        array.write_to(&mut bytes).unwrap();
+        assert!(bytes.windows(5).any(|window| window == b"'<c8'"));
+
+        let read = NpyArray::<Complex32>::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(read.data(), array.data());
+    }
+
+    #[test]
+    fn test_read_big_endian() {
+        let mut bytes = Vec::new();
+        let mut header = NpyHeader::new(NpyDtype::Int32, vec![2], false);
+        header.write_to(&mut bytes).unwrap();
+        header.big_endian = true;
+        let position = bytes.windows(3).position(|w| w == b"<i4").unwrap();
+        bytes[position] = b'>';
+        bytes.extend_from_slice(&1i32.to_be_bytes());
+        bytes.extend_from_slice(&(-2i32).to_be_bytes());
+
+        let read = NpyArray::<i32>::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(read.data(), &[1, -2]);
+        assert!(header.write_to(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_read_dtype_mismatch() {
+        let mut bytes = Vec::new();
+        NpyArray::from_vec(vec![1u16, 2])
+            .write_to(&mut bytes)
+            .unwrap();
+
+        let result = NpyArray::<i16>::read_from(&mut &bytes[..]);
+        assert!(matches!(
+            result,
+            Err(NpyError::DtypeMismatch {
+                expected: NpyDtype::Int16,
+                found: NpyDtype::UInt16
+            })
+        ));
+    }
+
+    #[test]
+    fn test_read_truncated_data() {
+        let mut bytes = Vec::new();
+        NpyArray::from_vec(vec![1u64; 4])
+            .write_to(&mut bytes)
+            .unwrap();
+        bytes.truncate(bytes.len() - 1);
+
+        let result = NpyArray::<u64>::read_from(&mut &bytes[..]);
+        assert!(matches!(result, Err(NpyError::Io(_))));
+    }
+
+    #[test]
+    fn test_read_oversized_shape() {
+        // The header claims 2^40 elements but no data follows
+        let mut bytes = Vec::new();
+        NpyHeader::new(NpyDtype::Float32, vec![1 << 20, 1 << 20], false)
+            .write_to(&mut bytes)
+            .unwrap();
+        bytes.extend_from_slice(&[0u8; 16]);
+
+        let result = NpyArray::<f32>::read_from(&mut &bytes[..]);
+        assert!(matches!(result, Err(NpyError::Io(_))));
+
+        let mut bytes = Vec::new();
+        NpyHeader::new(NpyDtype::Float64, vec![usize::MAX / 4], false)
+            .write_to(&mut bytes)
+            .unwrap();
+        let result = NpyArray::<f64>::read_from(&mut &bytes[..]);
+        assert!(matches!(result, Err(NpyError::Format(_))));
+    }
+
+    #[test]
+    fn test_shape_mismatch() {
+        let result = NpyArray::new(vec![0u8; 6], vec![4, 2], false);
+        assert!(matches!(
+            result,
+            Err(NpyError::ShapeMismatch { len: 6, .. })
+        ));
+        let result = NpyArray::new(vec![0u8; 0], vec![usize::MAX, 2, 0], false);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_fortran_order() {
+        // [[1, 2, 3], [4, 5, 6]]
+        let array = NpyArray::new(vec![1, 2, 3, 4, 5, 6u8], vec![2, 3], false).unwrap();
+        let fortran = array.clone().into_fortran_order();
+        assert!(fortran.is_fortran_order());
+        assert_eq!(fortran.data(), &[1, 4, 2, 5, 3, 6]);
+        assert_eq!(fortran.clone().into_c_order(), array);
+
+        let cube = NpyArray::new((0..24u32).collect(), vec![2, 3, 4], false).unwrap();
+        let fortran = cube.clone().into_fortran_order();
+        // Element [i, j, k] moves from 12i + 4j + k to i + 2j + 6k
+        assert_eq!(fortran.data()[1 + 2 * 2 + 6 * 3], 12 + 4 * 2 + 3);
+        assert_eq!(fortran.into_c_order(), cube);
+    }
+
+    #[cfg(feature = "half")]
+    #[test]
+    fn test_half_roundtrip() {
+        let array = NpyArray::from_vec(vec![half::f16::from_f32(1.5), half::f16::from_f32(-2.0)]);
+        let mut bytes = Vec::new();
+        array.write_to(&mut bytes).unwrap();
+        assert!(bytes.windows(5).any(|window| window == b"'<f2'"));
+        assert_eq!(NpyArray::read_from(&mut &bytes[..]).unwrap(), array);
+    }
+
+    #[test]
+    fn test_save_load_device() {
+        let path = std::env::temp_dir().join("hip_rs_test_save_load_device.npy");
+        let host: Vec<f32> = (0..6).map(|i| i as f32).collect();
+        let mut buffer = MemoryPointer::<f32>::alloc(6).unwrap();
+        copy(&mut buffer, &host).unwrap();
+
+        // Save as a column-major 2x3 matrix, as hipBLAS would store it
+        buffer.save_npy_with_shape(&path, &[2, 3], true).unwrap();
+        let array = NpyArray::<f32>::read(&path).unwrap();
+        assert_eq!(array.shape(), &[2, 3]);
+        assert!(array.is_fortran_order());
+        assert_eq!(array.data(), &host[..]);
+
+        let (loaded, shape) = MemoryPointer::<f32>::load_npy_fortran(&path).unwrap();
+        assert_eq!(shape, vec![2, 3]);
+        let mut result = vec![0.0f32; 6];
+        copy(&mut result[..], &loaded).unwrap();
+        assert_eq!(result, host);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_save_npy_shape_mismatch() {
+        let buffer = MemoryPointer::<i32>::alloc(4).unwrap();
+        let path = std::env::temp_dir().join("hip_rs_test_save_npy_shape_mismatch.npy");
+        let result = buffer.save_npy_with_shape(&path, &[3, 2], false);
+        assert!(matches!(result, Err(NpyError::ShapeMismatch { .. })));
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,250 @@
+use super::allocator::DeviceAllocator;
+use super::memory::MemoryPointer;
+use super::npy::{NpyArray, NpyElement, NpyError, NpyHeader, NpyResult};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+use zip::result::ZipError;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+impl From<ZipError> for NpyError {
+    fn from(error: ZipError) -> Self {
+        match error {
+            ZipError::Io(error) => NpyError::Io(error),
+            error => NpyError::Format(error.to_string()),
+        }
+    }
+}
+
+/// Writes named arrays to an .npz archive, as `np.savez` does.
+///
+/// # Examples
+/// ```
+/// use hip_rs::{MemoryPointer, NpyArray, NpzReader, NpzWriter};
+/// use std::io::Cursor;
+///
+/// let weights = MemoryPointer::<f32>::alloc(6).unwrap();
+/// weights.memset(0, 6 * 4).unwrap();
+///
+/// let mut writer = NpzWriter::new(Cursor::new(Vec::new()));
+/// writer.add_buffer("weights", &weights, &[2, 3], true).unwrap();
+/// writer.add_array("bias", &NpyArray::from_vec(vec![1.0f32, 2.0])).unwrap();
+/// let archive = writer.finish().unwrap();
+///
+/// let mut reader = NpzReader::new(archive).unwrap();
+/// assert_eq!(reader.names(), vec!["bias", "weights"]);
+/// let weights = reader.buffer::<f32>("weights").unwrap();
+/// assert_eq!(weights.size(), 6);
+/// ```
+pub struct NpzWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+    compressed: bool,
+}
+
+impl NpzWriter<BufWriter<File>> {
+    /// Creates an .npz file, replacing any existing file.
+    pub fn create<P: AsRef<Path>>(path: P) -> NpyResult<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write + Seek> NpzWriter<W> {
+    /// Creates a writer for an uncompressed archive.
+    pub fn new(writer: W) -> Self {
+        Self {
+            zip: ZipWriter::new(writer),
+            compressed: false,
+        }
+    }
+
+    /// Deflates the arrays, as `np.savez_compressed` does.
+    pub fn with_compression(mut self, compressed: bool) -> Self {
+        self.compressed = compressed;
+        self
+    }
+
+    /// Adds an array stored as `<name>.npy`.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the array, without the `.npy` extension
+    /// * `array` - The array to add
+    pub fn add_array<T: NpyElement>(&mut self, name: &str, array: &NpyArray<T>) -> NpyResult<()> {
+        let method = if self.compressed {
+            CompressionMethod::Deflated
+        } else {
+            CompressionMethod::Stored
+        };
+        let bytes = std::mem::size_of_val(array.data());
+        let options = FileOptions::default()
+            .compression_method(method)
+            .large_file(bytes >= u32::MAX as usize);
+
+        self.zip.start_file(format!("{}.npy", name), options)?;
+        array.write_to(&mut self.zip)
+    }
+
+    /// Copies a device buffer to the host and adds it as `<name>.npy`.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the array, without the `.npy` extension
+    /// * `buffer` - The buffer to add
+    /// * `shape` - The dimensions, whose product must equal the buffer size
+    /// * `fortran_order` - True if the buffer is column-major, as for hipBLAS matrices
+    pub fn add_buffer<T: NpyElement, A: DeviceAllocator>(
+        &mut self,
+        name: &str,
+        buffer: &MemoryPointer<T, A>,
+        shape: &[usize],
+        fortran_order: bool,
+    ) -> NpyResult<()> {
+        self.add_array(name, &buffer.to_npy_array(shape, fortran_order)?)
+    }
+
+    /// Writes the archive directory and returns the underlying writer.
+    pub fn finish(mut self) -> NpyResult<W> {
+        let mut writer = self.zip.finish()?;
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+/// Reads named arrays from an .npz archive.
+pub struct NpzReader<R: Read + Seek> {
+    zip: ZipArchive<R>,
+}
+
+impl NpzReader<BufReader<File>> {
+    /// Opens an .npz file.
+    pub fn open<P: AsRef<Path>>(path: P) -> NpyResult<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> NpzReader<R> {
+    /// Reads the archive directory.
+    pub fn new(reader: R) -> NpyResult<Self> {
+        Ok(Self {
+            zip: ZipArchive::new(reader)?,
+        })
+    }
+
+    /// Returns the names of the arrays in the archive, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self
+            .zip
+            .file_names()
+            .filter_map(|name| name.strip_suffix(".npy"))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Reads the header of an array without reading its data.
+    pub fn header(&mut self, name: &str) -> NpyResult<NpyHeader> {
+        NpyHeader::read_from(&mut self.entry(name)?)
+    }
+
+    /// Reads an array into host memory.
+    ///
+    /// # Returns
+    /// * `Ok(NpyArray<T>)` - The array
+    /// * `Err(NpyError::MissingArray)` - If the archive has no array called `name`
+    /// * `Err(NpyError)` - If the array is invalid or holds another dtype
+    pub fn array<T: NpyElement>(&mut self, name: &str) -> NpyResult<NpyArray<T>> {
+        NpyArray::read_from(&mut self.entry(name)?)
+    }
+
+    /// Reads an array into a new device buffer, in the order it is stored.
+    pub fn buffer<T: NpyElement>(&mut self, name: &str) -> NpyResult<MemoryPointer<T>> {
+        MemoryPointer::from_npy_array(&self.array(name)?)
+    }
+
+    fn entry(&mut self, name: &str) -> NpyResult<zip::read::ZipFile<'_>> {
+        match self.zip.by_name(&format!("{}.npy", name)) {
+            Ok(file) => Ok(file),
+            Err(ZipError::FileNotFound) => Err(NpyError::MissingArray(name.to_string())),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::copy;
+    use std::io::Cursor;
+
+    fn write_archive(compressed: bool) -> Cursor<Vec<u8>> {
+        let mut writer = NpzWriter::new(Cursor::new(Vec::new())).with_compression(compressed);
+        writer
+            .add_array("x", &NpyArray::from_vec(vec![1i64, 2, 3]))
+            .unwrap();
+        writer
+            .add_array(
+                "matrix",
+                &NpyArray::new(vec![0.5f64; 12], vec![3, 4], true).unwrap(),
+            )
+            .unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_npz_roundtrip() {
+        for compressed in [false, true] {
+            let mut reader = NpzReader::new(write_archive(compressed)).unwrap();
+            assert_eq!(reader.names(), vec!["matrix", "x"]);
+
+            let x = reader.array::<i64>("x").unwrap();
+            assert_eq!(x.data(), &[1, 2, 3]);
+
+            let header = reader.header("matrix").unwrap();
+            assert_eq!(header.shape, vec![3, 4]);
+            assert!(header.fortran_order);
+            let matrix = reader.array::<f64>("matrix").unwrap();
+            assert_eq!(matrix.data(), &[0.5; 12]);
+        }
+    }
+
+    #[test]
+    fn test_npz_missing_array() {
+        let mut reader = NpzReader::new(write_archive(false)).unwrap();
+        let result = reader.array::<f32>("y");
+        assert!(matches!(result, Err(NpyError::MissingArray(name)) if name == "y"));
+    }
+
+    #[test]
+    fn test_npz_dtype_mismatch() {
+        let mut reader = NpzReader::new(write_archive(false)).unwrap();
+        let result = reader.array::<f64>("x");
+        assert!(matches!(result, Err(NpyError::DtypeMismatch { .. })));
+    }
+
+    #[test]
+    fn test_npz_not_an_archive() {
+        let result = NpzReader::new(Cursor::new(b"not a zip file".to_vec()));
+        assert!(matches!(result, Err(NpyError::Format(_))));
+    }
+
+    #[test]
+    fn test_npz_device_buffers() {
+        let path = std::env::temp_dir().join("hip_rs_test_npz_device_buffers.npz");
+        let host: Vec<u32> = (0..8).collect();
+        let mut buffer = MemoryPointer::<u32>::alloc(8).unwrap();
+        copy(&mut buffer, &host).unwrap();
+
+        let mut writer = NpzWriter::create(&path).unwrap();
+        writer.add_buffer("a", &buffer, &[2, 4], false).unwrap();
+        writer.add_buffer("b", &buffer, &[8], false).unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = NpzReader::open(&path).unwrap();
+        let loaded = reader.buffer::<u32>("a").unwrap();
+        let mut result = vec![0u32; 8];
+        copy(&mut result[..], &loaded).unwrap();
+        assert_eq!(result, host);
+        assert_eq!(reader.header("b").unwrap().shape, vec![8]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
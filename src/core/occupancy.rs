@@ -0,0 +1,252 @@
+use super::device_types::DeviceAttribute;
+use super::module::{Function, FunctionAttribute};
+use super::result::{HipError, HipResult, HipStatus};
+use crate::result::ResultExt;
+use crate::sys;
+
+/// A block size suggested by the occupancy calculator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OccupancyBlockSize {
+    /// Threads per block that maximize occupancy
+    pub block_size: u32,
+    /// Smallest grid that reaches that occupancy on every multiprocessor
+    pub min_grid_size: u32,
+}
+
+impl Function<'_> {
+    /// Returns how many blocks of the kernel can run at once on one multiprocessor.
+    ///
+    /// This is `hipModuleOccupancyMaxActiveBlocksPerMultiprocessor`, the
+    /// module counterpart of `hipOccupancyMaxActiveBlocksPerMultiprocessor`.
+    ///
+    /// # Arguments
+    /// * `block_size` - Threads per block
+    /// * `dynamic_shared_mem` - Dynamic shared memory per block in bytes
+    ///
+    /// # Returns
+    /// * `Ok(u32)` - Active blocks per multiprocessor, 0 if the block cannot run
+    /// * `Err(HipError)` - If the query failed
+    pub fn max_active_blocks_per_multiprocessor(
+        &self,
+        block_size: u32,
+        dynamic_shared_mem: usize,
+    ) -> HipResult<u32> {
+        let mut blocks = 0;
+        unsafe {
+            let code = sys::hipModuleOccupancyMaxActiveBlocksPerMultiprocessor(
+                &mut blocks,
+                self.handle(),
+                block_size.min(i32::MAX as u32) as i32,
+                dynamic_shared_mem,
+            );
+            (blocks.max(0) as u32, code).to_result()
+        }
+    }
+
+    /// Returns the block size that maximizes occupancy.
+    ///
+    /// # Arguments
+    /// * `dynamic_shared_mem` - Dynamic shared memory per block in bytes
+    /// * `block_size_limit` - Largest block size to consider, or 0 for no limit
+    ///
+    /// # Returns
+    /// * `Ok(OccupancyBlockSize)` - The block size and the grid that fills the device
+    /// * `Err(HipError)` - If the query failed
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hip_rs::Module;
+    ///
+    /// let module = Module::load("kernels.hsaco").unwrap();
+    /// let scale = module.function("scale").unwrap();
+    /// let suggestion = scale.max_potential_block_size(0, 0).unwrap();
+    /// println!("{} threads per block", suggestion.block_size);
+    /// ```
+    pub fn max_potential_block_size(
+        &self,
+        dynamic_shared_mem: usize,
+        block_size_limit: u32,
+    ) -> HipResult<OccupancyBlockSize> {
+        let mut grid_size = 0;
+        let mut block_size = 0;
+        unsafe {
+            let code = sys::hipModuleOccupancyMaxPotentialBlockSize(
+                &mut grid_size,
+                &mut block_size,
+                self.handle(),
+                dynamic_shared_mem,
+                block_size_limit.min(i32::MAX as u32) as i32,
+            );
+            let suggestion = OccupancyBlockSize {
+                block_size: block_size.max(0) as u32,
+                min_grid_size: grid_size.max(0) as u32,
+            };
+            (suggestion, code).to_result()
+        }
+    }
+
+    /// Returns the block size that maximizes occupancy when the dynamic
+    /// shared memory depends on the block size.
+    ///
+    /// Mirrors `hipOccupancyMaxPotentialBlockSizeVariableSMem`, which is only
+    /// available as a C++ template. Every multiple of the warp size up to the
+    /// kernel's limit is tried, preferring larger blocks on ties.
+    ///
+    /// # Arguments
+    /// * `shared_mem_for_block` - Dynamic shared memory in bytes for a block size
+    /// * `block_size_limit` - Largest block size to consider, or 0 for no limit
+    ///
+    /// # Returns
+    /// * `Ok(OccupancyBlockSize)` - The block size and the grid that fills the device
+    /// * `Err(HipError)` - `InvalidConfiguration` if no block size fits, or if a query failed
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hip_rs::Module;
+    ///
+    /// let module = Module::load("kernels.hsaco").unwrap();
+    /// let reduce = module.function("reduce").unwrap();
+    /// // One f32 of shared memory per thread
+    /// let suggestion = reduce
+    ///     .max_potential_block_size_with(|block_size| block_size as usize * 4, 0)
+    ///     .unwrap();
+    /// ```
+    pub fn max_potential_block_size_with<F>(
+        &self,
+        shared_mem_for_block: F,
+        block_size_limit: u32,
+    ) -> HipResult<OccupancyBlockSize>
+    where
+        F: Fn(u32) -> usize,
+    {
+        let device = self.device();
+        let warp_size = device.attribute(DeviceAttribute::WarpSize)?.max(1) as u32;
+        let multiprocessors = device
+            .attribute(DeviceAttribute::MultiprocessorCount)?
+            .max(0) as u32;
+        let mut max_block_size = self
+            .attribute(FunctionAttribute::MaxThreadsPerBlock)?
+            .max(0) as u32;
+        if block_size_limit > 0 {
+            max_block_size = max_block_size.min(block_size_limit);
+        }
+
+        let (block_size, active_blocks) =
+            best_block_size(max_block_size, warp_size, |block_size| {
+                self.max_active_blocks_per_multiprocessor(
+                    block_size,
+                    shared_mem_for_block(block_size),
+                )
+            })?;
+        Ok(OccupancyBlockSize {
+            block_size,
+            min_grid_size: active_blocks.saturating_mul(multiprocessors),
+        })
+    }
+}
+
+/// Finds the block size with the most active threads per multiprocessor.
+///
+/// Tries multiples of `granularity` from `max_block_size` down, so ties go
+/// to the larger block.
+///
+/// # Returns
+/// * `Ok((block_size, active_blocks))` - The best block size and its active blocks
+/// * `Err(HipError)` - `InvalidConfiguration` if no block size can run, or the first failed query
+fn best_block_size<F>(
+    max_block_size: u32,
+    granularity: u32,
+    mut active_blocks: F,
+) -> HipResult<(u32, u32)>
+where
+    F: FnMut(u32) -> HipResult<u32>,
+{
+    let mut best = (0, 0);
+    let mut best_threads = 0u64;
+    let mut block_size = max_block_size - max_block_size % granularity;
+    while block_size > 0 {
+        let blocks = active_blocks(block_size)?;
+        let threads = u64::from(blocks) * u64::from(block_size);
+        if threads > best_threads {
+            best = (block_size, blocks);
+            best_threads = threads;
+        }
+        block_size -= granularity;
+    }
+    if best_threads == 0 {
+        return Err(HipError::from_status(HipStatus::InvalidConfiguration));
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LaunchConfig, Program};
+
+    #[test]
+    fn test_best_block_size() {
+        // Register limited: 64 threads per wave, 2048 threads per multiprocessor
+        let occupancy = |block_size: u32| Ok((2048 / block_size).min(16));
+        assert_eq!(best_block_size(1024, 64, occupancy), Ok((1024, 2)));
+
+        // 160 bytes of shared memory per thread fit 409 threads in 64 KiB
+        let occupancy = |block_size: u32| Ok(65536 / (block_size * 160));
+        assert_eq!(best_block_size(1024, 64, occupancy), Ok((384, 1)));
+
+        // Block sizes are rounded down to the granularity
+        let mut tried = Vec::new();
+        let occupancy = |block_size: u32| {
+            tried.push(block_size);
+            Ok(1)
+        };
+        assert_eq!(best_block_size(200, 64, occupancy), Ok((192, 1)));
+        assert_eq!(tried, vec![192, 128, 64]);
+    }
+
+    #[test]
+    fn test_best_block_size_errors() {
+        assert_eq!(
+            best_block_size(1024, 64, |_| Ok(0)).unwrap_err().status,
+            HipStatus::InvalidConfiguration
+        );
+        assert_eq!(
+            best_block_size(32, 64, |_| Ok(1)).unwrap_err().status,
+            HipStatus::InvalidConfiguration
+        );
+        let failure = HipError::from_status(HipStatus::InvalidValue);
+        assert_eq!(best_block_size(1024, 64, |_| Err(failure)), Err(failure));
+    }
+
+    #[test]
+    fn test_occupancy_queries() {
+        let source = r#"
+            extern "C" __global__ void scale(float* data, float factor, unsigned n) {
+                unsigned i = blockIdx.x * blockDim.x + threadIdx.x;
+                if (i < n) data[i] *= factor;
+            }
+        "#;
+        let module = Program::new(source).compile().unwrap().load().unwrap();
+        let scale = module.function("scale").unwrap();
+
+        let suggestion = scale.max_potential_block_size(0, 0).unwrap();
+        assert!(suggestion.block_size > 0 && suggestion.min_grid_size > 0);
+        let limited = scale.max_potential_block_size(0, 64).unwrap();
+        assert!(limited.block_size <= 64);
+        assert!(
+            scale
+                .max_active_blocks_per_multiprocessor(suggestion.block_size, 0)
+                .unwrap()
+                > 0
+        );
+
+        let variable = scale
+            .max_potential_block_size_with(|block_size| block_size as usize * 4, 0)
+            .unwrap();
+        assert!(variable.block_size > 0);
+
+        let config = LaunchConfig::auto_for(&scale, 100_000).unwrap();
+        assert_eq!(config.block().x, suggestion.block_size);
+        assert_eq!(config.grid().x, 100_000u32.div_ceil(suggestion.block_size));
+    }
+}
@@ -0,0 +1,190 @@
+use super::device_copy::{write_zeroed, DeviceCopy};
+use super::flags::HostMallocFlags;
+use super::result::{HipError, HipResult, HipStatus};
+use crate::result::ResultExt;
+use crate::sys;
+use std::ops::{Deref, DerefMut};
+
+/// Page-locked host memory allocated with `hipHostMalloc`.
+///
+/// Copies between pinned memory and the device are faster than copies from
+/// pageable memory and can run asynchronously. The buffer dereferences to a
+/// slice and is freed when dropped.
+///
+/// # Examples
+/// ```
+/// use hip_rs::PinnedBuffer;
+///
+/// let mut buffer = PinnedBuffer::<f32>::alloc(1024).unwrap();
+/// buffer[0] = 1.0;
+/// assert_eq!(buffer.len(), 1024);
+/// ```
+#[derive(Debug)]
+pub struct PinnedBuffer<T: DeviceCopy> {
+    pointer: *mut T,
+    len: usize,
+}
+
+impl<T: DeviceCopy> PinnedBuffer<T> {
+    /// Allocates a zero-initialized buffer of `len` elements with default flags.
+    ///
+    /// # Arguments
+    /// * `len` - The number of elements
+    ///
+    /// # Returns
+    /// * `Ok(PinnedBuffer<T>)` - The allocated buffer
+    /// * `Err(HipError)` - If the size overflows or the allocation fails
+    #[track_caller]
+    pub fn alloc(len: usize) -> HipResult<Self> {
+        Self::alloc_with_flags(len, HostMallocFlags::DEFAULT)
+    }
+
+    /// Allocates a zero-initialized buffer of `len` elements.
+    ///
+    /// # Arguments
+    /// * `len` - The number of elements
+    /// * `flags` - [`HostMallocFlags`] controlling portability, mapping and caching
+    ///
+    /// # Returns
+    /// * `Ok(PinnedBuffer<T>)` - The allocated buffer
+    /// * `Err(HipError)` - If the size overflows, the flags are invalid or the allocation fails
+    #[track_caller]
+    pub fn alloc_with_flags(len: usize, flags: HostMallocFlags) -> HipResult<Self> {
+        let bytes = len
+            .checked_mul(std::mem::size_of::<T>())
+            .ok_or(HipError::from_status(HipStatus::InvalidValue))?;
+        // Empty buffers and zero-sized types need no memory
+        if bytes == 0 {
+            return Ok(Self {
+                pointer: std::ptr::NonNull::dangling().as_ptr(),
+                len,
+            });
+        }
+
+        let mut ptr = std::ptr::null_mut();
+        let code = unsafe { sys::hipHostMalloc(&mut ptr, bytes, flags.bits()) };
+        let result: HipResult<()> = ((), code).to_result();
+        result?;
+
+        unsafe { write_zeroed(ptr as *mut T, len) };
+
+        #[cfg(feature = "track-allocations")]
+        super::tracking::register(
+            super::tracking::AllocationKind::PinnedHost,
+            ptr as usize,
+            bytes,
+            crate::get_device().ok(),
+        );
+
+        Ok(Self {
+            pointer: ptr as *mut T,
+            len,
+        })
+    }
+
+    /// Allocates a buffer holding a copy of `data`.
+    #[track_caller]
+    pub fn from_slice(data: &[T]) -> HipResult<Self> {
+        let mut buffer = Self::alloc(data.len())?;
+        buffer.copy_from_slice(data);
+        Ok(buffer)
+    }
+
+    /// Returns the number of elements in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the buffer holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the raw host pointer.
+    pub fn as_pointer(&self) -> *mut T {
+        self.pointer
+    }
+}
+
+impl<T: DeviceCopy> Deref for PinnedBuffer<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.pointer, self.len) }
+    }
+}
+
+impl<T: DeviceCopy> DerefMut for PinnedBuffer<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.pointer, self.len) }
+    }
+}
+
+impl<T: DeviceCopy> Drop for PinnedBuffer<T> {
+    fn drop(&mut self) {
+        // Nothing was allocated, see `alloc`
+        if self.len * std::mem::size_of::<T>() == 0 {
+            return;
+        }
+
+        #[cfg(feature = "track-allocations")]
+        super::tracking::unregister(
+            super::tracking::AllocationKind::PinnedHost,
+            self.pointer as usize,
+        );
+
+        unsafe {
+            let code = sys::hipHostFree(self.pointer as *mut std::ffi::c_void);
+            if code != 0 {
+                log::error!(
+                    "PinnedBuffer failed to free memory: {:?}",
+                    HipError::new(code)
+                );
+            }
+        }
+    }
+}
+
+// The buffer owns its memory like a Box<[T]>
+unsafe impl<T: DeviceCopy + Send> Send for PinnedBuffer<T> {}
+unsafe impl<T: DeviceCopy + Sync> Sync for PinnedBuffer<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinned_alloc_is_zeroed() {
+        let buffer = PinnedBuffer::<u32>::alloc(256).unwrap();
+        assert_eq!(buffer.len(), 256);
+        assert!(buffer.iter().all(|&value| value == 0));
+    }
+
+    #[test]
+    fn test_pinned_from_slice() {
+        let mut buffer = PinnedBuffer::from_slice(&[1.0f32, 2.0, 3.0]).unwrap();
+        buffer[1] = 5.0;
+        assert_eq!(&buffer[..], &[1.0, 5.0, 3.0]);
+    }
+
+    #[test]
+    fn test_pinned_zero_len() {
+        let buffer = PinnedBuffer::<f64>::alloc(0).unwrap();
+        assert!(buffer.is_empty());
+        assert_eq!(&buffer[..], &[] as &[f64]);
+    }
+
+    #[test]
+    fn test_pinned_zero_sized_type() {
+        let buffer = PinnedBuffer::<()>::from_slice(&[(); 4]).unwrap();
+        assert_eq!(buffer.len(), 4);
+        assert!(!buffer.as_pointer().is_null());
+    }
+
+    #[test]
+    fn test_pinned_with_flags() {
+        let buffer = PinnedBuffer::<u8>::alloc_with_flags(4096, HostMallocFlags::PORTABLE).unwrap();
+        let info = crate::PointerInfo::query(buffer.as_pointer()).unwrap();
+        assert_eq!(info.memory_type, crate::MemoryType::HostPinned);
+    }
+}
@@ -0,0 +1,326 @@
+#[allow(unused_imports)]
+use super::result::{HipError, HipResult, HipStatus};
+use crate::result::ResultExt;
+use crate::sys;
+use crate::{runtime_get_version, Device};
+
+/// First runtime major version that reports unknown host pointers as
+/// `hipMemoryTypeUnregistered` instead of failing with `InvalidValue`
+const UNREGISTERED_TYPE_MAJOR_VERSION: u64 = 6;
+
+/// Where the memory behind a pointer lives and how it was allocated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryType {
+    /// Device memory, e.g. from `hipMalloc` or a memory pool
+    Device,
+    /// Page-locked host memory allocated by `hipHostMalloc`
+    HostPinned,
+    /// Pageable host memory page-locked with `hipHostRegister`
+    HostRegistered,
+    /// Managed memory that migrates between host and device
+    Managed,
+    /// Memory backing a HIP array
+    Array,
+    /// Host memory HIP does not know about, e.g. a regular `Vec`
+    UnregisteredHost,
+}
+
+impl MemoryType {
+    /// Returns true if kernels on the owning device can access the memory directly.
+    pub fn is_device_accessible(&self) -> bool {
+        !matches!(self, Self::UnregisteredHost | Self::Array)
+    }
+
+    /// Returns true if the host can access the memory directly.
+    pub fn is_host_accessible(&self) -> bool {
+        matches!(
+            self,
+            Self::HostPinned | Self::HostRegistered | Self::Managed | Self::UnregisteredHost
+        )
+    }
+}
+
+/// The allocation a pointer belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocationRange {
+    /// Start address of the allocation
+    pub base: *mut std::ffi::c_void,
+    /// Size of the allocation in bytes
+    pub size: usize,
+}
+
+impl AllocationRange {
+    /// Returns the offset of `ptr` from the start of the allocation, or
+    /// `None` if it lies outside the allocation.
+    pub fn offset_of<T>(&self, ptr: *const T) -> Option<usize> {
+        let offset = (ptr as usize).checked_sub(self.base as usize)?;
+        (offset < self.size).then_some(offset)
+    }
+
+    /// Returns true if `bytes` bytes starting at `ptr` lie within the allocation.
+    pub fn contains<T>(&self, ptr: *const T, bytes: usize) -> bool {
+        let Some(offset) = (ptr as usize).checked_sub(self.base as usize) else {
+            return false;
+        };
+        offset
+            .checked_add(bytes)
+            .is_some_and(|end| end <= self.size)
+    }
+
+    /// Returns the number of bytes from `ptr` to the end of the allocation.
+    pub fn remaining<T>(&self, ptr: *const T) -> Option<usize> {
+        self.offset_of(ptr).map(|offset| self.size - offset)
+    }
+}
+
+/// Information about an arbitrary pointer, as reported by the HIP runtime.
+///
+/// Used to validate and classify pointers received from other libraries
+/// before wrapping them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointerInfo {
+    /// The kind of memory the pointer refers to
+    pub memory_type: MemoryType,
+    /// The device the memory belongs to, `None` for unregistered host memory
+    pub device: Option<Device>,
+    /// The address to use for the memory in device code
+    pub device_pointer: Option<*mut std::ffi::c_void>,
+    /// The address to use for the memory on the host
+    pub host_pointer: Option<*mut std::ffi::c_void>,
+    /// The flags the memory was allocated with
+    pub allocation_flags: u32,
+    /// The allocation the pointer belongs to, if HIP tracks it
+    pub allocation: Option<AllocationRange>,
+}
+
+impl PointerInfo {
+    /// Queries the HIP runtime for information about `ptr`.
+    ///
+    /// Pointers HIP does not know about are reported as
+    /// [`MemoryType::UnregisteredHost`] rather than as an error. Runtimes
+    /// before 6.0 cannot tell them apart from invalid pointers, so there
+    /// every pointer the runtime rejects is reported as unregistered.
+    ///
+    /// # Arguments
+    /// * `ptr` - Any host or device pointer
+    ///
+    /// # Returns
+    /// * `Ok(PointerInfo)` - The pointer attributes
+    /// * `Err(HipError)` - If the runtime rejects the pointer or could not be queried
+    pub fn query<T>(ptr: *const T) -> HipResult<Self> {
+        let ptr = ptr as *const std::ffi::c_void;
+        if ptr.is_null() {
+            return Err(HipError::from_status(HipStatus::InvalidValue));
+        }
+
+        let mut attributes: sys::hipPointerAttribute_t = unsafe { std::mem::zeroed() };
+        let code = unsafe { sys::hipPointerGetAttributes(&mut attributes, ptr) };
+        let result: HipResult<()> = ((), code).to_result();
+        match result {
+            Ok(()) => {}
+            // Older runtimes report unknown host pointers as invalid values
+            Err(e) if e.status == HipStatus::InvalidValue && Self::rejects_unregistered()? => {
+                return Ok(Self::unregistered())
+            }
+            Err(e) => return Err(e),
+        }
+
+        let memory_type = match attributes.type_ {
+            sys::hipMemoryType_hipMemoryTypeUnregistered => return Ok(Self::unregistered()),
+            _ if attributes.isManaged != 0 => MemoryType::Managed,
+            sys::hipMemoryType_hipMemoryTypeManaged | sys::hipMemoryType_hipMemoryTypeUnified => {
+                MemoryType::Managed
+            }
+            sys::hipMemoryType_hipMemoryTypeHost => Self::classify_host(attributes.hostPointer)?,
+            sys::hipMemoryType_hipMemoryTypeArray => MemoryType::Array,
+            _ => MemoryType::Device,
+        };
+
+        Ok(Self {
+            memory_type,
+            device: Some(Device::new(attributes.device)),
+            device_pointer: non_null(attributes.devicePointer),
+            host_pointer: non_null(attributes.hostPointer),
+            allocation_flags: attributes.allocationFlags,
+            allocation: Self::query_allocation(ptr),
+        })
+    }
+
+    /// Returns true if the memory can be accessed directly by kernels.
+    pub fn is_device_accessible(&self) -> bool {
+        self.memory_type.is_device_accessible()
+    }
+
+    /// Returns true if the memory can be accessed directly by the host.
+    pub fn is_host_accessible(&self) -> bool {
+        self.memory_type.is_host_accessible()
+    }
+
+    fn unregistered() -> Self {
+        Self {
+            memory_type: MemoryType::UnregisteredHost,
+            device: None,
+            device_pointer: None,
+            host_pointer: None,
+            allocation_flags: 0,
+            allocation: None,
+        }
+    }
+
+    /// Returns true if the runtime fails on unknown host pointers instead of
+    /// reporting them as unregistered.
+    fn rejects_unregistered() -> HipResult<bool> {
+        Ok(runtime_get_version()?.major < UNREGISTERED_TYPE_MAJOR_VERSION)
+    }
+
+    /// Distinguishes `hipHostMalloc` memory from `hipHostRegister` memory.
+    ///
+    /// Only `hipHostMalloc` allocations have host flags. Memory without them
+    /// is only classified as registered once the runtime maps it to a device
+    /// pointer.
+    fn classify_host(host_pointer: *mut std::ffi::c_void) -> HipResult<MemoryType> {
+        let mut flags: u32 = 0;
+        let code = unsafe { sys::hipHostGetFlags(&mut flags, host_pointer) };
+        let result: HipResult<()> = ((), code).to_result();
+        match result {
+            Ok(()) => Ok(MemoryType::HostPinned),
+            Err(e) if e.status == HipStatus::InvalidValue => {
+                let mut device_pointer = std::ptr::null_mut();
+                let code =
+                    unsafe { sys::hipHostGetDevicePointer(&mut device_pointer, host_pointer, 0) };
+                if code == 0 {
+                    Ok(MemoryType::HostRegistered)
+                } else {
+                    Err(e)
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn query_allocation(ptr: *const std::ffi::c_void) -> Option<AllocationRange> {
+        let mut base = std::ptr::null_mut();
+        let mut size: usize = 0;
+        let code = unsafe { sys::hipMemGetAddressRange(&mut base, &mut size, ptr as *mut _) };
+        (code == 0 && !base.is_null()).then_some(AllocationRange { base, size })
+    }
+}
+
+fn non_null(ptr: *mut std::ffi::c_void) -> Option<*mut std::ffi::c_void> {
+    (!ptr.is_null()).then_some(ptr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryPointer;
+
+    fn range(base: usize, size: usize) -> AllocationRange {
+        AllocationRange {
+            base: base as *mut std::ffi::c_void,
+            size,
+        }
+    }
+
+    #[test]
+    fn test_allocation_range_offset_of() {
+        let allocation = range(0x1000, 0x100);
+        assert_eq!(allocation.offset_of(0x1000 as *const u8), Some(0));
+        assert_eq!(allocation.offset_of(0x10ff as *const u8), Some(0xff));
+        assert_eq!(allocation.offset_of(0x1100 as *const u8), None);
+        assert_eq!(allocation.offset_of(0x0fff as *const u8), None);
+    }
+
+    #[test]
+    fn test_allocation_range_contains() {
+        let allocation = range(0x1000, 0x100);
+        assert!(allocation.contains(0x1000 as *const u8, 0x100));
+        assert!(allocation.contains(0x1080 as *const u8, 0x80));
+        assert!(!allocation.contains(0x1080 as *const u8, 0x81));
+        assert!(!allocation.contains(0x0f00 as *const u8, 0x10));
+        assert!(!allocation.contains(0x1000 as *const u8, usize::MAX));
+        assert_eq!(allocation.remaining(0x1040 as *const u8), Some(0xc0));
+    }
+
+    #[test]
+    fn test_memory_type_accessibility() {
+        assert!(MemoryType::Device.is_device_accessible());
+        assert!(!MemoryType::Device.is_host_accessible());
+        assert!(MemoryType::Managed.is_device_accessible());
+        assert!(MemoryType::Managed.is_host_accessible());
+        assert!(!MemoryType::UnregisteredHost.is_device_accessible());
+    }
+
+    #[test]
+    fn test_query_null_pointer() {
+        let result = PointerInfo::query(std::ptr::null::<u8>());
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+    }
+
+    #[test]
+    fn test_query_device_pointer() {
+        let len = 1024;
+        let ptr = MemoryPointer::<f32>::alloc(len).unwrap();
+
+        let info = PointerInfo::query(ptr.as_pointer()).unwrap();
+        assert_eq!(info.memory_type, MemoryType::Device);
+        assert_eq!(info.device, Some(Device::new(0)));
+
+        // An interior pointer resolves to the same allocation
+        let interior = unsafe { ptr.as_pointer().add(10) };
+        let info = PointerInfo::query(interior).unwrap();
+        let allocation = info.allocation.unwrap();
+        assert_eq!(allocation.base as usize, ptr.as_pointer() as usize);
+        assert!(allocation.size >= len * std::mem::size_of::<f32>());
+        assert_eq!(
+            allocation.offset_of(interior),
+            Some(10 * std::mem::size_of::<f32>())
+        );
+    }
+
+    #[test]
+    fn test_query_pinned_host_pointer() {
+        let mut ptr = std::ptr::null_mut();
+        assert_eq!(unsafe { sys::hipHostMalloc(&mut ptr, 4096, 0) }, 0);
+
+        let info = PointerInfo::query(ptr).unwrap();
+        assert_eq!(info.memory_type, MemoryType::HostPinned);
+        assert!(info.is_host_accessible());
+        assert!(info.device_pointer.is_some());
+
+        assert_eq!(unsafe { sys::hipHostFree(ptr) }, 0);
+    }
+
+    #[test]
+    fn test_query_registered_host_pointer() {
+        let mut host = vec![0u8; 4096];
+        let ptr = host.as_mut_ptr() as *mut std::ffi::c_void;
+        assert_eq!(unsafe { sys::hipHostRegister(ptr, host.len(), 0) }, 0);
+
+        let info = PointerInfo::query(ptr).unwrap();
+        assert_eq!(info.memory_type, MemoryType::HostRegistered);
+
+        assert_eq!(unsafe { sys::hipHostUnregister(ptr) }, 0);
+    }
+
+    #[test]
+    fn test_query_managed_pointer() {
+        let mut ptr = std::ptr::null_mut();
+        // hipMemAttachGlobal
+        assert_eq!(unsafe { sys::hipMallocManaged(&mut ptr, 4096, 0x1) }, 0);
+
+        let info = PointerInfo::query(ptr).unwrap();
+        assert_eq!(info.memory_type, MemoryType::Managed);
+
+        assert_eq!(unsafe { sys::hipFree(ptr) }, 0);
+    }
+
+    #[test]
+    fn test_query_unregistered_host_pointer() {
+        let host = [0u8; 64];
+        let info = PointerInfo::query(host.as_ptr()).unwrap();
+        assert_eq!(info.memory_type, MemoryType::UnregisteredHost);
+        assert_eq!(info.device, None);
+        assert_eq!(info.allocation, None);
+    }
+}
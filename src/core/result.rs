@@ -8,6 +8,7 @@ pub enum HipStatus {
     MemoryAllocation = 2,
     NotInitialized = 3,
     Deinitialized = 4,
+    InvalidConfiguration = 9,
     InvalidDevice = 101,
     FileNotFound = 301,
     NotReady = 600,
@@ -23,6 +24,7 @@ impl HipStatus {
             2 => HipStatus::MemoryAllocation,
             3 => HipStatus::NotInitialized,
             4 => HipStatus::Deinitialized,
+            9 => HipStatus::InvalidConfiguration,
             101 => HipStatus::InvalidDevice,
             301 => HipStatus::FileNotFound,
             600 => HipStatus::NotReady,
@@ -74,6 +76,7 @@ impl StatusCode for HipError {
             HipStatus::MemoryAllocation => "MemoryAllocation",
             HipStatus::NotInitialized => "NotInitialized",
             HipStatus::Deinitialized => "Deinitialized",
+            HipStatus::InvalidConfiguration => "InvalidConfiguration",
             HipStatus::InvalidDevice => "InvalidDevice",
             HipStatus::FileNotFound => "FileNotFound",
             HipStatus::NotReady => "NotReady",
@@ -104,6 +107,7 @@ mod tests {
         assert_eq!(HipStatus::from(2), HipStatus::MemoryAllocation);
         assert_eq!(HipStatus::from(3), HipStatus::NotInitialized);
         assert_eq!(HipStatus::from(4), HipStatus::Deinitialized);
+        assert_eq!(HipStatus::from(9), HipStatus::InvalidConfiguration);
         assert_eq!(HipStatus::from(101), HipStatus::InvalidDevice);
         assert_eq!(HipStatus::from(301), HipStatus::FileNotFound);
         assert_eq!(HipStatus::from(600), HipStatus::NotReady);
@@ -0,0 +1,839 @@
+use super::device_copy::DeviceCopy;
+use super::memory::{memory_copy, MemoryCopyKind, MemoryPointer};
+use super::pinned::PinnedBuffer;
+use super::result::{HipError, HipStatus};
+use super::slice::DeviceSlice;
+use crate::result::StatusCode;
+use memmap2::Mmap;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Headers larger than this are rejected, matching the reference implementation
+const MAX_HEADER_SIZE: usize = 100_000_000;
+
+/// Default size of the pinned buffer uploads are staged through
+const DEFAULT_STAGING_SIZE: usize = 16 << 20;
+
+/// Default alignment of tensors uploaded to an arena, in bytes
+pub const DEFAULT_ARENA_ALIGNMENT: usize = 256;
+
+/// Errors from loading .safetensors files.
+#[derive(Debug)]
+pub enum SafeTensorsError {
+    /// Reading the file failed
+    Io(io::Error),
+    /// Allocating or copying device memory failed
+    Hip(HipError),
+    /// The header is malformed or does not match the file
+    InvalidHeader(String),
+    /// A tensor entry in the header is invalid
+    InvalidTensor { name: String, reason: String },
+}
+
+pub type SafeTensorsResult<T> = std::result::Result<T, SafeTensorsError>;
+
+impl fmt::Display for SafeTensorsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SafeTensorsError::Io(error) => write!(f, "I/O error: {}", error),
+            SafeTensorsError::Hip(error) => StatusCode::fmt(error, f),
+            SafeTensorsError::InvalidHeader(reason) => {
+                write!(f, "invalid safetensors header: {}", reason)
+            }
+            SafeTensorsError::InvalidTensor { name, reason } => {
+                write!(f, "invalid tensor '{}': {}", name, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SafeTensorsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SafeTensorsError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SafeTensorsError {
+    fn from(error: io::Error) -> Self {
+        SafeTensorsError::Io(error)
+    }
+}
+
+impl From<HipError> for SafeTensorsError {
+    fn from(error: HipError) -> Self {
+        SafeTensorsError::Hip(error)
+    }
+}
+
+/// The element types of safetensors tensors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SafeTensorDtype {
+    Bool,
+    U8,
+    I8,
+    F8E5M2,
+    F8E4M3,
+    I16,
+    U16,
+    F16,
+    BF16,
+    I32,
+    U32,
+    F32,
+    F64,
+    I64,
+    U64,
+}
+
+impl SafeTensorDtype {
+    const ALL: [SafeTensorDtype; 15] = [
+        SafeTensorDtype::Bool,
+        SafeTensorDtype::U8,
+        SafeTensorDtype::I8,
+        SafeTensorDtype::F8E5M2,
+        SafeTensorDtype::F8E4M3,
+        SafeTensorDtype::I16,
+        SafeTensorDtype::U16,
+        SafeTensorDtype::F16,
+        SafeTensorDtype::BF16,
+        SafeTensorDtype::I32,
+        SafeTensorDtype::U32,
+        SafeTensorDtype::F32,
+        SafeTensorDtype::F64,
+        SafeTensorDtype::I64,
+        SafeTensorDtype::U64,
+    ];
+
+    /// Returns the name used in the header, e.g. `F32`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SafeTensorDtype::Bool => "BOOL",
+            SafeTensorDtype::U8 => "U8",
+            SafeTensorDtype::I8 => "I8",
+            SafeTensorDtype::F8E5M2 => "F8_E5M2",
+            SafeTensorDtype::F8E4M3 => "F8_E4M3",
+            SafeTensorDtype::I16 => "I16",
+            SafeTensorDtype::U16 => "U16",
+            SafeTensorDtype::F16 => "F16",
+            SafeTensorDtype::BF16 => "BF16",
+            SafeTensorDtype::I32 => "I32",
+            SafeTensorDtype::U32 => "U32",
+            SafeTensorDtype::F32 => "F32",
+            SafeTensorDtype::F64 => "F64",
+            SafeTensorDtype::I64 => "I64",
+            SafeTensorDtype::U64 => "U64",
+        }
+    }
+
+    /// Returns the size of one element in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            SafeTensorDtype::Bool
+            | SafeTensorDtype::U8
+            | SafeTensorDtype::I8
+            | SafeTensorDtype::F8E5M2
+            | SafeTensorDtype::F8E4M3 => 1,
+            SafeTensorDtype::I16
+            | SafeTensorDtype::U16
+            | SafeTensorDtype::F16
+            | SafeTensorDtype::BF16 => 2,
+            SafeTensorDtype::I32 | SafeTensorDtype::U32 | SafeTensorDtype::F32 => 4,
+            SafeTensorDtype::F64 | SafeTensorDtype::I64 | SafeTensorDtype::U64 => 8,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|dtype| dtype.name() == name)
+    }
+}
+
+/// A tensor entry in a safetensors header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TensorInfo {
+    pub dtype: SafeTensorDtype,
+    pub shape: Vec<usize>,
+    /// Start and end of the tensor data, relative to the end of the header
+    pub data_offsets: (usize, usize),
+}
+
+impl TensorInfo {
+    /// Returns the number of elements.
+    pub fn num_elements(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Returns the size of the tensor data in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.data_offsets.1 - self.data_offsets.0
+    }
+}
+
+/// The parsed and validated header of a .safetensors file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafeTensorsHeader {
+    /// The tensors, sorted by name
+    pub tensors: BTreeMap<String, TensorInfo>,
+    /// Free-form string metadata from `__metadata__`
+    pub metadata: BTreeMap<String, String>,
+    /// Offset of the tensor data from the start of the file
+    pub data_start: usize,
+}
+
+impl SafeTensorsHeader {
+    /// Parses and validates the header of a .safetensors file.
+    ///
+    /// Every tensor must have a known dtype, a byte size matching its shape,
+    /// and the tensors must exactly cover the data after the header, without
+    /// gaps or overlaps.
+    ///
+    /// # Arguments
+    /// * `file` - The complete file contents
+    ///
+    /// # Returns
+    /// * `Ok(SafeTensorsHeader)` - The validated header
+    /// * `Err(SafeTensorsError)` - If the header is malformed or inconsistent with the file
+    pub fn parse(file: &[u8]) -> SafeTensorsResult<Self> {
+        let invalid = |reason: &str| SafeTensorsError::InvalidHeader(reason.to_string());
+
+        let size_bytes: [u8; 8] = file
+            .get(..8)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| invalid("file is too short"))?;
+        let header_size = usize::try_from(u64::from_le_bytes(size_bytes))
+            .ok()
+            .filter(|&size| size <= MAX_HEADER_SIZE)
+            .ok_or_else(|| invalid("header is too large"))?;
+        let data_start = 8 + header_size;
+        let header = file
+            .get(8..data_start)
+            .ok_or_else(|| invalid("header extends past the end of the file"))?;
+
+        let header: Value = serde_json::from_slice(header)
+            .map_err(|error| SafeTensorsError::InvalidHeader(error.to_string()))?;
+        let entries = header
+            .as_object()
+            .ok_or_else(|| invalid("header is not a JSON object"))?;
+
+        let mut tensors = BTreeMap::new();
+        let mut metadata = BTreeMap::new();
+        for (name, value) in entries {
+            if name == "__metadata__" {
+                metadata = Self::parse_metadata(value)?;
+            } else {
+                tensors.insert(name.clone(), Self::parse_tensor(name, value)?);
+            }
+        }
+
+        let header = Self {
+            tensors,
+            metadata,
+            data_start,
+        };
+        header.validate_offsets(file.len() - data_start)?;
+        Ok(header)
+    }
+
+    fn parse_metadata(value: &Value) -> SafeTensorsResult<BTreeMap<String, String>> {
+        let invalid = || {
+            SafeTensorsError::InvalidHeader("__metadata__ must map strings to strings".to_string())
+        };
+        value
+            .as_object()
+            .ok_or_else(invalid)?
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), value.as_str().ok_or_else(invalid)?.to_string())))
+            .collect()
+    }
+
+    fn parse_tensor(name: &str, value: &Value) -> SafeTensorsResult<TensorInfo> {
+        let invalid = |reason: String| SafeTensorsError::InvalidTensor {
+            name: name.to_string(),
+            reason,
+        };
+        let field = |key: &str| {
+            value
+                .get(key)
+                .ok_or_else(|| invalid(format!("missing '{}'", key)))
+        };
+        let integers = |key: &str| -> SafeTensorsResult<Vec<usize>> {
+            field(key)?
+                .as_array()
+                .and_then(|values| {
+                    values
+                        .iter()
+                        .map(|value| value.as_u64().and_then(|v| usize::try_from(v).ok()))
+                        .collect()
+                })
+                .ok_or_else(|| invalid(format!("'{}' must be a list of integers", key)))
+        };
+
+        let dtype_name = field("dtype")?
+            .as_str()
+            .ok_or_else(|| invalid("'dtype' must be a string".to_string()))?;
+        let dtype = SafeTensorDtype::from_name(dtype_name)
+            .ok_or_else(|| invalid(format!("unsupported dtype '{}'", dtype_name)))?;
+        let shape = integers("shape")?;
+        let data_offsets = match integers("data_offsets")?[..] {
+            [start, end] if start <= end => (start, end),
+            _ => return Err(invalid("'data_offsets' must be [start, end]".to_string())),
+        };
+
+        let expected = shape
+            .iter()
+            .try_fold(dtype.size(), |product, &dim| product.checked_mul(dim))
+            .ok_or_else(|| invalid("shape is too large".to_string()))?;
+        if expected != data_offsets.1 - data_offsets.0 {
+            return Err(invalid(format!(
+                "shape {:?} of {} needs {} bytes but data_offsets cover {}",
+                shape,
+                dtype.name(),
+                expected,
+                data_offsets.1 - data_offsets.0
+            )));
+        }
+
+        Ok(TensorInfo {
+            dtype,
+            shape,
+            data_offsets,
+        })
+    }
+
+    /// Checks that the tensors tile the data section exactly.
+    fn validate_offsets(&self, data_len: usize) -> SafeTensorsResult<()> {
+        let mut by_offset: Vec<_> = self.tensors.iter().collect();
+        by_offset.sort_by_key(|(_, info)| info.data_offsets);
+
+        let mut expected_start = 0;
+        for (name, info) in by_offset {
+            let (start, end) = info.data_offsets;
+            if start != expected_start {
+                let reason = if start < expected_start {
+                    "overlaps another tensor"
+                } else {
+                    "leaves a gap before it"
+                };
+                return Err(SafeTensorsError::InvalidTensor {
+                    name: name.clone(),
+                    reason: reason.to_string(),
+                });
+            }
+            expected_start = end;
+        }
+
+        if expected_start != data_len {
+            return Err(SafeTensorsError::InvalidHeader(format!(
+                "tensors cover {} bytes but the file has {} bytes of data",
+                expected_start, data_len
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A memory-mapped .safetensors file, ready to be uploaded to the device.
+///
+/// Uploads copy each tensor from the mapping into a pinned staging buffer and
+/// from there to device memory, so the file is never read into pageable memory
+/// first.
+///
+/// # Examples
+/// ```no_run
+/// use hip_rs::{SafeTensors, DEFAULT_ARENA_ALIGNMENT};
+///
+/// let file = SafeTensors::open("model.safetensors").unwrap();
+/// for (name, info) in &file.header().tensors {
+///     println!("{}: {:?} {:?}", name, info.dtype, info.shape);
+/// }
+///
+/// let tensors = file.upload_to_arena(DEFAULT_ARENA_ALIGNMENT).unwrap();
+/// let weight = tensors.get("lm_head.weight").unwrap();
+/// let weight = weight.typed::<f32>().unwrap();
+/// ```
+pub struct SafeTensors {
+    mmap: Mmap,
+    header: SafeTensorsHeader,
+    staging_size: usize,
+}
+
+impl SafeTensors {
+    /// Memory-maps a .safetensors file and validates its header.
+    ///
+    /// The file must not be modified while it is open.
+    pub fn open<P: AsRef<Path>>(path: P) -> SafeTensorsResult<Self> {
+        let file = File::open(path)?;
+        // Safety: the mapping is only read, and modifying the file while it is
+        // mapped is documented as unsupported
+        let mmap = unsafe { Mmap::map(&file)? };
+        let header = SafeTensorsHeader::parse(&mmap)?;
+        Ok(Self {
+            mmap,
+            header,
+            staging_size: DEFAULT_STAGING_SIZE,
+        })
+    }
+
+    /// Sets the size of the pinned staging buffer, 16 MiB by default.
+    pub fn with_staging_size(mut self, bytes: usize) -> Self {
+        self.staging_size = bytes.max(1);
+        self
+    }
+
+    pub fn header(&self) -> &SafeTensorsHeader {
+        &self.header
+    }
+
+    /// Returns the raw bytes of a tensor in the mapped file.
+    pub fn tensor_data(&self, name: &str) -> Option<&[u8]> {
+        let (start, end) = self.header.tensors.get(name)?.data_offsets;
+        let data_start = self.header.data_start;
+        Some(&self.mmap[data_start + start..data_start + end])
+    }
+
+    /// Uploads every tensor to its own device buffer.
+    ///
+    /// # Returns
+    /// * `Ok(DeviceTensors)` - The uploaded tensors
+    /// * `Err(SafeTensorsError)` - If an allocation or copy fails
+    pub fn upload(&self) -> SafeTensorsResult<DeviceTensors> {
+        let mut buffers = Vec::with_capacity(self.header.tensors.len());
+        let mut tensors = BTreeMap::new();
+        for (name, info) in &self.header.tensors {
+            tensors.insert(
+                name.clone(),
+                TensorEntry {
+                    dtype: info.dtype,
+                    shape: info.shape.clone(),
+                    buffer: buffers.len(),
+                    offset: 0,
+                    len: info.size_in_bytes(),
+                },
+            );
+            buffers.push(MemoryPointer::<u8>::alloc(info.size_in_bytes())?);
+        }
+
+        let mut staging = self.staging_buffer()?;
+        for (name, entry) in &tensors {
+            self.stage(name, buffers[entry.buffer].as_pointer(), &mut staging)?;
+        }
+
+        Ok(DeviceTensors {
+            buffers,
+            tensors,
+            metadata: self.header.metadata.clone(),
+            arena: false,
+        })
+    }
+
+    /// Uploads all tensors into a single device allocation.
+    ///
+    /// # Arguments
+    /// * `alignment` - Alignment of each tensor in the arena in bytes, a power of two
+    ///
+    /// # Returns
+    /// * `Ok(DeviceTensors)` - The uploaded tensors
+    /// * `Err(SafeTensorsError)` - If the alignment is invalid or an allocation or copy fails
+    pub fn upload_to_arena(&self, alignment: usize) -> SafeTensorsResult<DeviceTensors> {
+        let (tensors, arena_size) = arena_layout(&self.header, alignment)?;
+        let arena = MemoryPointer::<u8>::alloc(arena_size)?;
+
+        let mut staging = self.staging_buffer()?;
+        for (name, entry) in &tensors {
+            let dst = arena.as_pointer().wrapping_add(entry.offset);
+            self.stage(name, dst, &mut staging)?;
+        }
+
+        Ok(DeviceTensors {
+            buffers: vec![arena],
+            tensors,
+            metadata: self.header.metadata.clone(),
+            arena: true,
+        })
+    }
+
+    fn staging_buffer(&self) -> SafeTensorsResult<PinnedBuffer<u8>> {
+        let largest = self
+            .header
+            .tensors
+            .values()
+            .map(TensorInfo::size_in_bytes)
+            .max()
+            .unwrap_or(0);
+        Ok(PinnedBuffer::alloc(self.staging_size.min(largest))?)
+    }
+
+    /// Copies a tensor to `dst` through the pinned staging buffer.
+    fn stage(
+        &self,
+        name: &str,
+        dst: *mut u8,
+        staging: &mut PinnedBuffer<u8>,
+    ) -> SafeTensorsResult<()> {
+        let data = self.tensor_data(name).unwrap_or_default();
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let chunk_size = staging.len();
+        for (index, chunk) in data.chunks(chunk_size).enumerate() {
+            staging[..chunk.len()].copy_from_slice(chunk);
+            unsafe {
+                memory_copy(
+                    dst.add(index * chunk_size) as *mut std::ffi::c_void,
+                    staging.as_pointer() as *const std::ffi::c_void,
+                    chunk.len(),
+                    MemoryCopyKind::HostToDevice,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Places the tensors in one arena, in the order of the file.
+fn arena_layout(
+    header: &SafeTensorsHeader,
+    alignment: usize,
+) -> SafeTensorsResult<(BTreeMap<String, TensorEntry>, usize)> {
+    if !alignment.is_power_of_two() {
+        return Err(HipError::from_status(HipStatus::InvalidValue).into());
+    }
+    let too_large = || SafeTensorsError::InvalidHeader("tensors are too large".to_string());
+
+    let mut by_offset: Vec<_> = header.tensors.iter().collect();
+    by_offset.sort_by_key(|(_, info)| info.data_offsets);
+
+    let mut tensors = BTreeMap::new();
+    let mut arena_size = 0usize;
+    for (name, info) in by_offset {
+        let offset = arena_size
+            .checked_next_multiple_of(alignment)
+            .ok_or_else(too_large)?;
+        arena_size = offset
+            .checked_add(info.size_in_bytes())
+            .ok_or_else(too_large)?;
+        tensors.insert(
+            name.clone(),
+            TensorEntry {
+                dtype: info.dtype,
+                shape: info.shape.clone(),
+                buffer: 0,
+                offset,
+                len: info.size_in_bytes(),
+            },
+        );
+    }
+    Ok((tensors, arena_size))
+}
+
+#[derive(Debug)]
+struct TensorEntry {
+    dtype: SafeTensorDtype,
+    shape: Vec<usize>,
+    buffer: usize,
+    offset: usize,
+    len: usize,
+}
+
+/// Tensors uploaded to device memory by [`SafeTensors`].
+#[derive(Debug)]
+pub struct DeviceTensors {
+    buffers: Vec<MemoryPointer<u8>>,
+    tensors: BTreeMap<String, TensorEntry>,
+    metadata: BTreeMap<String, String>,
+    arena: bool,
+}
+
+/// A tensor in device memory.
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceTensor<'a> {
+    pub dtype: SafeTensorDtype,
+    pub shape: &'a [usize],
+    /// The raw tensor data
+    pub data: DeviceSlice<'a, u8>,
+}
+
+impl<'a> DeviceTensor<'a> {
+    /// Views the data as elements of `T`.
+    ///
+    /// # Returns
+    /// * `Some(DeviceSlice<T>)` - If `T` has the size of the dtype and the data is aligned for it
+    /// * `None` - Otherwise
+    pub fn typed<T: DeviceCopy>(&self) -> Option<DeviceSlice<'a, T>> {
+        if std::mem::size_of::<T>() != self.dtype.size() {
+            return None;
+        }
+        self.data.cast()
+    }
+}
+
+impl DeviceTensors {
+    /// Returns the tensor called `name`.
+    pub fn get(&self, name: &str) -> Option<DeviceTensor<'_>> {
+        self.tensors.get(name).map(|entry| self.tensor(entry))
+    }
+
+    /// Iterates over the tensors, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, DeviceTensor<'_>)> {
+        self.tensors
+            .iter()
+            .map(|(name, entry)| (name.as_str(), self.tensor(entry)))
+    }
+
+    /// Returns the number of tensors.
+    pub fn len(&self) -> usize {
+        self.tensors.len()
+    }
+
+    /// Returns true if there are no tensors.
+    pub fn is_empty(&self) -> bool {
+        self.tensors.is_empty()
+    }
+
+    /// Returns the string metadata of the file.
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Returns the allocation holding all tensors, if they were uploaded to an arena.
+    pub fn arena(&self) -> Option<&MemoryPointer<u8>> {
+        if self.arena {
+            self.buffers.first()
+        } else {
+            None
+        }
+    }
+
+    fn tensor<'a>(&'a self, entry: &'a TensorEntry) -> DeviceTensor<'a> {
+        let data = self.buffers[entry.buffer]
+            .slice(entry.offset..entry.offset + entry.len)
+            .expect("tensor lies within its buffer");
+        DeviceTensor {
+            dtype: entry.dtype,
+            shape: &entry.shape,
+            data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::copy;
+
+    /// Builds a file with the given header and data section.
+    fn build_file(header: &str, data: &[u8]) -> Vec<u8> {
+        let mut file = (header.len() as u64).to_le_bytes().to_vec();
+        file.extend_from_slice(header.as_bytes());
+        file.extend_from_slice(data);
+        file
+    }
+
+    fn sample_file() -> Vec<u8> {
+        let header = r#"{
+            "__metadata__": {"format": "pt"},
+            "weight": {"dtype": "F32", "shape": [2, 2], "data_offsets": [0, 16]},
+            "bias": {"dtype": "F16", "shape": [3], "data_offsets": [16, 22]},
+            "step": {"dtype": "I64", "shape": [], "data_offsets": [22, 30]}
+        }"#;
+        let mut data = Vec::new();
+        for value in [1.0f32, 2.0, 3.0, 4.0] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&[0x00, 0x3c, 0x00, 0x40, 0x00, 0x42]);
+        data.extend_from_slice(&7i64.to_le_bytes());
+        build_file(header, &data)
+    }
+
+    fn parse_error(header: &str, data_len: usize) -> SafeTensorsError {
+        SafeTensorsHeader::parse(&build_file(header, &vec![0; data_len])).unwrap_err()
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let file = sample_file();
+        let header = SafeTensorsHeader::parse(&file).unwrap();
+
+        assert_eq!(header.metadata["format"], "pt");
+        assert_eq!(header.data_start, file.len() - 30);
+        assert_eq!(
+            header.tensors.keys().collect::<Vec<_>>(),
+            vec!["bias", "step", "weight"]
+        );
+
+        let bias = &header.tensors["bias"];
+        assert_eq!(bias.dtype, SafeTensorDtype::F16);
+        assert_eq!(bias.shape, vec![3]);
+        assert_eq!(bias.data_offsets, (16, 22));
+        assert_eq!(bias.size_in_bytes(), 6);
+
+        let step = &header.tensors["step"];
+        assert_eq!(step.num_elements(), 1);
+    }
+
+    #[test]
+    fn test_dtype_names() {
+        for dtype in SafeTensorDtype::ALL {
+            assert_eq!(SafeTensorDtype::from_name(dtype.name()), Some(dtype));
+        }
+        assert_eq!(SafeTensorDtype::BF16.size(), 2);
+        assert_eq!(SafeTensorDtype::F8E4M3.size(), 1);
+        assert_eq!(SafeTensorDtype::from_name("F128"), None);
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        let header = SafeTensorsHeader::parse(&build_file("{}", &[])).unwrap();
+        assert!(header.tensors.is_empty());
+        assert_eq!(header.data_start, 10);
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let cases = [
+            vec![1, 2, 3],
+            u64::MAX.to_le_bytes().to_vec(),
+            100u64.to_le_bytes().to_vec(),
+            build_file("[1, 2]", &[]),
+            build_file("{\"a\": ", &[]),
+            build_file(r#"{"__metadata__": {"version": 2}}"#, &[]),
+        ];
+
+        for file in cases {
+            assert!(matches!(
+                SafeTensorsHeader::parse(&file),
+                Err(SafeTensorsError::InvalidHeader(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_invalid_tensors() {
+        let cases = [
+            (
+                r#"{"t": {"dtype": "Q4", "shape": [1], "data_offsets": [0, 1]}}"#,
+                1,
+            ),
+            (r#"{"t": {"shape": [1], "data_offsets": [0, 1]}}"#, 1),
+            (
+                r#"{"t": {"dtype": "U8", "shape": [-1], "data_offsets": [0, 1]}}"#,
+                1,
+            ),
+            (
+                r#"{"t": {"dtype": "U8", "shape": [1], "data_offsets": [1, 0]}}"#,
+                1,
+            ),
+            (
+                r#"{"t": {"dtype": "U8", "shape": [1], "data_offsets": [0]}}"#,
+                1,
+            ),
+            (
+                r#"{"t": {"dtype": "F32", "shape": [2], "data_offsets": [0, 4]}}"#,
+                4,
+            ),
+            (
+                r#"{"t": {"dtype": "U8", "shape": [4294967296, 4294967296, 4294967296], "data_offsets": [0, 0]}}"#,
+                0,
+            ),
+        ];
+
+        for (header, data_len) in cases {
+            let error = parse_error(header, data_len);
+            assert!(
+                matches!(&error, SafeTensorsError::InvalidTensor { name, .. } if name == "t"),
+                "{}: {}",
+                header,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_offsets() {
+        let overlap = r#"{
+            "a": {"dtype": "U8", "shape": [4], "data_offsets": [0, 4]},
+            "b": {"dtype": "U8", "shape": [4], "data_offsets": [2, 6]}
+        }"#;
+        assert!(matches!(
+            parse_error(overlap, 6),
+            SafeTensorsError::InvalidTensor { name, .. } if name == "b"
+        ));
+
+        let gap = r#"{"a": {"dtype": "U8", "shape": [4], "data_offsets": [2, 6]}}"#;
+        assert!(matches!(
+            parse_error(gap, 6),
+            SafeTensorsError::InvalidTensor { name, .. } if name == "a"
+        ));
+
+        // Data section longer or shorter than the tensors
+        let single = r#"{"a": {"dtype": "U8", "shape": [4], "data_offsets": [0, 4]}}"#;
+        assert!(matches!(
+            parse_error(single, 5),
+            SafeTensorsError::InvalidHeader(_)
+        ));
+        assert!(matches!(
+            parse_error(single, 3),
+            SafeTensorsError::InvalidHeader(_)
+        ));
+    }
+
+    #[test]
+    fn test_arena_layout() {
+        let header = SafeTensorsHeader::parse(&sample_file()).unwrap();
+        let (tensors, size) = arena_layout(&header, 256).unwrap();
+
+        assert_eq!(tensors["weight"].offset, 0);
+        assert_eq!(tensors["bias"].offset, 256);
+        assert_eq!(tensors["step"].offset, 512);
+        assert_eq!(size, 520);
+
+        let (tensors, size) = arena_layout(&header, 1).unwrap();
+        assert_eq!(tensors["step"].offset, 22);
+        assert_eq!(size, 30);
+
+        assert!(arena_layout(&header, 3).is_err());
+    }
+
+    #[test]
+    fn test_upload() {
+        let path = std::env::temp_dir().join("hip_rs_test_safetensors_upload.safetensors");
+        std::fs::write(&path, sample_file()).unwrap();
+
+        // A tiny staging buffer exercises chunked uploads
+        let file = SafeTensors::open(&path).unwrap().with_staging_size(5);
+        assert_eq!(file.tensor_data("step").unwrap(), &7i64.to_le_bytes());
+
+        for tensors in [file.upload().unwrap(), file.upload_to_arena(256).unwrap()] {
+            assert_eq!(tensors.len(), 3);
+            assert_eq!(tensors.metadata()["format"], "pt");
+
+            let weight = tensors.get("weight").unwrap();
+            assert_eq!(weight.shape, &[2, 2]);
+            let mut values = vec![0.0f32; 4];
+            copy(&mut values[..], &weight.typed::<f32>().unwrap()).unwrap();
+            assert_eq!(values, vec![1.0, 2.0, 3.0, 4.0]);
+
+            let bias = tensors.get("bias").unwrap();
+            assert!(bias.typed::<f32>().is_none());
+            let mut bits = vec![0u16; 3];
+            copy(&mut bits[..], &bias.typed::<u16>().unwrap()).unwrap();
+            assert_eq!(bits, vec![0x3c00, 0x4000, 0x4200]);
+        }
+
+        let arena = file.upload_to_arena(512).unwrap();
+        let step = arena.get("step").unwrap();
+        assert_eq!(
+            step.data.as_pointer(),
+            arena.arena().unwrap().as_pointer().wrapping_add(1024)
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+use super::allocator::DeviceAllocator;
+use super::device_copy::DeviceCopy;
+use super::memory::MemoryPointer;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+
+/// A borrowed view of contiguous device memory, the device analogue of `&[T]`.
+///
+/// Slices are created from a [`MemoryPointer`] or from buffers that hand out
+/// views into their memory, and cannot outlive the memory they borrow.
+///
+/// # Examples
+/// ```
+/// use hip_rs::MemoryPointer;
+///
+/// let buffer = MemoryPointer::<f32>::alloc(1024).unwrap();
+/// let tail = buffer.slice(512..).unwrap();
+/// assert_eq!(tail.len(), 512);
+/// assert_eq!(tail.as_pointer(), buffer.as_pointer().wrapping_add(512));
+/// ```
+#[derive(Debug)]
+pub struct DeviceSlice<'a, T> {
+    pointer: *mut T,
+    len: usize,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<T> Clone for DeviceSlice<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for DeviceSlice<'_, T> {}
+
+impl<'a, T: DeviceCopy> DeviceSlice<'a, T> {
+    /// Creates a slice from a device pointer and a number of elements.
+    ///
+    /// # Safety
+    /// `pointer` must point to `len` elements of device memory that stays
+    /// allocated for `'a`.
+    pub unsafe fn from_raw_parts(pointer: *mut T, len: usize) -> Self {
+        Self {
+            pointer,
+            len,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the device pointer to the first element.
+    pub fn as_pointer(&self) -> *mut T {
+        self.pointer
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the slice holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the size of the slice in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.len * std::mem::size_of::<T>()
+    }
+
+    /// Returns a sub-slice, or `None` if the range is out of bounds.
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> Option<DeviceSlice<'a, T>> {
+        let (start, end) = resolve_range(range, self.len)?;
+        Some(unsafe { Self::from_raw_parts(self.pointer.wrapping_add(start), end - start) })
+    }
+
+    /// Reinterprets the slice as elements of another type.
+    ///
+    /// # Returns
+    /// * `Some(DeviceSlice<U>)` - If the pointer is aligned for `U` and the size is a multiple of it
+    /// * `None` - Otherwise
+    pub fn cast<U: DeviceCopy>(self) -> Option<DeviceSlice<'a, U>> {
+        let bytes = self.size_in_bytes();
+        let size = std::mem::size_of::<U>();
+        if size == 0
+            || !bytes.is_multiple_of(size)
+            || !(self.pointer as usize).is_multiple_of(std::mem::align_of::<U>())
+        {
+            return None;
+        }
+        Some(unsafe { DeviceSlice::from_raw_parts(self.pointer as *mut U, bytes / size) })
+    }
+}
+
+// A slice shares its memory like a &[T]
+unsafe impl<T: DeviceCopy + Sync> Send for DeviceSlice<'_, T> {}
+unsafe impl<T: DeviceCopy + Sync> Sync for DeviceSlice<'_, T> {}
+
+impl<T: DeviceCopy, A: DeviceAllocator> MemoryPointer<T, A> {
+    /// Returns a slice covering the whole buffer.
+    pub fn as_slice(&self) -> DeviceSlice<'_, T> {
+        unsafe { DeviceSlice::from_raw_parts(self.as_pointer(), self.size()) }
+    }
+
+    /// Returns a slice of part of the buffer, or `None` if the range is out of bounds.
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> Option<DeviceSlice<'_, T>> {
+        self.as_slice().slice(range)
+    }
+}
+
+fn resolve_range<R: RangeBounds<usize>>(range: R, len: usize) -> Option<(usize, usize)> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.checked_add(1)?,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1)?,
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    if start > end || end > len {
+        return None;
+    }
+    Some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_slice<T: DeviceCopy>(address: usize, len: usize) -> DeviceSlice<'static, T> {
+        unsafe { DeviceSlice::from_raw_parts(address as *mut T, len) }
+    }
+
+    #[test]
+    fn test_resolve_range() {
+        assert_eq!(resolve_range(.., 10), Some((0, 10)));
+        assert_eq!(resolve_range(2..5, 10), Some((2, 5)));
+        assert_eq!(resolve_range(2..=5, 10), Some((2, 6)));
+        assert_eq!(resolve_range(..10, 10), Some((0, 10)));
+        assert_eq!(resolve_range(10.., 10), Some((10, 10)));
+        assert_eq!(resolve_range(..11, 10), None);
+        assert_eq!(
+            resolve_range((Bound::Included(5), Bound::Excluded(2)), 10),
+            None
+        );
+        assert_eq!(resolve_range(..=usize::MAX, 10), None);
+    }
+
+    #[test]
+    fn test_sub_slice() {
+        let slice = fake_slice::<f32>(0x1000, 256);
+        let sub = slice.slice(16..48).unwrap();
+        assert_eq!(sub.as_pointer() as usize, 0x1000 + 16 * 4);
+        assert_eq!(sub.len(), 32);
+        assert_eq!(sub.size_in_bytes(), 128);
+        assert!(slice.slice(200..300).is_none());
+    }
+
+    #[test]
+    fn test_cast() {
+        let bytes = fake_slice::<u8>(0x1000, 64);
+        let floats = bytes.cast::<f32>().unwrap();
+        assert_eq!(floats.len(), 16);
+        assert_eq!(floats.cast::<f64>().unwrap().len(), 8);
+
+        // Size not a multiple of the element size
+        assert!(bytes.slice(..6).unwrap().cast::<f32>().is_none());
+        // Misaligned pointer
+        assert!(bytes.slice(1..5).unwrap().cast::<u16>().is_none());
+        assert!(bytes.cast::<()>().is_none());
+    }
+
+    #[test]
+    fn test_memory_pointer_slice() {
+        let buffer = MemoryPointer::<u32>::alloc(100).unwrap();
+        let slice = buffer.as_slice();
+        assert_eq!(slice.len(), 100);
+        assert_eq!(slice.as_pointer(), buffer.as_pointer());
+        assert_eq!(buffer.slice(90..).unwrap().len(), 10);
+        assert!(buffer.slice(..101).is_none());
+    }
+}
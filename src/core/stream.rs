@@ -2,6 +2,7 @@
 use super::result::{HipResult, HipStatus};
 use crate::result::ResultExt;
 use crate::sys;
+use crate::Event;
 
 /// A handle to a HIP stream that executes commands in order.
 #[derive(Debug)]
@@ -58,6 +59,32 @@ impl Stream {
             ((), code).to_result()
         }
     }
+
+    /// Blocks the host thread until all work in the stream has completed.
+    ///
+    /// # Returns
+    /// * `Ok(())` - All operations in the stream have completed
+    /// * `Err(HipError)` - If the stream handle is invalid or a previous operation failed
+    pub fn synchronize(&self) -> HipResult<()> {
+        unsafe {
+            let code = sys::hipStreamSynchronize(self.handle);
+            ((), code).to_result()
+        }
+    }
+
+    /// Makes all future work submitted to the stream wait until `event` completes.
+    ///
+    /// The host thread is not blocked. The event may have been recorded in
+    /// another stream, or opened from another process.
+    ///
+    /// # Arguments
+    /// * `event` - The [`Event`] to wait for
+    pub fn wait_event(&self, event: &Event) -> HipResult<()> {
+        unsafe {
+            let code = sys::hipStreamWaitEvent(self.handle, event.handle(), 0);
+            ((), code).to_result()
+        }
+    }
 }
 
 impl Drop for Stream {
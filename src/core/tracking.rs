@@ -0,0 +1,318 @@
+use crate::Device;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::Location;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+/// The kind of resource an [`AllocationRecord`] describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AllocationKind {
+    /// Device memory owned by a [`crate::MemoryPointer`]
+    DeviceMemory,
+    /// Page-locked host memory owned by a [`crate::PinnedBuffer`]
+    PinnedHost,
+    /// Managed memory owned by a [`crate::ManagedBuffer`]
+    ManagedMemory,
+    /// A memory pool owned by a [`crate::MemPool`]
+    MemPool,
+    /// A hipBLAS context owned by a [`crate::BlasHandle`]
+    BlasHandle,
+}
+
+/// A live allocation, as registered when it was made.
+#[derive(Debug, Clone)]
+pub struct AllocationRecord {
+    /// The kind of resource
+    pub kind: AllocationKind,
+    /// The device pointer or raw handle of the resource
+    pub address: usize,
+    /// Size in bytes, `0` for handles
+    pub size: usize,
+    /// The device the resource belongs to, if known
+    pub device: Option<Device>,
+    /// Where the allocation was requested
+    pub location: &'static Location<'static>,
+    /// Backtrace of the allocation, captured when `RUST_BACKTRACE` or
+    /// `RUST_LIB_BACKTRACE` enables it
+    pub backtrace: Option<Arc<Backtrace>>,
+}
+
+/// A resource released more often than it was allocated.
+#[derive(Debug, Clone)]
+pub struct DoubleFree {
+    /// The kind of resource
+    pub kind: AllocationKind,
+    /// The device pointer or raw handle that was released again
+    pub address: usize,
+    /// Where the last allocation at this address was requested, if any
+    pub allocated_at: Option<&'static Location<'static>>,
+}
+
+/// Leaks and double frees found by [`allocation_report`].
+#[derive(Debug, Clone, Default)]
+pub struct AllocationReport {
+    /// Allocations that are still alive
+    pub leaks: Vec<AllocationRecord>,
+    /// Releases of resources that were not alive
+    pub double_frees: Vec<DoubleFree>,
+}
+
+impl AllocationReport {
+    /// Returns true if there are no leaks and no double frees.
+    pub fn is_clean(&self) -> bool {
+        self.leaks.is_empty() && self.double_frees.is_empty()
+    }
+
+    /// Returns the total size of the leaked allocations in bytes.
+    pub fn leaked_bytes(&self) -> usize {
+        self.leaks.iter().map(|record| record.size).sum()
+    }
+}
+
+impl fmt::Display for AllocationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} live allocation(s) totalling {} bytes, {} double free(s)",
+            self.leaks.len(),
+            self.leaked_bytes(),
+            self.double_frees.len()
+        )?;
+        for record in &self.leaks {
+            write!(
+                f,
+                "  leak: {:?} {:#x}, {} bytes",
+                record.kind, record.address, record.size
+            )?;
+            if let Some(device) = record.device {
+                write!(f, " on device {}", device.id())?;
+            }
+            writeln!(f, ", allocated at {}", record.location)?;
+            if let Some(backtrace) = &record.backtrace {
+                writeln!(f, "{}", backtrace)?;
+            }
+        }
+        for double_free in &self.double_frees {
+            write!(
+                f,
+                "  double free: {:?} {:#x}",
+                double_free.kind, double_free.address
+            )?;
+            match double_free.allocated_at {
+                Some(location) => writeln!(f, ", allocated at {}", location)?,
+                None => writeln!(f, ", never allocated")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    live: HashMap<(AllocationKind, usize), AllocationRecord>,
+    /// Allocation sites of released resources, to explain double frees
+    released: HashMap<(AllocationKind, usize), &'static Location<'static>>,
+    double_frees: Vec<DoubleFree>,
+}
+
+fn registry() -> MutexGuard<'static, Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Registers a new allocation made at the caller's location.
+#[track_caller]
+pub(crate) fn register(kind: AllocationKind, address: usize, size: usize, device: Option<Device>) {
+    let backtrace = Backtrace::capture();
+    let record = AllocationRecord {
+        kind,
+        address,
+        size,
+        device,
+        location: Location::caller(),
+        backtrace: (backtrace.status() == BacktraceStatus::Captured).then(|| Arc::new(backtrace)),
+    };
+
+    let mut registry = registry();
+    registry.released.remove(&(kind, address));
+    registry.live.insert((kind, address), record);
+}
+
+/// Removes an allocation from the registry, recording a double free if it
+/// was not alive.
+pub(crate) fn unregister(kind: AllocationKind, address: usize) {
+    let mut registry = registry();
+    match registry.live.remove(&(kind, address)) {
+        Some(record) => {
+            registry.released.insert((kind, address), record.location);
+        }
+        None => {
+            let allocated_at = registry.released.get(&(kind, address)).copied();
+            log::error!("Double free of {:?} {:#x}", kind, address);
+            registry.double_frees.push(DoubleFree {
+                kind,
+                address,
+                allocated_at,
+            });
+        }
+    }
+}
+
+/// Returns all allocations that are currently alive.
+///
+/// # Examples
+/// ```
+/// use hip_rs::{live_allocations, MemoryPointer};
+///
+/// let ptr = MemoryPointer::<f32>::alloc(1024).unwrap();
+/// for record in live_allocations() {
+///     println!("{} bytes allocated at {}", record.size, record.location);
+/// }
+/// ```
+pub fn live_allocations() -> Vec<AllocationRecord> {
+    registry().live.values().cloned().collect()
+}
+
+/// Returns the allocations that are still alive and all double frees seen so far.
+pub fn allocation_report() -> AllocationReport {
+    let registry = registry();
+    AllocationReport {
+        leaks: registry.live.values().cloned().collect(),
+        double_frees: registry.double_frees.clone(),
+    }
+}
+
+/// Prints the [`allocation_report`] to stderr when the process exits, if it
+/// is not clean.
+///
+/// Calling this more than once installs the handler only once.
+#[cfg(unix)]
+pub fn report_at_exit() {
+    extern "C" fn print_report() {
+        let report = allocation_report();
+        if !report.is_clean() {
+            eprintln!("hip_rs allocation report: {}", report);
+        }
+    }
+
+    static INSTALLED: OnceLock<()> = OnceLock::new();
+    INSTALLED.get_or_init(|| unsafe {
+        if libc::atexit(print_report) != 0 {
+            log::error!("Failed to install the allocation report handler");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The registry is shared with every other test, so these tests use
+    // addresses no real allocation can have and only look at their own entries.
+    fn find(kind: AllocationKind, address: usize) -> Option<AllocationRecord> {
+        live_allocations()
+            .into_iter()
+            .find(|record| record.kind == kind && record.address == address)
+    }
+
+    #[test]
+    fn test_register_and_unregister() {
+        let address = 0x10;
+        register(
+            AllocationKind::DeviceMemory,
+            address,
+            256,
+            Some(Device::new(0)),
+        );
+
+        let record = find(AllocationKind::DeviceMemory, address).unwrap();
+        assert_eq!(record.size, 256);
+        assert_eq!(record.device, Some(Device::new(0)));
+        assert_eq!(record.location.file(), file!());
+
+        unregister(AllocationKind::DeviceMemory, address);
+        assert!(find(AllocationKind::DeviceMemory, address).is_none());
+    }
+
+    #[test]
+    fn test_double_free_is_reported() {
+        let address = 0x20;
+        register(AllocationKind::MemPool, address, 0, None);
+        unregister(AllocationKind::MemPool, address);
+        unregister(AllocationKind::MemPool, address);
+
+        let report = allocation_report();
+        let double_free = report
+            .double_frees
+            .iter()
+            .find(|double_free| double_free.address == address)
+            .unwrap();
+        assert_eq!(double_free.kind, AllocationKind::MemPool);
+        assert_eq!(double_free.allocated_at.unwrap().file(), file!());
+        assert!(report.to_string().contains("double free: MemPool 0x20"));
+    }
+
+    #[test]
+    fn test_kinds_are_tracked_separately() {
+        let address = 0x30;
+        register(AllocationKind::BlasHandle, address, 0, None);
+        register(AllocationKind::DeviceMemory, address, 64, None);
+
+        unregister(AllocationKind::BlasHandle, address);
+        assert!(find(AllocationKind::DeviceMemory, address).is_some());
+        unregister(AllocationKind::DeviceMemory, address);
+    }
+
+    #[test]
+    fn test_report_format() {
+        let report = AllocationReport {
+            leaks: vec![AllocationRecord {
+                kind: AllocationKind::DeviceMemory,
+                address: 0x1000,
+                size: 4096,
+                device: Some(Device::new(1)),
+                location: Location::caller(),
+                backtrace: None,
+            }],
+            double_frees: vec![],
+        };
+
+        assert!(!report.is_clean());
+        assert_eq!(report.leaked_bytes(), 4096);
+        let text = report.to_string();
+        assert!(text.starts_with("1 live allocation(s) totalling 4096 bytes, 0 double free(s)"));
+        assert!(text.contains("leak: DeviceMemory 0x1000, 4096 bytes on device 1"));
+    }
+
+    #[test]
+    fn test_memory_pointer_is_tracked() {
+        let ptr = crate::MemoryPointer::<f32>::alloc(1024).unwrap();
+        let address = ptr.as_pointer() as usize;
+
+        let record = find(AllocationKind::DeviceMemory, address).unwrap();
+        assert_eq!(record.size, 1024 * std::mem::size_of::<f32>());
+        assert_eq!(record.location.file(), file!());
+
+        drop(ptr);
+        assert!(find(AllocationKind::DeviceMemory, address).is_none());
+    }
+
+    #[test]
+    fn test_blas_handles_are_released() {
+        let mut addresses = Vec::new();
+        for _ in 0..100 {
+            let handle = crate::BlasHandle::new().unwrap();
+            addresses.push(handle.handle() as usize);
+            assert!(find(AllocationKind::BlasHandle, handle.handle() as usize).is_some());
+        }
+
+        let live = live_allocations();
+        assert!(!live.iter().any(|record| {
+            record.kind == AllocationKind::BlasHandle && addresses.contains(&record.address)
+        }));
+    }
+}
@@ -0,0 +1,610 @@
+use super::copy::{CopyDestination, CopySource, DeviceMemory};
+use super::device_copy::DeviceCopy;
+use super::event::Event;
+use super::flags::EventFlags;
+use super::memory::{memory_copy_async, MemoryCopyKind};
+use super::pinned::PinnedBuffer;
+use super::result::{HipError, HipResult, HipStatus};
+use super::stream::Stream;
+use std::collections::VecDeque;
+use std::ffi::c_void;
+
+/// Default size of each chunk and staging buffer
+const DEFAULT_CHUNK_SIZE: usize = 4 << 20;
+
+/// Default number of staging buffers in the ring
+const DEFAULT_NUM_BUFFERS: usize = 4;
+
+/// Chunks alternate between this many streams
+const NUM_STREAMS: usize = 2;
+
+/// Settings for a [`TransferEngine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferConfig {
+    chunk_size: usize,
+    num_buffers: usize,
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            num_buffers: DEFAULT_NUM_BUFFERS,
+        }
+    }
+}
+
+impl TransferConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the size of each chunk and staging buffer in bytes, 4 MiB by default.
+    ///
+    /// Larger chunks amortize per-copy overhead; smaller chunks start
+    /// overlapping sooner and report progress more often.
+    pub fn with_chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes;
+        self
+    }
+
+    /// Sets the number of pinned staging buffers in the ring, 4 by default.
+    ///
+    /// This bounds the number of chunks in flight at once.
+    pub fn with_num_buffers(mut self, num_buffers: usize) -> Self {
+        self.num_buffers = num_buffers;
+        self
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn num_buffers(&self) -> usize {
+        self.num_buffers
+    }
+}
+
+/// Progress of a transfer, reported each time a chunk completes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub bytes_done: usize,
+    pub total_bytes: usize,
+    pub chunks_done: usize,
+    pub total_chunks: usize,
+}
+
+impl TransferProgress {
+    /// Returns the completed fraction between 0 and 1.
+    pub fn fraction(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 1.0;
+        }
+        self.bytes_done as f64 / self.total_bytes as f64
+    }
+
+    /// Returns true once every chunk has completed.
+    pub fn is_complete(&self) -> bool {
+        self.chunks_done == self.total_chunks
+    }
+}
+
+/// Copies large pageable host buffers to and from the device in chunks.
+///
+/// `hipMemcpy` from pageable memory is staged by the runtime and blocks the
+/// host for the whole copy. The engine instead splits a copy into chunks and
+/// moves each through one of a ring of reusable pinned buffers, so the host
+/// copies one chunk into pinned memory while earlier chunks are copied by the
+/// device on two alternating streams.
+///
+/// # Examples
+/// ```
+/// use hip_rs::{MemoryPointer, TransferConfig, TransferEngine};
+///
+/// let config = TransferConfig::new().with_chunk_size(1 << 20);
+/// let mut engine = TransferEngine::new(config).unwrap();
+///
+/// let host = vec![1.5f32; 4 << 20];
+/// let mut device = MemoryPointer::<f32>::alloc(host.len()).unwrap();
+/// engine
+///     .upload_with_progress(&mut device, &host, |progress| {
+///         println!("{:.0}%", progress.fraction() * 100.0);
+///     })
+///     .unwrap();
+///
+/// let mut result = vec![0.0f32; host.len()];
+/// engine.download(&mut result, &device).unwrap();
+/// assert_eq!(host, result);
+/// ```
+#[derive(Debug)]
+pub struct TransferEngine {
+    config: TransferConfig,
+    staging: Vec<PinnedBuffer<u8>>,
+    events: Vec<Event>,
+    streams: [Stream; NUM_STREAMS],
+}
+
+impl TransferEngine {
+    /// Allocates the staging ring and creates the streams.
+    ///
+    /// # Returns
+    /// * `Ok(TransferEngine)` - The engine
+    /// * `Err(HipError)` - If the chunk size or number of buffers is zero, or an allocation fails
+    pub fn new(config: TransferConfig) -> HipResult<Self> {
+        if config.chunk_size == 0 || config.num_buffers == 0 {
+            return Err(HipError::from_status(HipStatus::InvalidValue));
+        }
+
+        let staging = (0..config.num_buffers)
+            .map(|_| PinnedBuffer::alloc(config.chunk_size))
+            .collect::<HipResult<_>>()?;
+        let events = (0..config.num_buffers)
+            .map(|_| Event::create_with_flags(EventFlags::DISABLE_TIMING))
+            .collect::<HipResult<_>>()?;
+        Ok(Self {
+            config,
+            staging,
+            events,
+            streams: [Stream::create()?, Stream::create()?],
+        })
+    }
+
+    pub fn config(&self) -> &TransferConfig {
+        &self.config
+    }
+
+    /// Copies `src` from host memory to the start of `dst` on the device.
+    ///
+    /// # Arguments
+    /// * `dst` - The device buffer, at least as long as `src`
+    /// * `src` - The host data, typically pageable
+    ///
+    /// # Returns
+    /// * `Ok(())` - Once the whole copy has completed
+    /// * `Err(HipError)` - If `dst` is too short or a copy failed
+    pub fn upload<T, D>(&mut self, dst: &mut D, src: &[T]) -> HipResult<()>
+    where
+        T: DeviceCopy,
+        D: CopyDestination<Elem = T, Location = DeviceMemory> + ?Sized,
+    {
+        self.upload_with_progress(dst, src, |_| {})
+    }
+
+    /// Like [`TransferEngine::upload`], calling `progress` as chunks complete.
+    pub fn upload_with_progress<T, D, F>(
+        &mut self,
+        dst: &mut D,
+        src: &[T],
+        progress: F,
+    ) -> HipResult<()>
+    where
+        T: DeviceCopy,
+        D: CopyDestination<Elem = T, Location = DeviceMemory> + ?Sized,
+        F: FnMut(TransferProgress),
+    {
+        if dst.copy_len() < src.len() {
+            return Err(HipError::from_status(HipStatus::InvalidValue));
+        }
+        let dst = dst.copy_mut_pointer() as *mut u8;
+        let schedule = self.schedule(std::mem::size_of_val(src));
+        let src = src.as_ptr() as *const u8;
+        let mut tracker = ProgressTracker::new(schedule, progress);
+        let Self {
+            staging,
+            events,
+            streams,
+            ..
+        } = &*self;
+
+        let result = run_pipeline(
+            schedule,
+            |chunk| unsafe {
+                // The slot is free: its previous chunk was finished before this one started
+                let pinned = staging[chunk.slot].as_pointer();
+                std::ptr::copy_nonoverlapping(src.add(chunk.offset), pinned, chunk.len);
+
+                let stream = &streams[chunk.stream];
+                memory_copy_async(
+                    dst.add(chunk.offset) as *mut c_void,
+                    pinned as *const c_void,
+                    chunk.len,
+                    MemoryCopyKind::HostToDevice,
+                    stream,
+                )?;
+                events[chunk.slot].record(stream)
+            },
+            |chunk| {
+                events[chunk.slot].synchronize()?;
+                tracker.complete(chunk);
+                Ok(())
+            },
+        );
+        self.settle(result)
+    }
+
+    /// Copies all elements of `src` from the device to the start of `dst` in host memory.
+    ///
+    /// # Arguments
+    /// * `dst` - The host buffer, typically pageable, at least as long as `src`
+    /// * `src` - The device buffer or slice
+    ///
+    /// # Returns
+    /// * `Ok(())` - Once the whole copy has completed
+    /// * `Err(HipError)` - If `dst` is too short or a copy failed
+    pub fn download<T, S>(&mut self, dst: &mut [T], src: &S) -> HipResult<()>
+    where
+        T: DeviceCopy,
+        S: CopySource<Elem = T, Location = DeviceMemory> + ?Sized,
+    {
+        self.download_with_progress(dst, src, |_| {})
+    }
+
+    /// Like [`TransferEngine::download`], calling `progress` as chunks complete.
+    pub fn download_with_progress<T, S, F>(
+        &mut self,
+        dst: &mut [T],
+        src: &S,
+        progress: F,
+    ) -> HipResult<()>
+    where
+        T: DeviceCopy,
+        S: CopySource<Elem = T, Location = DeviceMemory> + ?Sized,
+        F: FnMut(TransferProgress),
+    {
+        let len = src.copy_len();
+        if dst.len() < len {
+            return Err(HipError::from_status(HipStatus::InvalidValue));
+        }
+        let schedule = self.schedule(len * std::mem::size_of::<T>());
+        let dst = dst.as_mut_ptr() as *mut u8;
+        let src = src.copy_pointer() as *const u8;
+        let mut tracker = ProgressTracker::new(schedule, progress);
+        let Self {
+            staging,
+            events,
+            streams,
+            ..
+        } = &*self;
+
+        let result = run_pipeline(
+            schedule,
+            |chunk| unsafe {
+                let stream = &streams[chunk.stream];
+                memory_copy_async(
+                    staging[chunk.slot].as_pointer() as *mut c_void,
+                    src.add(chunk.offset) as *const c_void,
+                    chunk.len,
+                    MemoryCopyKind::DeviceToHost,
+                    stream,
+                )?;
+                events[chunk.slot].record(stream)
+            },
+            |chunk| unsafe {
+                events[chunk.slot].synchronize()?;
+                let pinned = staging[chunk.slot].as_pointer();
+                std::ptr::copy_nonoverlapping(pinned, dst.add(chunk.offset), chunk.len);
+                tracker.complete(chunk);
+                Ok(())
+            },
+        );
+        self.settle(result)
+    }
+
+    fn schedule(&self, total: usize) -> ChunkSchedule {
+        ChunkSchedule {
+            total,
+            chunk_size: self.config.chunk_size,
+            slots: self.config.num_buffers,
+        }
+    }
+
+    /// Waits for copies still in flight after a failure, so the caller's
+    /// buffers and the staging ring are no longer in use when we return.
+    fn settle(&self, result: HipResult<()>) -> HipResult<()> {
+        if result.is_err() {
+            for stream in &self.streams {
+                if let Err(error) = stream.synchronize() {
+                    log::error!("TransferEngine failed to drain stream: {:?}", error);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// One chunk of a transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Chunk {
+    index: usize,
+    /// Byte offset into the source and destination
+    offset: usize,
+    len: usize,
+    /// Staging buffer the chunk moves through
+    slot: usize,
+    /// Stream the device copy is enqueued on
+    stream: usize,
+}
+
+/// Splits a transfer into chunks, assigning staging buffers round-robin.
+#[derive(Debug, Clone, Copy)]
+struct ChunkSchedule {
+    total: usize,
+    chunk_size: usize,
+    slots: usize,
+}
+
+impl ChunkSchedule {
+    fn num_chunks(&self) -> usize {
+        self.total.div_ceil(self.chunk_size)
+    }
+
+    fn chunks(self) -> impl Iterator<Item = Chunk> {
+        (0..self.num_chunks()).map(move |index| {
+            let offset = index * self.chunk_size;
+            Chunk {
+                index,
+                offset,
+                len: self.chunk_size.min(self.total - offset),
+                slot: index % self.slots,
+                stream: index % NUM_STREAMS,
+            }
+        })
+    }
+}
+
+/// Runs `start` for each chunk in order and `finish` once it may be
+/// completed, keeping at most one chunk in flight per staging buffer.
+///
+/// A chunk is always finished before the next chunk using its staging
+/// buffer is started, and chunks are finished in order.
+fn run_pipeline<E>(
+    schedule: ChunkSchedule,
+    mut start: impl FnMut(&Chunk) -> Result<(), E>,
+    mut finish: impl FnMut(&Chunk) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut in_flight = VecDeque::with_capacity(schedule.slots);
+    for chunk in schedule.chunks() {
+        if in_flight.len() == schedule.slots {
+            if let Some(done) = in_flight.pop_front() {
+                finish(&done)?;
+            }
+        }
+        start(&chunk)?;
+        in_flight.push_back(chunk);
+    }
+    while let Some(done) = in_flight.pop_front() {
+        finish(&done)?;
+    }
+    Ok(())
+}
+
+struct ProgressTracker<F> {
+    progress: TransferProgress,
+    callback: F,
+}
+
+impl<F: FnMut(TransferProgress)> ProgressTracker<F> {
+    fn new(schedule: ChunkSchedule, callback: F) -> Self {
+        Self {
+            progress: TransferProgress {
+                bytes_done: 0,
+                total_bytes: schedule.total,
+                chunks_done: 0,
+                total_chunks: schedule.num_chunks(),
+            },
+            callback,
+        }
+    }
+
+    fn complete(&mut self, chunk: &Chunk) {
+        self.progress.bytes_done += chunk.len;
+        self.progress.chunks_done += 1;
+        (self.callback)(self.progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{copy, MemoryPointer};
+
+    fn schedule(total: usize, chunk_size: usize, slots: usize) -> ChunkSchedule {
+        ChunkSchedule {
+            total,
+            chunk_size,
+            slots,
+        }
+    }
+
+    #[test]
+    fn test_chunks() {
+        let chunks: Vec<_> = schedule(10, 4, 2).chunks().collect();
+        assert_eq!(
+            chunks,
+            vec![
+                Chunk {
+                    index: 0,
+                    offset: 0,
+                    len: 4,
+                    slot: 0,
+                    stream: 0
+                },
+                Chunk {
+                    index: 1,
+                    offset: 4,
+                    len: 4,
+                    slot: 1,
+                    stream: 1
+                },
+                Chunk {
+                    index: 2,
+                    offset: 8,
+                    len: 2,
+                    slot: 0,
+                    stream: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chunks_cover_transfer() {
+        for (total, chunk_size, slots) in [(0, 4, 2), (1, 4, 3), (4096, 4096, 1), (1000, 7, 5)] {
+            let schedule = schedule(total, chunk_size, slots);
+            let chunks: Vec<_> = schedule.chunks().collect();
+            assert_eq!(chunks.len(), schedule.num_chunks());
+
+            let mut offset = 0;
+            for chunk in &chunks {
+                assert_eq!(chunk.offset, offset);
+                assert!(chunk.len > 0 && chunk.len <= chunk_size);
+                assert!(chunk.slot < slots);
+                offset += chunk.len;
+            }
+            assert_eq!(offset, total);
+        }
+    }
+
+    #[test]
+    fn test_pipeline_reuses_slots_after_finish() {
+        #[derive(Debug, PartialEq)]
+        enum Step {
+            Start(usize),
+            Finish(usize),
+        }
+
+        let steps = std::cell::RefCell::new(Vec::new());
+        let busy = std::cell::RefCell::new(vec![false; 3]);
+        let result: Result<(), ()> = run_pipeline(
+            schedule(100, 10, 3),
+            |chunk| {
+                assert!(!busy.borrow()[chunk.slot], "slot reused while busy");
+                busy.borrow_mut()[chunk.slot] = true;
+                steps.borrow_mut().push(Step::Start(chunk.index));
+                Ok(())
+            },
+            |chunk| {
+                busy.borrow_mut()[chunk.slot] = false;
+                steps.borrow_mut().push(Step::Finish(chunk.index));
+                Ok(())
+            },
+        );
+        assert!(result.is_ok());
+
+        let steps = steps.into_inner();
+        assert_eq!(steps.len(), 20);
+        // Three chunks start before the first one has to finish
+        assert_eq!(
+            &steps[..5],
+            &[
+                Step::Start(0),
+                Step::Start(1),
+                Step::Start(2),
+                Step::Finish(0),
+                Step::Start(3)
+            ]
+        );
+        let finished: Vec<_> = steps
+            .iter()
+            .filter_map(|step| match step {
+                Step::Finish(index) => Some(*index),
+                Step::Start(_) => None,
+            })
+            .collect();
+        assert_eq!(finished, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_pipeline_stops_on_error() {
+        let mut started = Vec::new();
+        let result = run_pipeline(
+            schedule(100, 10, 2),
+            |chunk| {
+                started.push(chunk.index);
+                Ok(())
+            },
+            |chunk| {
+                if chunk.index == 1 {
+                    Err(chunk.index)
+                } else {
+                    Ok(())
+                }
+            },
+        );
+        assert_eq!(result, Err(1));
+        assert_eq!(started, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_progress() {
+        let schedule = schedule(10, 4, 2);
+        let mut reports = Vec::new();
+        let mut tracker = ProgressTracker::new(schedule, |progress| reports.push(progress));
+        for chunk in schedule.chunks() {
+            tracker.complete(&chunk);
+        }
+
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[0].bytes_done, 4);
+        assert_eq!(reports[0].fraction(), 0.4);
+        assert!(!reports[1].is_complete());
+        assert_eq!(
+            reports[2],
+            TransferProgress {
+                bytes_done: 10,
+                total_bytes: 10,
+                chunks_done: 3,
+                total_chunks: 3
+            }
+        );
+        assert!(reports[2].is_complete());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let result = TransferEngine::new(TransferConfig::new().with_chunk_size(0));
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+        let result = TransferEngine::new(TransferConfig::new().with_num_buffers(0));
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+    }
+
+    #[test]
+    fn test_upload_download() {
+        // Chunks that split elements exercise byte-granular staging
+        let config = TransferConfig::new()
+            .with_chunk_size(1000)
+            .with_num_buffers(3);
+        let mut engine = TransferEngine::new(config).unwrap();
+
+        let host: Vec<u32> = (0..10_007).collect();
+        let mut device = MemoryPointer::<u32>::alloc(host.len()).unwrap();
+        let mut reports = Vec::new();
+        engine
+            .upload_with_progress(&mut device, &host, |progress| reports.push(progress))
+            .unwrap();
+        assert_eq!(reports.len(), 41);
+        assert!(reports.last().unwrap().is_complete());
+
+        let mut check = vec![0u32; host.len()];
+        copy(&mut check[..], &device).unwrap();
+        assert_eq!(check, host);
+
+        let mut result = vec![0u32; host.len()];
+        engine.download(&mut result[..], &device).unwrap();
+        assert_eq!(result, host);
+
+        let mut tail = [0u32; 7];
+        engine
+            .download(&mut tail[..], &device.slice(10_000..).unwrap())
+            .unwrap();
+        assert_eq!(&tail[..], &host[10_000..]);
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        let mut engine = TransferEngine::new(TransferConfig::new()).unwrap();
+        let mut device = MemoryPointer::<u8>::alloc(4).unwrap();
+        let result = engine.upload(&mut device, &[0u8; 8]);
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+    }
+}
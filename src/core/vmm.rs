@@ -0,0 +1,541 @@
+#[allow(unused_imports)]
+use super::result::{HipError, HipResult, HipStatus};
+use super::{DeviceCopy, MemAccessFlags, MemAllocationHandleType, MemAllocationType, MemLocation};
+use crate::result::ResultExt;
+use crate::sys;
+use crate::Device;
+use std::marker::PhantomData;
+
+/// Rounds `value` up to the next multiple of `granularity`.
+fn round_up(value: usize, granularity: usize) -> usize {
+    if granularity == 0 {
+        return value;
+    }
+    value.div_ceil(granularity) * granularity
+}
+
+/// Converts `(Device, MemAccessFlags)` pairs to raw access descriptors.
+fn to_access_descs(access: &[(Device, MemAccessFlags)]) -> Vec<sys::hipMemAccessDesc> {
+    access
+        .iter()
+        .map(|(device, flags)| sys::hipMemAccessDesc {
+            location: MemLocation::device(*device).to_sys(),
+            flags: (*flags).into(),
+        })
+        .collect()
+}
+
+/// Which allocation granularity to query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocationGranularity {
+    /// The smallest granularity physical allocations and mappings must be aligned to
+    Minimum,
+    /// The granularity recommended for best performance
+    Recommended,
+}
+
+impl From<AllocationGranularity> for u32 {
+    fn from(granularity: AllocationGranularity) -> Self {
+        match granularity {
+            AllocationGranularity::Minimum => {
+                sys::hipMemAllocationGranularity_flags_hipMemAllocationGranularityMinimum
+            }
+            AllocationGranularity::Recommended => {
+                sys::hipMemAllocationGranularity_flags_hipMemAllocationGranularityRecommended
+            }
+        }
+    }
+}
+
+/// Properties of a physical allocation created with [`PhysicalAllocation::create`].
+#[derive(Debug, Clone)]
+pub struct MemAllocationProps {
+    /// Allocation type (must be hipMemAllocationTypePinned)
+    pub alloc_type: MemAllocationType,
+    /// Handle type the allocation can be exported as
+    pub handle_types: MemAllocationHandleType,
+    /// Location where the allocation should reside
+    pub location: MemLocation,
+}
+
+impl Default for MemAllocationProps {
+    fn default() -> Self {
+        Self::device(Device::new(0))
+    }
+}
+
+impl MemAllocationProps {
+    /// Creates properties for a pinned, non-exportable allocation on `device`.
+    pub fn device(device: Device) -> Self {
+        Self {
+            alloc_type: MemAllocationType::Pinned,
+            handle_types: MemAllocationHandleType::None,
+            location: MemLocation::device(device),
+        }
+    }
+
+    pub fn with_handle_types(mut self, handle_types: MemAllocationHandleType) -> Self {
+        self.handle_types = handle_types;
+        self
+    }
+
+    /// Convert to the raw HIP hipMemAllocationProp structure
+    pub(crate) fn to_sys_props(&self) -> sys::hipMemAllocationProp {
+        let mut props: sys::hipMemAllocationProp = unsafe { std::mem::zeroed() };
+        props.type_ = self.alloc_type as u32;
+        props.requestedHandleType = self.handle_types as u32;
+        props.location = self.location.to_sys();
+        props
+    }
+
+    /// Gets the allocation granularity for these properties.
+    ///
+    /// Sizes of physical allocations, and sizes and offsets of mappings,
+    /// must be multiples of the minimum granularity.
+    ///
+    /// # Arguments
+    /// * `granularity` - Whether to query the minimum or recommended granularity
+    ///
+    /// # Returns
+    /// * `Ok(usize)` - The granularity in bytes
+    /// * `Err(HipError)` - If the properties are invalid or VMM is not supported
+    pub fn granularity(&self, granularity: AllocationGranularity) -> HipResult<usize> {
+        let props = self.to_sys_props();
+        let mut value: usize = 0;
+        unsafe {
+            let code = sys::hipMemGetAllocationGranularity(&mut value, &props, granularity.into());
+            (value, code).to_result()
+        }
+    }
+}
+
+/// A reserved range of virtual device addresses without backing memory.
+///
+/// Physical memory is attached with [`Mapping::map`]. The range is freed when dropped,
+/// so all mappings into it must be dropped first, which the borrow checker enforces.
+#[derive(Debug)]
+pub struct VirtualAddressRange {
+    pointer: *mut std::ffi::c_void,
+    size: usize,
+}
+
+impl VirtualAddressRange {
+    /// Reserves `size` bytes of virtual address space.
+    ///
+    /// # Arguments
+    /// * `size` - Size of the range in bytes, a multiple of the allocation granularity
+    /// * `alignment` - Alignment of the start address, `0` for the default
+    ///
+    /// # Returns
+    /// * `Ok(VirtualAddressRange)` - The reserved range
+    /// * `Err(HipError)` - If the size is invalid or the address space is exhausted
+    pub fn reserve(size: usize, alignment: usize) -> HipResult<Self> {
+        let mut pointer = std::ptr::null_mut();
+        unsafe {
+            let code =
+                sys::hipMemAddressReserve(&mut pointer, size, alignment, std::ptr::null_mut(), 0);
+            (Self { pointer, size }, code).to_result()
+        }
+    }
+
+    /// Returns the start address of the range.
+    pub fn as_pointer(&self) -> *mut std::ffi::c_void {
+        self.pointer
+    }
+
+    /// Returns the size of the range in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+impl Drop for VirtualAddressRange {
+    fn drop(&mut self) {
+        if !self.pointer.is_null() {
+            unsafe {
+                let code = sys::hipMemAddressFree(self.pointer, self.size);
+                if code != 0 {
+                    log::error!("Failed to free virtual address range: {}", code);
+                }
+            }
+        }
+    }
+}
+
+unsafe impl Send for VirtualAddressRange {}
+unsafe impl Sync for VirtualAddressRange {}
+
+/// Physical device memory that is not yet addressable.
+///
+/// The memory is made accessible by mapping it into a [`VirtualAddressRange`].
+/// Dropping the allocation releases the handle, the memory itself is freed
+/// once it is no longer mapped anywhere.
+#[derive(Debug)]
+pub struct PhysicalAllocation {
+    handle: sys::hipMemGenericAllocationHandle_t,
+    size: usize,
+}
+
+impl PhysicalAllocation {
+    /// Creates a physical allocation of `size` bytes.
+    ///
+    /// # Arguments
+    /// * `size` - Size in bytes, a multiple of the minimum granularity of `props`
+    /// * `props` - Where and how the memory is allocated
+    ///
+    /// # Returns
+    /// * `Ok(PhysicalAllocation)` - The allocation
+    /// * `Err(HipError)` - If the size is not aligned or the device is out of memory
+    pub fn create(size: usize, props: &MemAllocationProps) -> HipResult<Self> {
+        let sys_props = props.to_sys_props();
+        let mut handle = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hipMemCreate(&mut handle, size, &sys_props, 0);
+            (Self { handle, size }, code).to_result()
+        }
+    }
+
+    /// Returns the raw allocation handle.
+    pub fn handle(&self) -> sys::hipMemGenericAllocationHandle_t {
+        self.handle
+    }
+
+    /// Returns the size of the allocation in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+impl Drop for PhysicalAllocation {
+    fn drop(&mut self) {
+        if !self.handle.is_null() {
+            unsafe {
+                let code = sys::hipMemRelease(self.handle);
+                if code != 0 {
+                    log::error!("Failed to release physical allocation: {}", code);
+                }
+            }
+        }
+    }
+}
+
+unsafe impl Send for PhysicalAllocation {}
+unsafe impl Sync for PhysicalAllocation {}
+
+/// Physical memory mapped into part of a [`VirtualAddressRange`].
+///
+/// The memory is unmapped when dropped. Mapped memory is not accessible
+/// until access is granted with [`Mapping::set_access`].
+#[derive(Debug)]
+pub struct Mapping<'a> {
+    pointer: *mut std::ffi::c_void,
+    size: usize,
+    _range: PhantomData<&'a VirtualAddressRange>,
+}
+
+impl<'a> Mapping<'a> {
+    /// Maps all of `allocation` into `range`, starting `offset` bytes into the range.
+    ///
+    /// # Arguments
+    /// * `range` - The reserved address range to map into
+    /// * `offset` - Offset into `range` in bytes, a multiple of the granularity
+    /// * `allocation` - The physical memory to map
+    ///
+    /// # Returns
+    /// * `Ok(Mapping)` - The mapping, valid while `range` is alive
+    /// * `Err(HipError)` - `InvalidValue` if the allocation does not fit in the range,
+    ///   or the error returned by HIP
+    pub fn map(
+        range: &'a VirtualAddressRange,
+        offset: usize,
+        allocation: &PhysicalAllocation,
+    ) -> HipResult<Self> {
+        let fits = offset
+            .checked_add(allocation.size)
+            .is_some_and(|end| end <= range.size);
+        if !fits {
+            return Err(HipError::from_status(HipStatus::InvalidValue));
+        }
+
+        let pointer = unsafe { (range.pointer as *mut u8).add(offset) } as *mut std::ffi::c_void;
+        unsafe {
+            let code = sys::hipMemMap(pointer, allocation.size, 0, allocation.handle, 0);
+            ((), code).to_result()?;
+        }
+
+        Ok(Self {
+            pointer,
+            size: allocation.size,
+            _range: PhantomData,
+        })
+    }
+
+    /// Returns the start address of the mapped memory.
+    pub fn as_pointer(&self) -> *mut std::ffi::c_void {
+        self.pointer
+    }
+
+    /// Returns the size of the mapping in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Sets which devices can access the mapped memory.
+    ///
+    /// # Arguments
+    /// * `access` - Pairs of device and the access it should have
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the access permissions were updated
+    /// * `Err(HipError)` - If a device is invalid or peer access is not supported
+    pub fn set_access(&self, access: &[(Device, MemAccessFlags)]) -> HipResult<()> {
+        let descs = to_access_descs(access);
+        unsafe {
+            let code = sys::hipMemSetAccess(self.pointer, self.size, descs.as_ptr(), descs.len());
+            ((), code).to_result()
+        }
+    }
+}
+
+impl Drop for Mapping<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            let code = sys::hipMemUnmap(self.pointer, self.size);
+            if code != 0 {
+                log::error!("Failed to unmap memory: {}", code);
+            }
+        }
+    }
+}
+
+/// A device buffer that grows in place without copying.
+///
+/// The buffer reserves address space for its maximum capacity up front and
+/// maps physical memory into it as it grows, so the device pointer never
+/// changes and existing contents are never moved.
+#[derive(Debug)]
+pub struct GrowableDeviceBuffer<T> {
+    /// Physical memory backing the buffer, mapped back to back from the start of `range`
+    allocations: Vec<PhysicalAllocation>,
+    /// Number of bytes mapped from the start of `range`
+    mapped: usize,
+    granularity: usize,
+    device: Device,
+    range: VirtualAddressRange,
+    _type: PhantomData<T>,
+}
+
+impl<T: DeviceCopy> GrowableDeviceBuffer<T> {
+    /// Creates an empty buffer on `device` that can grow up to `max_capacity` elements.
+    ///
+    /// Only address space is reserved, no physical memory is allocated.
+    ///
+    /// # Arguments
+    /// * `device` - The device the memory resides on
+    /// * `max_capacity` - The maximum number of elements the buffer can hold
+    ///
+    /// # Returns
+    /// * `Ok(GrowableDeviceBuffer<T>)` - The empty buffer
+    /// * `Err(HipError)` - If VMM is not supported or the address space is exhausted
+    pub fn new(device: Device, max_capacity: usize) -> HipResult<Self> {
+        let props = MemAllocationProps::device(device);
+        let granularity = props.granularity(AllocationGranularity::Recommended)?;
+        let max_bytes = max_capacity
+            .checked_mul(std::mem::size_of::<T>())
+            .ok_or(HipError::from_status(HipStatus::InvalidValue))?;
+        let range = VirtualAddressRange::reserve(round_up(max_bytes.max(1), granularity), 0)?;
+
+        Ok(Self {
+            allocations: Vec::new(),
+            mapped: 0,
+            granularity,
+            device,
+            range,
+            _type: PhantomData,
+        })
+    }
+
+    /// Returns the stable device pointer to the start of the buffer.
+    pub fn as_pointer(&self) -> *mut T {
+        self.range.as_pointer() as *mut T
+    }
+
+    /// Returns the number of elements backed by physical memory.
+    pub fn capacity(&self) -> usize {
+        match std::mem::size_of::<T>() {
+            0 => usize::MAX,
+            size => self.mapped / size,
+        }
+    }
+
+    /// Returns the number of elements the buffer can grow to.
+    pub fn max_capacity(&self) -> usize {
+        match std::mem::size_of::<T>() {
+            0 => usize::MAX,
+            size => self.range.size() / size,
+        }
+    }
+
+    /// Grows the buffer so it holds at least `capacity` elements.
+    ///
+    /// Existing contents and the device pointer are unchanged. The capacity is
+    /// rounded up to the allocation granularity. Does nothing if the buffer is
+    /// already large enough.
+    ///
+    /// # Arguments
+    /// * `capacity` - The required number of elements
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the buffer holds at least `capacity` elements
+    /// * `Err(HipError)` - `InvalidValue` if `capacity` exceeds the maximum capacity,
+    ///   or the error returned by HIP when allocating or mapping memory
+    pub fn grow_to(&mut self, capacity: usize) -> HipResult<()> {
+        let bytes = capacity
+            .checked_mul(std::mem::size_of::<T>())
+            .ok_or(HipError::from_status(HipStatus::InvalidValue))?;
+        if bytes <= self.mapped {
+            return Ok(());
+        }
+
+        let target = round_up(bytes, self.granularity);
+        if target > self.range.size() {
+            return Err(HipError::from_status(HipStatus::InvalidValue));
+        }
+
+        let props = MemAllocationProps::device(self.device);
+        let allocation = PhysicalAllocation::create(target - self.mapped, &props)?;
+        let mapping = Mapping::map(&self.range, self.mapped, &allocation)?;
+        mapping.set_access(&[(self.device, MemAccessFlags::ReadWrite)])?;
+
+        // The mapping is released in `Drop`, together with all earlier ones
+        std::mem::forget(mapping);
+        self.mapped = target;
+        self.allocations.push(allocation);
+        Ok(())
+    }
+}
+
+impl<T> Drop for GrowableDeviceBuffer<T> {
+    fn drop(&mut self) {
+        // Unmap before the physical allocations are released and the range is freed
+        let mut offset = 0;
+        for allocation in &self.allocations {
+            unsafe {
+                let pointer = (self.range.as_pointer() as *mut u8).add(offset);
+                let code = sys::hipMemUnmap(pointer as *mut std::ffi::c_void, allocation.size());
+                if code != 0 {
+                    log::error!("GrowableDeviceBuffer failed to unmap memory: {}", code);
+                }
+            }
+            offset += allocation.size();
+        }
+    }
+}
+
+unsafe impl<T: Send> Send for GrowableDeviceBuffer<T> {}
+unsafe impl<T: Sync> Sync for GrowableDeviceBuffer<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryCopyKind;
+
+    #[test]
+    fn test_round_up() {
+        assert_eq!(round_up(0, 4096), 0);
+        assert_eq!(round_up(1, 4096), 4096);
+        assert_eq!(round_up(4096, 4096), 4096);
+        assert_eq!(round_up(4097, 4096), 8192);
+        assert_eq!(round_up(123, 0), 123);
+    }
+
+    #[test]
+    fn test_allocation_granularity() {
+        let props = MemAllocationProps::device(Device::new(0));
+        let minimum = props.granularity(AllocationGranularity::Minimum).unwrap();
+        let recommended = props
+            .granularity(AllocationGranularity::Recommended)
+            .unwrap();
+        assert!(minimum > 0);
+        assert!(recommended >= minimum);
+    }
+
+    #[test]
+    fn test_reserve_create_map() {
+        let device = Device::new(0);
+        let props = MemAllocationProps::device(device);
+        let granularity = props.granularity(AllocationGranularity::Minimum).unwrap();
+
+        let range = VirtualAddressRange::reserve(2 * granularity, 0).unwrap();
+        assert!(!range.as_pointer().is_null());
+        assert_eq!(range.size(), 2 * granularity);
+
+        let allocation = PhysicalAllocation::create(granularity, &props).unwrap();
+        let mapping = Mapping::map(&range, granularity, &allocation).unwrap();
+        mapping
+            .set_access(&[(device, MemAccessFlags::ReadWrite)])
+            .unwrap();
+        assert_eq!(
+            mapping.as_pointer() as usize,
+            range.as_pointer() as usize + granularity
+        );
+
+        let result = unsafe { sys::hipMemset(mapping.as_pointer(), 0, mapping.size()) };
+        assert_eq!(result, 0);
+    }
+
+    #[test]
+    fn test_map_out_of_range() {
+        let props = MemAllocationProps::device(Device::new(0));
+        let granularity = props.granularity(AllocationGranularity::Minimum).unwrap();
+
+        let range = VirtualAddressRange::reserve(granularity, 0).unwrap();
+        let allocation = PhysicalAllocation::create(granularity, &props).unwrap();
+        let result = Mapping::map(&range, granularity, &allocation);
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+    }
+
+    #[test]
+    fn test_growable_buffer_grows_in_place() {
+        let mut buffer = GrowableDeviceBuffer::<u32>::new(Device::new(0), 1 << 24).unwrap();
+        assert_eq!(buffer.capacity(), 0);
+        assert!(buffer.max_capacity() >= 1 << 24);
+        let pointer = buffer.as_pointer();
+
+        buffer.grow_to(1024).unwrap();
+        let first_capacity = buffer.capacity();
+        assert!(first_capacity >= 1024);
+
+        // Fill the first chunk so we can check it survives growing
+        let host: Vec<u32> = (0..1024).collect();
+        let code = unsafe {
+            sys::hipMemcpy(
+                buffer.as_pointer() as *mut std::ffi::c_void,
+                host.as_ptr() as *const std::ffi::c_void,
+                host.len() * std::mem::size_of::<u32>(),
+                MemoryCopyKind::HostToDevice.into(),
+            )
+        };
+        assert_eq!(code, 0);
+
+        buffer.grow_to(first_capacity * 4).unwrap();
+        assert!(buffer.capacity() >= first_capacity * 4);
+        assert_eq!(buffer.as_pointer(), pointer);
+
+        let mut readback = vec![0u32; 1024];
+        let code = unsafe {
+            sys::hipMemcpy(
+                readback.as_mut_ptr() as *mut std::ffi::c_void,
+                buffer.as_pointer() as *const std::ffi::c_void,
+                readback.len() * std::mem::size_of::<u32>(),
+                MemoryCopyKind::DeviceToHost.into(),
+            )
+        };
+        assert_eq!(code, 0);
+        assert_eq!(readback, host);
+
+        // Growing within the capacity is a no-op
+        buffer.grow_to(10).unwrap();
+        assert!(buffer.grow_to(buffer.max_capacity() + 1).is_err());
+    }
+}
@@ -1,10 +1,10 @@
-use super::{BlasHandle, BlasResult, Operation};
+use super::{BlasError, BlasHandle, BlasResult, BlasStatus, DeviceMatrix, Operation};
 use crate::result::ResultExt;
 use crate::Complex32;
-use crate::{sys, MemoryPointer};
+use crate::{sys, DeviceAllocator, DeviceCopy, MemoryPointer};
 
 /// Trait for types supported by GEMM operations
-pub trait GemmDatatype {
+pub trait GemmDatatype: DeviceCopy {
     /// Calls the appropriate HIPBLAS GEMM function for this datatype
     unsafe fn hipblas_gemm(
         handle: sys::hipblasHandle_t,
@@ -177,12 +177,12 @@ pub fn gemm<T: GemmDatatype>(
     n: i32,
     k: i32,
     alpha: &T,
-    a: &MemoryPointer<T>,
+    a: &MemoryPointer<T, impl DeviceAllocator>,
     lda: i32,
-    b: &MemoryPointer<T>,
+    b: &MemoryPointer<T, impl DeviceAllocator>,
     ldb: i32,
     beta: &T,
-    c: &mut MemoryPointer<T>,
+    c: &mut MemoryPointer<T, impl DeviceAllocator>,
     ldc: i32,
 ) -> BlasResult<()> {
     unsafe {
@@ -206,6 +206,84 @@ pub fn gemm<T: GemmDatatype>(
     }
 }
 
+/// Performs C = alpha * op(A) * op(B) + beta * C on [`DeviceMatrix`] operands
+///
+/// `m`, `n`, `k` and the leading dimensions are taken from the matrices:
+/// op(A) must be m x k, op(B) k x n and C m x n.
+///
+/// # Arguments
+/// * `handle` - HIPBLAS library handle
+/// * `trans_a` - How to transform matrix A
+/// * `trans_b` - How to transform matrix B
+/// * `alpha` - Scalar multiplier for AB
+/// * `a` - Input matrix A
+/// * `b` - Input matrix B
+/// * `beta` - Scalar multiplier for C
+/// * `c` - Input/output matrix C
+///
+/// # Returns
+/// * `Ok(())` if successful
+/// * `Err(BlasError)` with `InvalidValue` if the dimensions do not agree, or if the operation failed
+///
+/// # Examples
+/// ```
+/// use hip_rs::{gemm_matrix, BlasHandle, DeviceMatrix, Operation};
+///
+/// let handle = BlasHandle::new().unwrap();
+/// let a = DeviceMatrix::from_row_major(2, 3, &[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+/// let b = DeviceMatrix::from_row_major(2, 3, &[1.0f32, 0.0, 0.0, 0.0, 1.0, 0.0]).unwrap();
+/// let mut c = DeviceMatrix::<f32>::alloc(3, 3).unwrap();
+///
+/// // C = A^T * B
+/// gemm_matrix(&handle, Operation::Transpose, Operation::None, &1.0, &a, &b, &0.0, &mut c).unwrap();
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn gemm_matrix<T: GemmDatatype>(
+    handle: &BlasHandle,
+    trans_a: Operation,
+    trans_b: Operation,
+    alpha: &T,
+    a: &DeviceMatrix<T>,
+    b: &DeviceMatrix<T>,
+    beta: &T,
+    c: &mut DeviceMatrix<T>,
+) -> BlasResult<()> {
+    let (m, k) = op_shape(a, trans_a);
+    let (b_k, n) = op_shape(b, trans_b);
+    if k != b_k || c.rows() != m || c.cols() != n {
+        return Err(BlasError::from_status(BlasStatus::InvalidValue));
+    }
+
+    let dim = |value: usize| {
+        i32::try_from(value).map_err(|_| BlasError::from_status(BlasStatus::InvalidValue))
+    };
+    let (lda, ldb, ldc) = (dim(a.ld())?, dim(b.ld())?, dim(c.ld())?);
+    gemm(
+        handle,
+        trans_a,
+        trans_b,
+        dim(m)?,
+        dim(n)?,
+        dim(k)?,
+        alpha,
+        a.memory(),
+        lda,
+        b.memory(),
+        ldb,
+        beta,
+        c.memory_mut(),
+        ldc,
+    )
+}
+
+/// Returns the rows and columns of op(matrix).
+fn op_shape<T: DeviceCopy>(matrix: &DeviceMatrix<T>, op: Operation) -> (usize, usize) {
+    match op {
+        Operation::None => (matrix.rows(), matrix.cols()),
+        Operation::Transpose | Operation::Conjugate => (matrix.cols(), matrix.rows()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::Complex32;
@@ -410,4 +488,40 @@ mod tests {
         );
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_gemm_matrix() {
+        let handle = BlasHandle::new().unwrap();
+        // A is 2x3, B is 3x2, stored row-major on the host
+        let a = DeviceMatrix::from_row_major(2, 3, &[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        let b = DeviceMatrix::from_row_major(3, 2, &[7.0f32, 8.0, 9.0, 10.0, 11.0, 12.0]).unwrap();
+        let mut c = DeviceMatrix::<f32>::alloc_with_ld(2, 2, 8).unwrap();
+
+        gemm_matrix(
+            &handle,
+            Operation::None,
+            Operation::None,
+            &1.0,
+            &a,
+            &b,
+            &0.0,
+            &mut c,
+        )
+        .unwrap();
+        assert_eq!(c.to_row_major().unwrap(), vec![58.0, 64.0, 139.0, 154.0]);
+
+        // A^T * A^T has mismatched inner dimensions
+        let mut wrong = DeviceMatrix::<f32>::alloc(3, 3).unwrap();
+        let result = gemm_matrix(
+            &handle,
+            Operation::Transpose,
+            Operation::Transpose,
+            &1.0,
+            &a,
+            &a,
+            &0.0,
+            &mut wrong,
+        );
+        assert_eq!(result.unwrap_err().status, BlasStatus::InvalidValue);
+    }
 }
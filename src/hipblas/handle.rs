@@ -42,10 +42,20 @@ impl BlasHandle {
     ///
     /// let handle = BlasHandle::new().unwrap();
     /// ```
+    #[track_caller]
     pub fn new() -> BlasResult<Self> {
         let mut handle = std::ptr::null_mut();
         unsafe {
             let status = sys::hipblasCreate(&mut handle);
+            #[cfg(feature = "track-allocations")]
+            if !handle.is_null() {
+                crate::core::tracking::register(
+                    crate::core::tracking::AllocationKind::BlasHandle,
+                    handle as usize,
+                    0,
+                    crate::get_device().ok(),
+                );
+            }
             (Self { handle }, status).to_result()
         }
     }
@@ -65,6 +75,12 @@ impl BlasHandle {
 impl Drop for BlasHandle {
     fn drop(&mut self) {
         if !self.handle.is_null() {
+            #[cfg(feature = "track-allocations")]
+            crate::core::tracking::unregister(
+                crate::core::tracking::AllocationKind::BlasHandle,
+                self.handle as usize,
+            );
+
             unsafe {
                 let status = sys::hipblasDestroy(self.handle);
                 if status != 0 {
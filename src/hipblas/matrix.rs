@@ -0,0 +1,405 @@
+use crate::result::ResultExt;
+use crate::MemoryPointer;
+use crate::{sys, DeviceCopy, DeviceSlice, HipError, HipResult, HipStatus, MemoryCopyKind};
+
+/// A dense matrix in device memory, stored column-major as hipBLAS expects.
+///
+/// Column `j` starts at element `j * ld()`. The leading dimension is at least
+/// the number of rows and may be larger to pad columns, in which case the
+/// padding elements are never read back.
+///
+/// # Examples
+/// ```
+/// use hip_rs::DeviceMatrix;
+///
+/// // [[1, 2, 3],
+/// //  [4, 5, 6]]
+/// let matrix = DeviceMatrix::from_row_major(2, 3, &[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+/// assert_eq!(matrix.ld(), 2);
+/// assert_eq!(matrix.to_column_major().unwrap(), vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+/// assert_eq!(matrix.to_row_major().unwrap(), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+/// ```
+#[derive(Debug)]
+pub struct DeviceMatrix<T: DeviceCopy> {
+    data: MemoryPointer<T>,
+    rows: usize,
+    cols: usize,
+    ld: usize,
+}
+
+impl<T: DeviceCopy> DeviceMatrix<T> {
+    /// Allocates an uninitialized `rows` x `cols` matrix with tightly packed columns.
+    pub fn alloc(rows: usize, cols: usize) -> HipResult<Self> {
+        Self::alloc_with_ld(rows, cols, rows.max(1))
+    }
+
+    /// Allocates an uninitialized matrix with the given leading dimension.
+    ///
+    /// # Arguments
+    /// * `rows` - The number of rows
+    /// * `cols` - The number of columns
+    /// * `ld` - The distance between columns in elements, at least `max(1, rows)`
+    ///
+    /// # Returns
+    /// * `Ok(DeviceMatrix<T>)` - The allocated matrix
+    /// * `Err(HipError)` - If `ld` is too small, the size overflows or the allocation fails
+    pub fn alloc_with_ld(rows: usize, cols: usize, ld: usize) -> HipResult<Self> {
+        if ld < rows.max(1) {
+            return Err(HipError::from_status(HipStatus::InvalidValue));
+        }
+        let len = ld
+            .checked_mul(cols)
+            .ok_or(HipError::from_status(HipStatus::InvalidValue))?;
+        Ok(Self {
+            data: MemoryPointer::alloc(len)?,
+            rows,
+            cols,
+            ld,
+        })
+    }
+
+    /// Uploads a matrix whose elements are laid out column by column.
+    ///
+    /// # Returns
+    /// * `Ok(DeviceMatrix<T>)` - The uploaded matrix
+    /// * `Err(HipError)` - If `data` does not hold `rows * cols` elements or the upload fails
+    pub fn from_column_major(rows: usize, cols: usize, data: &[T]) -> HipResult<Self> {
+        let mut matrix = Self::alloc(rows, cols)?;
+        matrix.copy_from_column_major(data)?;
+        Ok(matrix)
+    }
+
+    /// Uploads a matrix whose elements are laid out row by row, transposing
+    /// it on the host.
+    ///
+    /// # Returns
+    /// * `Ok(DeviceMatrix<T>)` - The uploaded matrix
+    /// * `Err(HipError)` - If `data` does not hold `rows * cols` elements or the upload fails
+    pub fn from_row_major(rows: usize, cols: usize, data: &[T]) -> HipResult<Self> {
+        check_len(rows, cols, data.len())?;
+        Self::from_column_major(rows, cols, &transpose(data, cols, rows))
+    }
+
+    /// Overwrites the matrix with column-major host data.
+    pub fn copy_from_column_major(&mut self, data: &[T]) -> HipResult<()> {
+        check_len(self.rows, self.cols, data.len())?;
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let size = std::mem::size_of::<T>();
+        unsafe {
+            memory_copy_2d(
+                self.data.as_pointer() as *mut std::ffi::c_void,
+                self.ld * size,
+                data.as_ptr() as *const std::ffi::c_void,
+                self.rows * size,
+                self.rows * size,
+                self.cols,
+                MemoryCopyKind::HostToDevice,
+            )
+        }
+    }
+
+    /// Downloads the matrix as `rows * cols` elements laid out column by column.
+    pub fn to_column_major(&self) -> HipResult<Vec<T>> {
+        let len = self.rows * self.cols;
+        let mut data = Vec::with_capacity(len);
+        if len == 0 {
+            return Ok(data);
+        }
+
+        let size = std::mem::size_of::<T>();
+        unsafe {
+            memory_copy_2d(
+                data.as_mut_ptr() as *mut std::ffi::c_void,
+                self.rows * size,
+                self.data.as_pointer() as *const std::ffi::c_void,
+                self.ld * size,
+                self.rows * size,
+                self.cols,
+                MemoryCopyKind::DeviceToHost,
+            )?;
+            // The copy initialized every element
+            data.set_len(len);
+        }
+        Ok(data)
+    }
+
+    /// Downloads the matrix as `rows * cols` elements laid out row by row.
+    pub fn to_row_major(&self) -> HipResult<Vec<T>> {
+        Ok(transpose(&self.to_column_major()?, self.rows, self.cols))
+    }
+
+    /// Returns the number of rows.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the number of columns.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns the leading dimension, the distance between columns in elements.
+    pub fn ld(&self) -> usize {
+        self.ld
+    }
+
+    /// Returns the device pointer to the first element.
+    pub fn as_pointer(&self) -> *mut T {
+        self.data.as_pointer()
+    }
+
+    /// Returns the underlying memory, including any column padding.
+    pub fn as_slice(&self) -> DeviceSlice<'_, T> {
+        self.data.as_slice()
+    }
+
+    /// Returns the underlying device buffer.
+    pub fn memory(&self) -> &MemoryPointer<T> {
+        &self.data
+    }
+
+    pub(crate) fn memory_mut(&mut self) -> &mut MemoryPointer<T> {
+        &mut self.data
+    }
+}
+
+fn check_len(rows: usize, cols: usize, len: usize) -> HipResult<()> {
+    if rows.checked_mul(cols) != Some(len) {
+        return Err(HipError::from_status(HipStatus::InvalidValue));
+    }
+    Ok(())
+}
+
+/// Transposes a column-major `rows` x `cols` matrix, which is the same as
+/// converting a row-major `cols` x `rows` matrix to column-major.
+fn transpose<T: Copy>(data: &[T], rows: usize, cols: usize) -> Vec<T> {
+    let mut out = Vec::with_capacity(data.len());
+    for row in 0..rows {
+        out.extend((0..cols).map(|col| data[col * rows + row]));
+    }
+    out
+}
+
+/// Copies `height` rows of `width` bytes between memory with different pitches.
+///
+/// # Safety
+/// Both regions must be valid for the pitches and sizes given, in the
+/// locations implied by `kind`.
+unsafe fn memory_copy_2d(
+    dst: *mut std::ffi::c_void,
+    dst_pitch: usize,
+    src: *const std::ffi::c_void,
+    src_pitch: usize,
+    width: usize,
+    height: usize,
+    kind: MemoryCopyKind,
+) -> HipResult<()> {
+    let code = sys::hipMemcpy2D(dst, dst_pitch, src, src_pitch, width, height, kind.into());
+    ((), code).to_result()
+}
+
+#[cfg(feature = "ndarray")]
+impl<T: DeviceCopy> DeviceMatrix<T> {
+    /// Uploads a two dimensional array of any memory layout.
+    ///
+    /// Column-major (Fortran order) arrays are uploaded directly; any other
+    /// layout, including row-major arrays and strided views, is gathered into
+    /// column-major order on the host first.
+    ///
+    /// # Examples
+    /// ```
+    /// use hip_rs::DeviceMatrix;
+    /// use ndarray::array;
+    ///
+    /// let a = array![[1.0f64, 2.0], [3.0, 4.0], [5.0, 6.0]];
+    /// let matrix = DeviceMatrix::from_ndarray(&a).unwrap();
+    /// assert_eq!((matrix.rows(), matrix.cols(), matrix.ld()), (3, 2, 3));
+    /// assert_eq!(matrix.to_ndarray().unwrap(), a);
+    /// ```
+    pub fn from_ndarray<S>(array: &ndarray::ArrayBase<S, ndarray::Ix2>) -> HipResult<Self>
+    where
+        S: ndarray::Data<Elem = T>,
+    {
+        let (rows, cols) = array.dim();
+        let transposed = array.t();
+        match transposed.as_slice() {
+            // The transpose is row-major exactly when the array is column-major
+            Some(data) => Self::from_column_major(rows, cols, data),
+            None => {
+                let data: Vec<T> = transposed.iter().copied().collect();
+                Self::from_column_major(rows, cols, &data)
+            }
+        }
+    }
+
+    /// Downloads the matrix into a column-major array.
+    ///
+    /// Call `as_standard_layout` on the result for a row-major array.
+    pub fn to_ndarray(&self) -> HipResult<ndarray::Array2<T>> {
+        use ndarray::ShapeBuilder;
+
+        let data = self.to_column_major()?;
+        let array = ndarray::Array2::from_shape_vec((self.rows, self.cols).f(), data)
+            .expect("downloaded data matches the matrix shape");
+        Ok(array)
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+impl<T: DeviceCopy + nalgebra::Scalar> DeviceMatrix<T> {
+    /// Uploads a nalgebra matrix or matrix view.
+    ///
+    /// Contiguous matrices such as `DMatrix` are already column-major and are
+    /// uploaded directly; views with a column stride are gathered on the host.
+    ///
+    /// # Examples
+    /// ```
+    /// use hip_rs::DeviceMatrix;
+    /// use nalgebra::DMatrix;
+    ///
+    /// let m = DMatrix::from_row_slice(2, 3, &[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    /// let matrix = DeviceMatrix::from_nalgebra(&m).unwrap();
+    /// assert_eq!(matrix.to_nalgebra().unwrap(), m);
+    /// ```
+    pub fn from_nalgebra<R, C, S>(matrix: &nalgebra::Matrix<T, R, C, S>) -> HipResult<Self>
+    where
+        R: nalgebra::Dim,
+        C: nalgebra::Dim,
+        S: nalgebra::RawStorage<T, R, C>,
+    {
+        let (rows, cols) = matrix.shape();
+        if matrix.data.is_contiguous() {
+            // Contiguous storage is column-major with ld equal to rows
+            let data = unsafe { matrix.data.as_slice_unchecked() };
+            return Self::from_column_major(rows, cols, data);
+        }
+
+        let data: Vec<T> = matrix.iter().cloned().collect();
+        Self::from_column_major(rows, cols, &data)
+    }
+
+    /// Downloads the matrix into a `DMatrix`.
+    pub fn to_nalgebra(&self) -> HipResult<nalgebra::DMatrix<T>> {
+        Ok(nalgebra::DMatrix::from_vec(
+            self.rows,
+            self.cols,
+            self.to_column_major()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transpose() {
+        // Column-major 2x3: [[1, 3, 5], [2, 4, 6]]
+        let data = [1, 2, 3, 4, 5, 6];
+        assert_eq!(transpose(&data, 2, 3), vec![1, 3, 5, 2, 4, 6]);
+        assert_eq!(transpose(&transpose(&data, 2, 3), 3, 2), data);
+        assert!(transpose::<u8>(&[], 0, 4).is_empty());
+    }
+
+    #[test]
+    fn test_check_len() {
+        assert!(check_len(2, 3, 6).is_ok());
+        assert!(check_len(0, 3, 0).is_ok());
+        assert!(check_len(2, 3, 5).is_err());
+        assert!(check_len(usize::MAX, 2, 0).is_err());
+    }
+
+    #[test]
+    fn test_invalid_ld() {
+        let result = DeviceMatrix::<f32>::alloc_with_ld(4, 2, 3);
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+        let result = DeviceMatrix::<f32>::alloc_with_ld(0, 2, 0);
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+    }
+
+    #[test]
+    fn test_row_major_roundtrip() {
+        let data: Vec<f32> = (0..12).map(|i| i as f32).collect();
+        let matrix = DeviceMatrix::from_row_major(3, 4, &data).unwrap();
+        assert_eq!(matrix.ld(), 3);
+        assert_eq!(matrix.to_row_major().unwrap(), data);
+        assert_eq!(matrix.to_column_major().unwrap(), transpose(&data, 4, 3));
+    }
+
+    #[test]
+    fn test_padded_ld() {
+        let mut matrix = DeviceMatrix::<i32>::alloc_with_ld(3, 2, 64).unwrap();
+        assert_eq!(matrix.as_slice().len(), 128);
+
+        matrix.copy_from_column_major(&[1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(matrix.to_column_major().unwrap(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(matrix.to_row_major().unwrap(), vec![1, 4, 2, 5, 3, 6]);
+
+        // Column 1 starts at the leading dimension
+        let mut column = [0i32; 3];
+        crate::copy(&mut column[..], &matrix.as_slice().slice(64..67).unwrap()).unwrap();
+        assert_eq!(column, [4, 5, 6]);
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        let result = DeviceMatrix::from_row_major(2, 2, &[1.0f64; 3]);
+        assert_eq!(result.unwrap_err().status, HipStatus::InvalidValue);
+    }
+
+    #[test]
+    fn test_empty_matrix() {
+        let matrix = DeviceMatrix::<f32>::from_column_major(0, 5, &[]).unwrap();
+        assert_eq!(matrix.ld(), 1);
+        assert!(matrix.to_row_major().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_ndarray_layouts() {
+        use ndarray::{s, Array2, ShapeBuilder};
+
+        let c_order = Array2::from_shape_fn((3, 4), |(i, j)| (i * 4 + j) as f32);
+        let f_order =
+            Array2::from_shape_vec((3, 4).f(), c_order.t().iter().copied().collect()).unwrap();
+        assert_eq!(c_order, f_order);
+
+        let strided = c_order.slice(s![..;2, ..;-1]);
+        for (array, expected) in [
+            (c_order.view(), c_order.clone()),
+            (f_order.view(), c_order.clone()),
+            (strided, strided.to_owned()),
+        ] {
+            let matrix = DeviceMatrix::from_ndarray(&array).unwrap();
+            assert_eq!(matrix.rows(), expected.nrows());
+            assert_eq!(matrix.ld(), expected.nrows());
+            let downloaded = matrix.to_ndarray().unwrap();
+            assert!(downloaded.t().is_standard_layout());
+            assert_eq!(downloaded, expected);
+        }
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn test_nalgebra_views() {
+        let m = nalgebra::DMatrix::from_fn(4, 5, |i, j| (i * 10 + j) as f64);
+        let matrix = DeviceMatrix::from_nalgebra(&m).unwrap();
+        assert_eq!(matrix.to_column_major().unwrap(), m.as_slice());
+
+        // A view into the middle has a column stride of 4
+        let view = m.view((1, 1), (2, 3));
+        let matrix = DeviceMatrix::from_nalgebra(&view).unwrap();
+        assert_eq!((matrix.rows(), matrix.cols(), matrix.ld()), (2, 3, 2));
+        assert_eq!(matrix.to_nalgebra().unwrap(), view.clone_owned());
+
+        let fixed = nalgebra::Matrix2x3::new(1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0);
+        let matrix = DeviceMatrix::from_nalgebra(&fixed).unwrap();
+        assert_eq!(
+            matrix.to_row_major().unwrap(),
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+        );
+    }
+}
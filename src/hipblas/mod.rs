@@ -1,6 +1,7 @@
 mod blas_call;
 mod gemm;
 mod handle;
+mod matrix;
 mod result;
 mod types;
 
@@ -8,5 +9,6 @@ mod types;
 pub use blas_call::*;
 pub use gemm::*;
 pub use handle::*;
+pub use matrix::*;
 pub use result::*;
 pub use types::*;
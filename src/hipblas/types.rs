@@ -1,4 +1,4 @@
-use crate::sys;
+use crate::{sys, DeviceCopy};
 
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -50,6 +50,8 @@ impl From<sys::hipblasStatus_t> for Status {
     }
 }
 
+/// A single precision complex number with the layout of `hipblasComplex`.
+#[repr(transparent)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Complex32 {
     inner: sys::hipblasComplex,
@@ -101,6 +103,15 @@ impl From<Complex32> for sys::hipblasComplex {
     }
 }
 
+unsafe impl DeviceCopy for Complex32 {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Zeroable for Complex32 {}
+
+// Two f32 without padding, like hipblasComplex
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Pod for Complex32 {}
+
 impl Default for Complex32 {
     fn default() -> Self {
         Self::new(0.0, 0.0)
@@ -0,0 +1,575 @@
+use super::program::{CompileError, CompiledProgram, Program};
+use crate::{get_device, runtime_get_version};
+use semver::Version;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+/// Default limit on the total size of cached code objects
+const DEFAULT_MAX_SIZE: u64 = 512 << 20;
+
+/// Identifies the entry file format, bumped when it changes
+const ENTRY_MAGIC: &[u8; 8] = b"HIPRSKC\x01";
+
+const ENTRY_EXTENSION: &str = "kco";
+
+const DIGEST_SIZE: usize = 32;
+
+/// Identifies a compilation: a hash of everything that affects the code object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey([u8; DIGEST_SIZE]);
+
+impl CacheKey {
+    /// Computes the key for compiling `program` for `target` with a given runtime.
+    ///
+    /// # Arguments
+    /// * `program` - The program, including its headers, options and name expressions
+    /// * `target` - The GPU target ID, e.g. `gfx90a:sramecc+:xnack-`
+    /// * `runtime_version` - The HIP runtime version, which determines the compiler
+    pub fn new(program: &Program, target: &str, runtime_version: &Version) -> Self {
+        let mut hasher = KeyHasher(Sha256::new());
+        hasher.field(ENTRY_MAGIC);
+        hasher.field(program.source().as_bytes());
+        hasher.field(program.name().as_bytes());
+        hasher.list(
+            program
+                .headers()
+                .iter()
+                .flat_map(|(name, source)| [name, source]),
+        );
+        hasher.list(program.options());
+        hasher.list(program.name_expressions());
+        hasher.field(target.as_bytes());
+        hasher.field(runtime_version.to_string().as_bytes());
+        Self(hasher.0.finalize().into())
+    }
+
+    /// Returns the key as lowercase hex, as used in entry file names.
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+/// Hashes fields with length prefixes, so that moving bytes between
+/// adjacent fields changes the key.
+struct KeyHasher(Sha256);
+
+impl KeyHasher {
+    fn field(&mut self, bytes: &[u8]) {
+        self.0.update((bytes.len() as u64).to_le_bytes());
+        self.0.update(bytes);
+    }
+
+    fn list<I, S>(&mut self, items: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let items: Vec<S> = items.into_iter().collect();
+        self.0.update((items.len() as u64).to_le_bytes());
+        for item in &items {
+            self.field(item.as_ref().as_bytes());
+        }
+    }
+}
+
+/// A persistent cache of code objects compiled with hipRTC.
+///
+/// Each entry is a file named after its [`CacheKey`], written to a temporary
+/// file and renamed into place so readers never see partial entries. Entries
+/// carry a checksum and are discarded if they fail validation. When the total
+/// size exceeds the limit, the least recently used entries are removed.
+///
+/// Several processes can share a cache directory.
+///
+/// # Examples
+/// ```
+/// use hip_rs::{KernelCache, Program};
+///
+/// let cache = KernelCache::new().unwrap().with_max_size(64 << 20);
+/// let program = Program::new(r#"extern "C" __global__ void noop() {}"#);
+///
+/// // Compiles on the first run and loads from disk afterwards
+/// let compiled = cache.compile(&program).unwrap();
+/// let module = compiled.load().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct KernelCache {
+    directory: PathBuf,
+    max_size: u64,
+}
+
+impl KernelCache {
+    /// Opens the cache in the default directory, see [`KernelCache::default_directory`].
+    pub fn new() -> io::Result<Self> {
+        Self::with_directory(Self::default_directory())
+    }
+
+    /// Opens the cache in `directory`, creating it if needed.
+    pub fn with_directory<P: Into<PathBuf>>(directory: P) -> io::Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+        Ok(Self {
+            directory,
+            max_size: DEFAULT_MAX_SIZE,
+        })
+    }
+
+    /// Sets the size limit in bytes, 512 MiB by default.
+    pub fn with_max_size(mut self, bytes: u64) -> Self {
+        self.max_size = bytes;
+        self
+    }
+
+    /// Returns `$XDG_CACHE_HOME/hip_rs/kernels`, falling back to
+    /// `$HOME/.cache` and then the temporary directory.
+    pub fn default_directory() -> PathBuf {
+        let absolute = |var| {
+            std::env::var_os(var)
+                .map(PathBuf::from)
+                .filter(|path| path.is_absolute())
+        };
+        let base = absolute("XDG_CACHE_HOME")
+            .or_else(|| absolute("HOME").map(|home| home.join(".cache")))
+            .unwrap_or_else(std::env::temp_dir);
+        base.join("hip_rs").join("kernels")
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// Compiles `program` for the current device, or loads it from the cache.
+    ///
+    /// Failing to store a new entry is logged, not returned; the compiled
+    /// program is still usable.
+    pub fn compile(&self, program: &Program) -> Result<CompiledProgram, CompileError> {
+        let target = get_device().and_then(|device| device.gcn_arch_name());
+        match (target, runtime_get_version()) {
+            (Ok(target), Ok(version)) => self.compile_for(program, &target, &version),
+            (Err(error), _) | (_, Err(error)) => {
+                log::warn!("Compiling without cache, device query failed: {:?}", error);
+                program.compile()
+            }
+        }
+    }
+
+    /// Like [`KernelCache::compile`], for an explicit target and runtime version.
+    pub fn compile_for(
+        &self,
+        program: &Program,
+        target: &str,
+        runtime_version: &Version,
+    ) -> Result<CompiledProgram, CompileError> {
+        let key = CacheKey::new(program, target, runtime_version);
+        if let Some(compiled) = self.get(&key) {
+            return Ok(compiled);
+        }
+        let compiled = program.compile()?;
+        if let Err(error) = self.insert(&key, &compiled) {
+            log::warn!("Failed to store kernel cache entry {}: {}", key, error);
+        }
+        Ok(compiled)
+    }
+
+    /// Looks up an entry, marking it as recently used.
+    ///
+    /// Entries that fail validation are removed and reported as missing.
+    pub fn get(&self, key: &CacheKey) -> Option<CompiledProgram> {
+        let path = self.entry_path(key);
+        let bytes = fs::read(&path).ok()?;
+        match decode_entry(&bytes, key) {
+            Some(compiled) => {
+                if let Err(error) = touch(&path) {
+                    log::warn!("Failed to update kernel cache entry {}: {}", key, error);
+                }
+                Some(compiled)
+            }
+            None => {
+                log::warn!("Removing invalid kernel cache entry {}", path.display());
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    /// Stores an entry, then evicts entries until the cache fits its size limit.
+    pub fn insert(&self, key: &CacheKey, compiled: &CompiledProgram) -> io::Result<()> {
+        let bytes = encode_entry(key, compiled);
+        let temp_path = self.directory.join(temp_file_name(key));
+        let result = File::create(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(&bytes)?;
+                file.sync_all()
+            })
+            .and_then(|()| fs::rename(&temp_path, self.entry_path(key)));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result?;
+        self.evict()
+    }
+
+    /// Removes an entry if present.
+    pub fn remove(&self, key: &CacheKey) -> io::Result<()> {
+        match fs::remove_file(self.entry_path(key)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the total size of all entries in bytes.
+    pub fn size(&self) -> io::Result<u64> {
+        Ok(self.entries()?.iter().map(|entry| entry.size).sum())
+    }
+
+    /// Removes least recently used entries until the cache fits its size limit.
+    pub fn evict(&self) -> io::Result<()> {
+        let mut entries = self.entries()?;
+        let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+        entries.sort_by_key(|entry| entry.used);
+        for entry in entries {
+            if total <= self.max_size {
+                break;
+            }
+            match fs::remove_file(&entry.path) {
+                // Another process may have evicted it first
+                Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+                _ => total -= entry.size,
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes all entries.
+    pub fn clear(&self) -> io::Result<()> {
+        for entry in self.entries()? {
+            let _ = fs::remove_file(entry.path);
+        }
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.directory
+            .join(format!("{}.{}", key.to_hex(), ENTRY_EXTENSION))
+    }
+
+    fn entries(&self) -> io::Result<Vec<EntryInfo>> {
+        let mut entries = Vec::new();
+        for dir_entry in fs::read_dir(&self.directory)? {
+            let dir_entry = dir_entry?;
+            let path = dir_entry.path();
+            if path.extension().is_none_or(|ext| ext != ENTRY_EXTENSION) {
+                continue;
+            }
+            // Skip entries removed since the directory was listed
+            let Ok(metadata) = dir_entry.metadata() else {
+                continue;
+            };
+            entries.push(EntryInfo {
+                path,
+                size: metadata.len(),
+                used: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+struct EntryInfo {
+    path: PathBuf,
+    size: u64,
+    /// Modification time, refreshed on every hit
+    used: SystemTime,
+}
+
+fn touch(path: &Path) -> io::Result<()> {
+    File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(SystemTime::now())
+}
+
+/// A name unique to this process and call, so concurrent writers never share a file.
+fn temp_file_name(key: &CacheKey) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!(
+        ".{}.{}.{}.tmp",
+        key.to_hex(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Serializes an entry as the magic, key, lowered names, log and code,
+/// followed by a SHA-256 checksum of everything before it.
+fn encode_entry(key: &CacheKey, compiled: &CompiledProgram) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(compiled.code().len() + compiled.log().len() + 256);
+    bytes.extend_from_slice(ENTRY_MAGIC);
+    bytes.extend_from_slice(&key.0);
+    let put = |bytes: &mut Vec<u8>, field: &[u8]| {
+        bytes.extend_from_slice(&(field.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(field);
+    };
+    bytes.extend_from_slice(&(compiled.lowered_names().len() as u64).to_le_bytes());
+    for (expression, lowered) in compiled.lowered_names() {
+        put(&mut bytes, expression.as_bytes());
+        put(&mut bytes, lowered.as_bytes());
+    }
+    put(&mut bytes, compiled.log().as_bytes());
+    put(&mut bytes, compiled.code());
+    let checksum = Sha256::digest(&bytes);
+    bytes.extend_from_slice(&checksum);
+    bytes
+}
+
+/// Parses an entry, returning `None` if it is truncated, corrupted or for another key.
+fn decode_entry(bytes: &[u8], key: &CacheKey) -> Option<CompiledProgram> {
+    let body_len = bytes.len().checked_sub(DIGEST_SIZE)?;
+    let (body, checksum) = bytes.split_at(body_len);
+    if Sha256::digest(body).as_slice() != checksum {
+        return None;
+    }
+
+    let mut reader = EntryReader(body);
+    if reader.take(ENTRY_MAGIC.len())? != ENTRY_MAGIC || reader.take(DIGEST_SIZE)? != key.0 {
+        return None;
+    }
+    let count = reader.u64()?;
+    let mut lowered_names = Vec::new();
+    for _ in 0..count {
+        let expression = reader.string()?;
+        let lowered = reader.string()?;
+        lowered_names.push((expression, lowered));
+    }
+    let log = reader.string()?;
+    let code = reader.field()?.to_vec();
+    if !reader.0.is_empty() {
+        return None;
+    }
+    Some(CompiledProgram::from_parts(code, log, lowered_names))
+}
+
+struct EntryReader<'a>(&'a [u8]);
+
+impl<'a> EntryReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.0.len() {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn field(&mut self) -> Option<&'a [u8]> {
+        let len = usize::try_from(self.u64()?).ok()?;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        String::from_utf8(self.field()?.to_vec()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_cache(name: &str) -> KernelCache {
+        let directory = std::env::temp_dir().join(format!(
+            "hip_rs_test_kernel_cache_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        KernelCache::with_directory(directory).unwrap()
+    }
+
+    fn compiled(code: &[u8]) -> CompiledProgram {
+        CompiledProgram::from_parts(
+            code.to_vec(),
+            "warning: unused variable".to_string(),
+            vec![("apply<float>".to_string(), "_Z5applyIfEvPT_".to_string())],
+        )
+    }
+
+    fn version() -> Version {
+        Version::new(6, 2, 41133)
+    }
+
+    fn key(source: &str) -> CacheKey {
+        CacheKey::new(&Program::new(source), "gfx90a", &version())
+    }
+
+    #[test]
+    fn test_key_is_stable() {
+        let program = Program::new("__global__ void k() {}").with_option("-O3");
+        let a = CacheKey::new(&program, "gfx90a", &version());
+        let b = CacheKey::new(&program.clone(), "gfx90a", &version());
+        assert_eq!(a, b);
+        assert_eq!(a.to_hex().len(), 64);
+        assert_eq!(a.to_string(), a.to_hex());
+    }
+
+    #[test]
+    fn test_key_covers_inputs() {
+        let program = Program::new("__global__ void k() {}");
+        let base = CacheKey::new(&program, "gfx90a", &version());
+        let variants = [
+            CacheKey::new(&program.clone().with_option("-O3"), "gfx90a", &version()),
+            CacheKey::new(&program.clone().with_define("N", "4"), "gfx90a", &version()),
+            CacheKey::new(
+                &program.clone().with_header("a.h", ""),
+                "gfx90a",
+                &version(),
+            ),
+            CacheKey::new(&program.clone().with_name("k.hip"), "gfx90a", &version()),
+            CacheKey::new(
+                &program.clone().with_name_expression("k"),
+                "gfx90a",
+                &version(),
+            ),
+            CacheKey::new(&program, "gfx90a:xnack+", &version()),
+            CacheKey::new(&program, "gfx90a", &Version::new(6, 3, 0)),
+            CacheKey::new(
+                &Program::new("__global__ void j() {}"),
+                "gfx90a",
+                &version(),
+            ),
+        ];
+        for variant in variants {
+            assert_ne!(variant, base);
+        }
+
+        // Moving bytes between fields must change the key
+        let split_a = Program::new("").with_options(["-DA", "B"]);
+        let split_b = Program::new("").with_options(["-DAB"]);
+        assert_ne!(
+            CacheKey::new(&split_a, "gfx90a", &version()),
+            CacheKey::new(&split_b, "gfx90a", &version())
+        );
+    }
+
+    #[test]
+    fn test_insert_get() {
+        let cache = temp_cache("insert_get");
+        let key = key("a");
+        assert!(cache.get(&key).is_none());
+
+        let entry = compiled(b"\x7fELF code object");
+        cache.insert(&key, &entry).unwrap();
+        assert_eq!(cache.get(&key).unwrap(), entry);
+        assert!(cache.get(&self::key("b")).is_none());
+
+        // Only the entry remains, no temporary files
+        let files: Vec<_> = fs::read_dir(cache.directory()).unwrap().collect();
+        assert_eq!(files.len(), 1);
+
+        cache.remove(&key).unwrap();
+        assert!(cache.get(&key).is_none());
+        cache.remove(&key).unwrap();
+        fs::remove_dir_all(cache.directory()).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_entries_are_discarded() {
+        let cache = temp_cache("invalid");
+        let key = key("a");
+        cache.insert(&key, &compiled(b"code")).unwrap();
+        let path = cache.entry_path(&key);
+
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - DIGEST_SIZE - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&path, &bytes).unwrap();
+        assert!(cache.get(&key).is_none());
+        assert!(!path.exists());
+
+        // A valid entry stored under the wrong name
+        cache.insert(&key, &compiled(b"code")).unwrap();
+        let other = self::key("b");
+        fs::rename(&path, cache.entry_path(&other)).unwrap();
+        assert!(cache.get(&other).is_none());
+
+        fs::write(&path, b"HIPRSKC").unwrap();
+        assert!(cache.get(&key).is_none());
+        fs::remove_dir_all(cache.directory()).unwrap();
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = temp_cache("lru");
+        let entry = compiled(&[0u8; 1000]);
+        let entry_size = encode_entry(&key("a"), &entry).len() as u64;
+        let cache = cache.with_max_size(entry_size * 3);
+
+        let keys: Vec<_> = ["a", "b", "c"].iter().map(|source| key(source)).collect();
+        let start = SystemTime::now() - Duration::from_secs(100);
+        for (i, key) in keys.iter().enumerate() {
+            cache.insert(key, &entry).unwrap();
+            File::options()
+                .write(true)
+                .open(cache.entry_path(key))
+                .unwrap()
+                .set_modified(start + Duration::from_secs(i as u64))
+                .unwrap();
+        }
+        assert_eq!(cache.size().unwrap(), entry_size * 3);
+
+        // Using "a" makes "b" the least recently used
+        assert!(cache.get(&keys[0]).is_some());
+        cache.insert(&key("d"), &entry).unwrap();
+        assert_eq!(cache.size().unwrap(), entry_size * 3);
+        assert!(cache.get(&keys[0]).is_some());
+        assert!(cache.get(&keys[1]).is_none());
+        assert!(cache.get(&keys[2]).is_some());
+
+        cache.clear().unwrap();
+        assert_eq!(cache.size().unwrap(), 0);
+        fs::remove_dir_all(cache.directory()).unwrap();
+    }
+
+    #[test]
+    fn test_compile_for_uses_cache() {
+        let cache = temp_cache("compile_for");
+        // Invalid source: a hit must not reach the compiler
+        let program = Program::new("not valid C++");
+        let key = CacheKey::new(&program, "gfx942", &version());
+        cache.insert(&key, &compiled(b"cached")).unwrap();
+
+        let result = cache.compile_for(&program, "gfx942", &version()).unwrap();
+        assert_eq!(result.code(), b"cached");
+        assert_eq!(result.lowered_name("apply<float>"), Some("_Z5applyIfEvPT_"));
+        fs::remove_dir_all(cache.directory()).unwrap();
+    }
+
+    #[test]
+    fn test_compile_populates_cache() {
+        let cache = temp_cache("compile");
+        let program = Program::new(r#"extern "C" __global__ void noop() {}"#);
+        let compiled = cache.compile(&program).unwrap();
+        assert_eq!(cache.compile(&program).unwrap(), compiled);
+        assert!(cache.size().unwrap() > 0);
+        fs::remove_dir_all(cache.directory()).unwrap();
+    }
+}
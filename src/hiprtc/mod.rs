@@ -0,0 +1,9 @@
+#[cfg(feature = "kernel-cache")]
+mod cache;
+mod program;
+mod result;
+
+#[cfg(feature = "kernel-cache")]
+pub use cache::*;
+pub use program::*;
+pub use result::*;
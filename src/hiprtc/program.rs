@@ -0,0 +1,493 @@
+use super::result::{RtcError, RtcResult, RtcStatus};
+use crate::result::{ResultExt, StatusCode};
+use crate::sys;
+use crate::{HipResult, Module};
+use semver::Version;
+use std::ffi::{c_char, CStr, CString};
+use std::fmt;
+
+/// Name given to programs that don't set one
+const DEFAULT_PROGRAM_NAME: &str = "program.hip";
+
+/// A HIP C++ program to compile at runtime with hipRTC.
+///
+/// The program only describes what to compile; [`Program::compile`] creates
+/// the hipRTC program, compiles it and returns the code object.
+///
+/// # Examples
+/// ```
+/// use hip_rs::{Module, Program};
+///
+/// let source = r#"
+///     extern "C" __global__ void scale(float* data, float factor, unsigned n) {
+///         unsigned i = blockIdx.x * blockDim.x + threadIdx.x;
+///         if (i < n) data[i] *= FACTOR_SCALE * factor;
+///     }
+/// "#;
+/// let compiled = Program::new(source)
+///     .with_name("scale.hip")
+///     .with_define("FACTOR_SCALE", "2.0f")
+///     .with_option("-O3")
+///     .compile()
+///     .unwrap();
+///
+/// let module = Module::load_data(compiled.code()).unwrap();
+/// let scale = module.function("scale").unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Program {
+    source: String,
+    name: String,
+    headers: Vec<(String, String)>,
+    options: Vec<String>,
+    name_expressions: Vec<String>,
+}
+
+impl Program {
+    /// Creates a program from HIP C++ source.
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            name: DEFAULT_PROGRAM_NAME.to_string(),
+            headers: Vec::new(),
+            options: Vec::new(),
+            name_expressions: Vec::new(),
+        }
+    }
+
+    /// Sets the file name used in diagnostics, `program.hip` by default.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Makes `source` available to `#include "<include_name>"`.
+    pub fn with_header(
+        mut self,
+        include_name: impl Into<String>,
+        source: impl Into<String>,
+    ) -> Self {
+        self.headers.push((include_name.into(), source.into()));
+        self
+    }
+
+    /// Adds a compiler option, e.g. `-O3` or `-std=c++17`.
+    pub fn with_option(mut self, option: impl Into<String>) -> Self {
+        self.options.push(option.into());
+        self
+    }
+
+    /// Adds several compiler options.
+    pub fn with_options<I, S>(mut self, options: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.extend(options.into_iter().map(Into::into));
+        self
+    }
+
+    /// Compiles for a GPU target such as `gfx90a` or `gfx942:sramecc+:xnack-`.
+    ///
+    /// Without a target, hipRTC compiles for the current device.
+    pub fn with_offload_arch(self, target: &str) -> Self {
+        self.with_option(format!("--offload-arch={}", target))
+    }
+
+    /// Defines a preprocessor macro, as `-D<name>=<value>`, or `-D<name>` if `value` is empty.
+    pub fn with_define(self, name: &str, value: &str) -> Self {
+        let option = if value.is_empty() {
+            format!("-D{}", name)
+        } else {
+            format!("-D{}={}", name, value)
+        };
+        self.with_option(option)
+    }
+
+    /// Registers a name expression, such as `scale<float>`, whose lowered
+    /// (mangled) name can be looked up after compiling.
+    ///
+    /// Registering a template instantiation also makes the compiler emit it.
+    pub fn with_name_expression(mut self, expression: impl Into<String>) -> Self {
+        self.name_expressions.push(expression.into());
+        self
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the headers as `(include_name, source)` pairs.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    pub fn options(&self) -> &[String] {
+        &self.options
+    }
+
+    pub fn name_expressions(&self) -> &[String] {
+        &self.name_expressions
+    }
+
+    /// Compiles the program to a code object.
+    ///
+    /// # Returns
+    /// * `Ok(CompiledProgram)` - The code object, compile log and lowered names
+    /// * `Err(CompileError)` - The hipRTC error with the compile log, if any
+    pub fn compile(&self) -> Result<CompiledProgram, CompileError> {
+        let source = c_string(&self.source)?;
+        let name = c_string(&self.name)?;
+        let header_sources = self
+            .headers
+            .iter()
+            .map(|(_, source)| c_string(source))
+            .collect::<RtcResult<Vec<_>>>()?;
+        let header_names = self
+            .headers
+            .iter()
+            .map(|(name, _)| c_string(name))
+            .collect::<RtcResult<Vec<_>>>()?;
+        let options = self
+            .options
+            .iter()
+            .map(|option| c_string(option))
+            .collect::<RtcResult<Vec<_>>>()?;
+        let expressions = self
+            .name_expressions
+            .iter()
+            .map(|expression| c_string(expression))
+            .collect::<RtcResult<Vec<_>>>()?;
+
+        let header_source_ptrs: Vec<*const c_char> =
+            header_sources.iter().map(|s| s.as_ptr()).collect();
+        let header_name_ptrs: Vec<*const c_char> =
+            header_names.iter().map(|s| s.as_ptr()).collect();
+        let mut option_ptrs: Vec<*const c_char> = options.iter().map(|s| s.as_ptr()).collect();
+
+        let program =
+            ProgramHandle::create(&source, &name, &header_source_ptrs, &header_name_ptrs)?;
+        for expression in &expressions {
+            let code = unsafe { sys::hiprtcAddNameExpression(program.0, expression.as_ptr()) };
+            let result: RtcResult<()> = ((), code).to_result();
+            result?;
+        }
+
+        let code = unsafe {
+            sys::hiprtcCompileProgram(
+                program.0,
+                option_ptrs.len() as i32,
+                option_ptrs.as_mut_ptr(),
+            )
+        };
+        let log = program.log()?;
+        let result: RtcResult<()> = ((), code).to_result();
+        if let Err(error) = result {
+            return Err(CompileError { error, log });
+        }
+
+        let lowered_names = self
+            .name_expressions
+            .iter()
+            .zip(&expressions)
+            .map(|(expression, c_expression)| {
+                Ok((expression.clone(), program.lowered_name(c_expression)?))
+            })
+            .collect::<RtcResult<Vec<_>>>()?;
+        Ok(CompiledProgram {
+            code: program.code()?,
+            log,
+            lowered_names,
+        })
+    }
+}
+
+/// The result of compiling a [`Program`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledProgram {
+    code: Vec<u8>,
+    log: String,
+    lowered_names: Vec<(String, String)>,
+}
+
+impl CompiledProgram {
+    /// Reassembles a compiled program read back from a [`crate::KernelCache`].
+    #[cfg(feature = "kernel-cache")]
+    pub(crate) fn from_parts(
+        code: Vec<u8>,
+        log: String,
+        lowered_names: Vec<(String, String)>,
+    ) -> Self {
+        Self {
+            code,
+            log,
+            lowered_names,
+        }
+    }
+
+    /// Returns the code object, ready for [`Module::load_data`].
+    pub fn code(&self) -> &[u8] {
+        &self.code
+    }
+
+    pub fn into_code(self) -> Vec<u8> {
+        self.code
+    }
+
+    /// Returns the compile log, which holds any warnings.
+    pub fn log(&self) -> &str {
+        &self.log
+    }
+
+    /// Returns the symbol for a name expression registered with
+    /// [`Program::with_name_expression`], for use with [`Module::function`].
+    pub fn lowered_name(&self, expression: &str) -> Option<&str> {
+        self.lowered_names
+            .iter()
+            .find(|(registered, _)| registered == expression)
+            .map(|(_, lowered)| lowered.as_str())
+    }
+
+    /// Returns all registered name expressions with their lowered names.
+    pub fn lowered_names(&self) -> &[(String, String)] {
+        &self.lowered_names
+    }
+
+    /// Loads the code object on the current device.
+    pub fn load(&self) -> HipResult<Module> {
+        Module::load_data(&self.code)
+    }
+}
+
+/// A failed compilation, with the compiler output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub error: RtcError,
+    /// The compile log, empty if the failure happened before compiling
+    pub log: String,
+}
+
+impl From<RtcError> for CompileError {
+    fn from(error: RtcError) -> Self {
+        Self {
+            error,
+            log: String::new(),
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        StatusCode::fmt(&self.error, f)?;
+        if !self.log.is_empty() {
+            write!(f, "\n{}", self.log.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// Owns a hipRTC program, destroying it on drop.
+struct ProgramHandle(sys::hiprtcProgram);
+
+impl ProgramHandle {
+    fn create(
+        source: &CStr,
+        name: &CStr,
+        header_sources: &[*const c_char],
+        header_names: &[*const c_char],
+    ) -> RtcResult<Self> {
+        let mut handle: sys::hiprtcProgram = std::ptr::null_mut();
+        unsafe {
+            let code = sys::hiprtcCreateProgram(
+                &mut handle,
+                source.as_ptr(),
+                name.as_ptr(),
+                header_sources.len() as i32,
+                header_sources.as_ptr() as *mut *const c_char,
+                header_names.as_ptr() as *mut *const c_char,
+            );
+            (Self(handle), code).to_result()
+        }
+    }
+
+    fn log(&self) -> RtcResult<String> {
+        let mut size = 0;
+        let code = unsafe { sys::hiprtcGetProgramLogSize(self.0, &mut size) };
+        let result: RtcResult<()> = ((), code).to_result();
+        result?;
+
+        let mut log = vec![0u8; size];
+        if size > 0 {
+            let code = unsafe { sys::hiprtcGetProgramLog(self.0, log.as_mut_ptr() as *mut c_char) };
+            let result: RtcResult<()> = ((), code).to_result();
+            result?;
+        }
+        // The size includes the terminating NUL
+        let end = log.iter().position(|&byte| byte == 0).unwrap_or(log.len());
+        Ok(String::from_utf8_lossy(&log[..end]).into_owned())
+    }
+
+    fn code(&self) -> RtcResult<Vec<u8>> {
+        let mut size = 0;
+        let code = unsafe { sys::hiprtcGetCodeSize(self.0, &mut size) };
+        let result: RtcResult<()> = ((), code).to_result();
+        result?;
+
+        let mut binary = vec![0u8; size];
+        unsafe {
+            let code = sys::hiprtcGetCode(self.0, binary.as_mut_ptr() as *mut c_char);
+            (binary, code).to_result()
+        }
+    }
+
+    fn lowered_name(&self, expression: &CStr) -> RtcResult<String> {
+        let mut lowered: *const c_char = std::ptr::null();
+        unsafe {
+            let code = sys::hiprtcGetLoweredName(self.0, expression.as_ptr(), &mut lowered);
+            let result: RtcResult<()> = ((), code).to_result();
+            result?;
+            // The string is owned by the program and must be copied before it is destroyed
+            Ok(CStr::from_ptr(lowered).to_string_lossy().into_owned())
+        }
+    }
+}
+
+impl Drop for ProgramHandle {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe {
+                let code = sys::hiprtcDestroyProgram(&mut self.0);
+                if code != 0 {
+                    log::error!("Failed to destroy hipRTC program: {}", code);
+                }
+            }
+        }
+    }
+}
+
+fn c_string(value: &str) -> RtcResult<CString> {
+    CString::new(value).map_err(|_| RtcError::from_status(RtcStatus::InvalidInput))
+}
+
+/// Gets the version of hipRTC.
+///
+/// # Returns
+/// * `Ok(Version)` - The major and minor version
+/// * `Err(RtcError)` - If the version could not be queried
+pub fn rtc_version() -> RtcResult<Version> {
+    let mut major = 0;
+    let mut minor = 0;
+    unsafe {
+        let code = sys::hiprtcVersion(&mut major, &mut minor);
+        (Version::new(major as u64, minor as u64, 0), code).to_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCALE_SOURCE: &str = r#"
+        extern "C" __global__ void scale(float* data, float factor, unsigned n) {
+            unsigned i = blockIdx.x * blockDim.x + threadIdx.x;
+            if (i < n) data[i] *= factor;
+        }
+    "#;
+
+    #[test]
+    fn test_program_options() {
+        let program = Program::new("")
+            .with_offload_arch("gfx90a:xnack-")
+            .with_define("N", "256")
+            .with_define("DEBUG", "")
+            .with_options(["-O3", "-std=c++17"]);
+        assert_eq!(
+            program.options(),
+            &[
+                "--offload-arch=gfx90a:xnack-",
+                "-DN=256",
+                "-DDEBUG",
+                "-O3",
+                "-std=c++17"
+            ]
+        );
+        assert_eq!(program.name(), DEFAULT_PROGRAM_NAME);
+    }
+
+    #[test]
+    fn test_nul_byte_is_rejected() {
+        let error = Program::new("int x;\0").compile().unwrap_err();
+        assert_eq!(error.error.status, RtcStatus::InvalidInput);
+        assert!(error.log.is_empty());
+    }
+
+    #[test]
+    fn test_compile_error_display() {
+        let error = CompileError {
+            error: RtcError::from_status(RtcStatus::Compilation),
+            log: "program.hip:1:1: error: unknown type name 'foo'\n".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "HIPRTC status: Compilation (code: 6)\nprogram.hip:1:1: error: unknown type name 'foo'"
+        );
+    }
+
+    #[test]
+    fn test_compile_and_load() {
+        let compiled = Program::new(SCALE_SOURCE).compile().unwrap();
+        assert!(!compiled.code().is_empty());
+
+        let module = compiled.load().unwrap();
+        assert_eq!(module.function("scale").unwrap().name(), "scale");
+    }
+
+    #[test]
+    fn test_compile_error_log() {
+        let error = Program::new("__global__ void broken() { undefined_call(); }")
+            .with_name("broken.hip")
+            .compile()
+            .unwrap_err();
+        assert_eq!(error.error.status, RtcStatus::Compilation);
+        assert!(error.log.contains("broken.hip"));
+        assert!(error.log.contains("undefined_call"));
+    }
+
+    #[test]
+    fn test_headers_and_name_expressions() {
+        let source = r#"
+            #include "ops.h"
+            template <typename T>
+            __global__ void apply(T* data) { data[threadIdx.x] = op(data[threadIdx.x]); }
+        "#;
+        let compiled = Program::new(source)
+            .with_header(
+                "ops.h",
+                "template <typename T> __device__ T op(T x) { return x + x; }",
+            )
+            .with_name_expression("apply<float>")
+            .with_name_expression("apply<int>")
+            .compile()
+            .unwrap();
+
+        let float_name = compiled.lowered_name("apply<float>").unwrap();
+        let int_name = compiled.lowered_name("apply<int>").unwrap();
+        assert_ne!(float_name, int_name);
+        assert!(compiled.lowered_name("apply<double>").is_none());
+
+        let module = compiled.load().unwrap();
+        assert!(module.function(float_name).is_ok());
+    }
+
+    #[test]
+    fn test_rtc_version() {
+        let version = rtc_version().unwrap();
+        assert!(version.major > 0);
+    }
+}
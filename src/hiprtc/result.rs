@@ -0,0 +1,140 @@
+use crate::result::{ResultExt, StatusCode};
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtcStatus {
+    Success = 0,
+    OutOfMemory = 1,
+    ProgramCreationFailure = 2,
+    InvalidInput = 3,
+    InvalidProgram = 4,
+    InvalidOption = 5,
+    Compilation = 6,
+    BuiltinOperationFailure = 7,
+    NoNameExpressionsAfterCompilation = 8,
+    NoLoweredNamesBeforeCompilation = 9,
+    NameExpressionNotValid = 10,
+    InternalError = 11,
+    Linking = 100,
+    Unknown = 999,
+}
+
+impl RtcStatus {
+    fn from(status: u32) -> Self {
+        match status {
+            0 => RtcStatus::Success,
+            1 => RtcStatus::OutOfMemory,
+            2 => RtcStatus::ProgramCreationFailure,
+            3 => RtcStatus::InvalidInput,
+            4 => RtcStatus::InvalidProgram,
+            5 => RtcStatus::InvalidOption,
+            6 => RtcStatus::Compilation,
+            7 => RtcStatus::BuiltinOperationFailure,
+            8 => RtcStatus::NoNameExpressionsAfterCompilation,
+            9 => RtcStatus::NoLoweredNamesBeforeCompilation,
+            10 => RtcStatus::NameExpressionNotValid,
+            11 => RtcStatus::InternalError,
+            100 => RtcStatus::Linking,
+            _ => RtcStatus::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RtcError {
+    pub status: RtcStatus,
+    pub code: u32,
+}
+
+impl RtcError {
+    pub fn new(code: u32) -> Self {
+        Self {
+            status: RtcStatus::from(code),
+            code,
+        }
+    }
+
+    pub fn from_status(status: RtcStatus) -> Self {
+        Self {
+            status,
+            code: status as u32,
+        }
+    }
+}
+
+impl StatusCode for RtcError {
+    fn is_success(&self) -> bool {
+        self.status == RtcStatus::Success
+    }
+
+    fn code(&self) -> u32 {
+        self.code
+    }
+
+    fn kind_str(&self) -> &'static str {
+        "HIPRTC"
+    }
+
+    fn status_str(&self) -> &'static str {
+        match self.status {
+            RtcStatus::Success => "Success",
+            RtcStatus::OutOfMemory => "OutOfMemory",
+            RtcStatus::ProgramCreationFailure => "ProgramCreationFailure",
+            RtcStatus::InvalidInput => "InvalidInput",
+            RtcStatus::InvalidProgram => "InvalidProgram",
+            RtcStatus::InvalidOption => "InvalidOption",
+            RtcStatus::Compilation => "Compilation",
+            RtcStatus::BuiltinOperationFailure => "BuiltinOperationFailure",
+            RtcStatus::NoNameExpressionsAfterCompilation => "NoNameExpressionsAfterCompilation",
+            RtcStatus::NoLoweredNamesBeforeCompilation => "NoLoweredNamesBeforeCompilation",
+            RtcStatus::NameExpressionNotValid => "NameExpressionNotValid",
+            RtcStatus::InternalError => "InternalError",
+            RtcStatus::Linking => "Linking",
+            RtcStatus::Unknown => "Unknown",
+        }
+    }
+}
+
+pub type RtcResult<T> = std::result::Result<T, RtcError>;
+
+impl<T> ResultExt<T, RtcError> for (T, u32) {
+    type Value = T;
+    fn to_result(self) -> RtcResult<T> {
+        let (value, status) = self;
+        (value, RtcError::new(status)).to_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtc_status_from() {
+        assert_eq!(RtcStatus::from(0), RtcStatus::Success);
+        assert_eq!(RtcStatus::from(3), RtcStatus::InvalidInput);
+        assert_eq!(RtcStatus::from(6), RtcStatus::Compilation);
+        assert_eq!(RtcStatus::from(11), RtcStatus::InternalError);
+        assert_eq!(RtcStatus::from(100), RtcStatus::Linking);
+        assert_eq!(RtcStatus::from(12), RtcStatus::Unknown);
+    }
+
+    #[test]
+    fn test_rtc_error_status_code() {
+        let error = RtcError::new(6);
+        assert!(!error.is_success());
+        assert_eq!(error.status, RtcStatus::Compilation);
+        assert_eq!(error.kind_str(), "HIPRTC");
+        assert_eq!(error.status_str(), "Compilation");
+        assert!(RtcError::from_status(RtcStatus::Success).is_success());
+    }
+
+    #[test]
+    fn test_result_ext() {
+        let success: RtcResult<i32> = (42, 0).to_result();
+        assert_eq!(success.unwrap(), 42);
+
+        let error: RtcResult<i32> = (42, 5).to_result();
+        assert_eq!(error.unwrap_err().status, RtcStatus::InvalidOption);
+    }
+}
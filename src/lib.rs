@@ -1,10 +1,17 @@
 #![allow(non_upper_case_globals)]
+// Lets derive macros refer to `::hip_rs` inside this crate
+extern crate self as hip_rs;
+
+mod code_object;
 mod core;
 mod hipblas;
+mod hiprtc;
 mod result;
 pub mod sys;
 
+pub use code_object::*;
 pub use core::*;
 pub use hipblas::*;
+pub use hiprtc::*;
 pub use result::*;
 pub use sys::*;
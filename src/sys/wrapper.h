@@ -1,2 +1,3 @@
 #include <hip/hip_runtime.h>
+#include <hip/hiprtc.h>
 #include <hipblas/hipblas.h>